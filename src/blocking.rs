@@ -0,0 +1,305 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! This module provides the blocking client wrapping the async [`DbClient`].
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use tokio::runtime::{Builder as RuntimeBuilder, Handle, Runtime};
+
+use crate::{
+    db_client::DbClient,
+    model::{
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse,
+        },
+        value::{DataType, TimestampMs},
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+/// Client driving the requests of the wrapped [`DbClient`] to completion on a
+/// tokio runtime, for the users not running in an async context.
+///
+/// Note that the methods of it will panic if called in an async context.
+pub struct BlockingClient {
+    inner: Arc<dyn DbClient>,
+    // Only set when the runtime is owned by the client.
+    runtime: Option<Runtime>,
+    handle: Handle,
+}
+
+#[allow(clippy::result_large_err)]
+impl BlockingClient {
+    /// Wrap the `inner` client with an owned single-threaded runtime.
+    pub fn new(inner: Arc<dyn DbClient>) -> Result<Self> {
+        let runtime = RuntimeBuilder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Client(format!("failed to build runtime, err:{e}")))?;
+        let handle = runtime.handle().clone();
+
+        Ok(Self {
+            inner,
+            runtime: Some(runtime),
+            handle,
+        })
+    }
+
+    /// Wrap the `inner` client with the runtime provided by the `handle`.
+    ///
+    /// The runtime should be a multi-threaded one, because the io and the
+    /// timers of a single-threaded runtime are not driven while blocking on
+    /// its handle.
+    pub fn with_handle(inner: Arc<dyn DbClient>, handle: Handle) -> Self {
+        Self {
+            inner,
+            runtime: None,
+            handle,
+        }
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        // Only the owned runtime itself drives its io and timers.
+        match &self.runtime {
+            Some(runtime) => runtime.block_on(future),
+            None => self.handle.block_on(future),
+        }
+    }
+
+    pub fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.block_on(self.inner.sql_query(ctx, req))
+    }
+
+    pub fn sql_query_into(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        buf: &mut SqlQueryResponse,
+    ) -> Result<()> {
+        self.block_on(self.inner.sql_query_into(ctx, req, buf))
+    }
+
+    pub fn sql_query_multi(
+        &self,
+        ctx: &RpcContext,
+        reqs: &[SqlQueryRequest],
+        concurrency: usize,
+    ) -> Vec<Result<SqlQueryResponse>> {
+        self.block_on(self.inner.sql_query_multi(ctx, reqs, concurrency))
+    }
+
+    pub fn sql_query_best_effort(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        deadline: Duration,
+    ) -> Result<SqlQueryResponse> {
+        self.block_on(self.inner.sql_query_best_effort(ctx, req, deadline))
+    }
+
+    pub fn sql_query_page(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        page_size: usize,
+        cursor: Option<&str>,
+    ) -> Result<(SqlQueryResponse, Option<String>)> {
+        self.block_on(self.inner.sql_query_page(ctx, req, page_size, cursor))
+    }
+
+    pub fn explain(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.block_on(self.inner.explain(ctx, req))
+    }
+
+    pub fn explain_with(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        keyword: &str,
+    ) -> Result<SqlQueryResponse> {
+        self.block_on(self.inner.explain_with(ctx, req, keyword))
+    }
+
+    pub fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        self.block_on(self.inner.sql_query_raw(ctx, req))
+    }
+
+    pub fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        self.block_on(self.inner.write(ctx, req))
+    }
+
+    pub fn try_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryResponse> {
+        self.block_on(self.inner.try_sql_query(ctx, req))
+    }
+
+    pub fn try_write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        self.block_on(self.inner.try_write(ctx, req))
+    }
+
+    pub fn write_batch(&self, ctx: &RpcContext, reqs: Vec<WriteRequest>) -> Result<WriteResponse> {
+        self.block_on(self.inner.write_batch(ctx, reqs))
+    }
+
+    pub fn write_chunked(
+        &self,
+        ctx: &RpcContext,
+        req: &WriteRequest,
+        max_bytes: usize,
+    ) -> Result<WriteResponse> {
+        self.block_on(self.inner.write_chunked(ctx, req, max_bytes))
+    }
+
+    pub fn write_series(
+        &self,
+        ctx: &RpcContext,
+        table: &str,
+        tags: &[(&str, &str)],
+        points: &[(TimestampMs, f64)],
+    ) -> Result<WriteResponse> {
+        self.block_on(self.inner.write_series(ctx, table, tags, points))
+    }
+
+    pub fn list_tables(&self, ctx: &RpcContext) -> Result<Vec<String>> {
+        self.block_on(self.inner.list_tables(ctx))
+    }
+
+    pub fn describe_table(&self, ctx: &RpcContext, table: &str) -> Result<Vec<(String, DataType)>> {
+        self.block_on(self.inner.describe_table(ctx, table))
+    }
+
+    pub fn server_version(&self, ctx: &RpcContext) -> Result<String> {
+        self.block_on(self.inner.server_version(ctx))
+    }
+
+    pub fn endpoint(&self) -> Option<&str> {
+        self.inner.endpoint()
+    }
+
+    pub fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        self.block_on(self.inner.health_check(ctx))
+    }
+
+    pub fn connect(&self) -> Result<()> {
+        self.block_on(self.inner.connect())
+    }
+}
+
+impl Drop for BlockingClient {
+    fn drop(&mut self) {
+        // Dropping the runtime directly will panic if it happens in an async
+        // context.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+
+    use super::BlockingClient;
+    use crate::{
+        db_client::DbClient,
+        model::{
+            sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
+            write::{Request as WriteRequest, Response as WriteResponse},
+        },
+        rpc_client::RpcContext,
+        Result,
+    };
+
+    struct AsyncClient;
+
+    #[async_trait]
+    impl DbClient for AsyncClient {
+        async fn sql_query(
+            &self,
+            _ctx: &RpcContext,
+            _req: &SqlQueryRequest,
+        ) -> Result<SqlQueryResponse> {
+            // Depend on the timers of the runtime.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            Ok(SqlQueryResponse {
+                affected_rows: 1,
+                ..Default::default()
+            })
+        }
+
+        async fn write(&self, _ctx: &RpcContext, _req: &WriteRequest) -> Result<WriteResponse> {
+            tokio::task::yield_now().await;
+            Ok(WriteResponse::new(2, 0))
+        }
+
+        async fn health_check(&self, _ctx: &RpcContext) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn make_sql_query_request() -> SqlQueryRequest {
+        SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "insert into test(t, v) values(1, 1)".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_owned_runtime() {
+        let client = BlockingClient::new(Arc::new(AsyncClient)).unwrap();
+        let ctx = RpcContext::default();
+
+        let resp = client.sql_query(&ctx, &make_sql_query_request()).unwrap();
+        assert_eq!(resp.affected_rows, 1);
+        let resp = client.write(&ctx, &WriteRequest::default()).unwrap();
+        assert_eq!(resp.success, 2);
+        client.health_check(&ctx).unwrap();
+    }
+
+    #[test]
+    fn test_provided_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let client = BlockingClient::with_handle(Arc::new(AsyncClient), runtime.handle().clone());
+        let ctx = RpcContext::default();
+
+        let resp = client.sql_query(&ctx, &make_sql_query_request()).unwrap();
+        assert_eq!(resp.affected_rows, 1);
+    }
+
+    #[tokio::test]
+    async fn test_drop_in_async_context() {
+        let client = BlockingClient::new(Arc::new(AsyncClient)).unwrap();
+        drop(client);
+    }
+}
@@ -17,18 +17,23 @@
 
 use std::time::Duration;
 
+use crate::{util::StatusCode, Error, Result};
+
 /// Config for the underlying grpc client
 #[derive(Debug, Clone)]
 pub struct RpcConfig {
-    /// Thread num used by the grpc client.
+    /// Unused, kept for compatibility.
     ///
-    /// The number of cpu cores will be used if not set.
+    /// The grpc client runs on the tokio runtime of the caller instead of its
+    /// own threads, so the clients built in one runtime already share the
+    /// threads.
     pub thread_num: Option<usize>,
     /// The max length of the message sent to server.
     ///
     /// -1 means unlimited, and the default value is 20MB.
     pub max_send_msg_len: i32,
-    /// The max length of the message received from server.
+    /// The max length of the message received from server, and the query
+    /// responses exceeding it fail with `Error::Client`.
     ///
     /// -1 means unlimited, and the default value is 1GB.
     pub max_recv_msg_len: i32,
@@ -57,6 +62,243 @@ pub struct RpcConfig {
     ///
     /// Default value is 3s.
     pub connect_timeout: Duration,
+    /// The number of the connections to every endpoint.
+    ///
+    /// The requests are sent through the connections in turn, and default
+    /// value is 1.
+    pub channel_pool_size: usize,
+    /// Timeout for health check.
+    ///
+    /// Default value is 3s.
+    pub default_health_check_timeout: Duration,
+    /// The max number of the queries and writes in flight, and the exceeding
+    /// ones wait until the earlier ones finish.
+    ///
+    /// It must not be zero, and it is unlimited by default.
+    pub max_inflight: Option<usize>,
+    /// The max number of the rows in the response of a non-streaming query,
+    /// and the exceeding one fails with `Error::Client("row limit exceeded")`
+    /// before the rows are built.
+    ///
+    /// The streaming queries are not limited, and it is unlimited by default.
+    pub max_response_rows: Option<usize>,
+    /// Fail the write with [`Error::PartialWrite`] if some rows are written but
+    /// the others fail, instead of returning them in the
+    /// [`WriteResponse`](crate::WriteResponse).
+    ///
+    /// It is disabled by default.
+    pub fail_on_partial_write: bool,
+    /// Tolerate the record batches of a query response, or of every response
+    /// of a streaming query, whose columns differ from the ones of its first
+    /// batch, instead of failing with [`ConvertError::ColumnCountMismatch`].
+    ///
+    /// The missing trailing columns are padded with [`Value::Null`], the
+    /// extra trailing columns are ignored, and a warning is logged then. It is
+    /// disabled by default.
+    ///
+    /// [`ConvertError::ColumnCountMismatch`]: crate::ConvertError::ColumnCountMismatch
+    /// [`Value::Null`]: crate::model::value::Value::Null
+    pub permissive_decode: bool,
+    /// Log a warning with the sql, the database and the elapsed time of every
+    /// `sql_query` or `write` taking longer than it.
+    ///
+    /// The streaming queries are not logged, and it is disabled by default.
+    pub slow_query_threshold: Option<Duration>,
+    /// The max number of the attempts to reconnect to an endpoint after a
+    /// request to it fails with `Unavailable`, 100ms apart.
+    ///
+    /// The connection to the endpoint is rebuilt on the next request, which
+    /// fails with [`Error::Connect`] if none of the attempts succeeds, and
+    /// the next one tries again. Default value is 3.
+    pub max_reconnect_attempts: usize,
+    /// Compression for the messages sent to and received from server.
+    ///
+    /// Compression saves the bandwidth for large writes and queries at the cost
+    /// of cpu and some latency on both sides, and it is disabled by default.
+    pub compression: Compression,
+    /// Load balancing among the addresses which the endpoint resolves to.
+    ///
+    /// The first reachable address is used by default.
+    pub load_balancing: LoadBalancing,
+    /// Connect to the server over TLS if set.
+    ///
+    /// The plaintext connection is used by default.
+    #[cfg(feature = "tls")]
+    pub tls: Option<TlsConfig>,
+}
+
+#[allow(clippy::result_large_err)]
+impl RpcConfig {
+    /// Check the config, and it is called before connecting to the server.
+    ///
+    /// The connect timeout must not be zero, and the keep alive timeout must
+    /// be shorter than the keep alive interval if keep alive is enabled,
+    /// otherwise the next ping may be sent before the last one times out. And
+    /// the max inflight must not be zero, otherwise no request could be sent.
+    pub fn validate(&self) -> Result<()> {
+        if self.connect_timeout.is_zero() {
+            return Err(Error::Client(
+                "Connect timeout must not be zero".to_string(),
+            ));
+        }
+        if self.keep_alive_while_idle && self.keep_alive_timeout >= self.keep_alive_interval {
+            return Err(Error::Client(format!(
+                "Keep alive timeout must be shorter than keep alive interval, timeout:{:?}, interval:{:?}",
+                self.keep_alive_timeout, self.keep_alive_interval
+            )));
+        }
+        if self.max_inflight == Some(0) {
+            return Err(Error::Client("Max inflight must not be zero".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Compression algorithm for the grpc messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+}
+
+/// Load balancing policy among the addresses of one endpoint, e.g. the replicas
+/// behind a DNS name returning multiple records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Connect to the first reachable address.
+    #[default]
+    PickFirst,
+    /// Resolve the endpoint once when the client is built, and spread the
+    /// requests among all the addresses by the power of two choices.
+    ///
+    /// The connections are made lazily, so the unreachable addresses fail the
+    /// requests instead of the build.
+    Balanced,
+}
+
+/// Config for the TLS connection to the server.
+///
+/// The certificates and the key in PEM format are checked when they are set,
+/// so that the malformed ones are found before any connection is made.
+#[cfg(feature = "tls")]
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    pub(crate) ca_cert: Option<Vec<u8>>,
+    pub(crate) identity: Option<(Vec<u8>, Vec<u8>)>,
+    pub(crate) domain_name: Option<String>,
+}
+
+#[cfg(feature = "tls")]
+#[allow(clippy::result_large_err)]
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify the server by the CA certificate instead of the native roots of
+    /// the system.
+    pub fn ca_cert_pem(mut self, pem: impl Into<Vec<u8>>) -> Result<Self> {
+        let pem = pem.into();
+        check_cert_pem(&pem)?;
+        self.ca_cert = Some(pem);
+        Ok(self)
+    }
+
+    /// Authenticate the client to the server by the certificate and the
+    /// private key, that is to say, the mutual TLS.
+    pub fn identity_pem(
+        mut self,
+        cert_pem: impl Into<Vec<u8>>,
+        key_pem: impl Into<Vec<u8>>,
+    ) -> Result<Self> {
+        let (cert_pem, key_pem) = (cert_pem.into(), key_pem.into());
+        check_cert_pem(&cert_pem)?;
+        check_key_pem(&key_pem)?;
+        self.identity = Some((cert_pem, key_pem));
+        Ok(self)
+    }
+
+    /// Override the name used to verify the server certificate, which is the
+    /// host of the endpoint by default.
+    pub fn domain_name(mut self, domain_name: impl Into<String>) -> Self {
+        self.domain_name = Some(domain_name.into());
+        self
+    }
+}
+
+#[cfg(feature = "tls")]
+#[allow(clippy::result_large_err)]
+fn check_cert_pem(pem: &[u8]) -> Result<()> {
+    match rustls_pemfile::certs(&mut &pem[..]) {
+        Ok(certs) if !certs.is_empty() => Ok(()),
+        Ok(_) => Err(Error::Client("No certificate found in pem".to_string())),
+        Err(e) => Err(Error::Client(format!(
+            "Failed to parse certificate pem, err:{e}"
+        ))),
+    }
+}
+
+#[cfg(feature = "tls")]
+#[allow(clippy::result_large_err)]
+fn check_key_pem(pem: &[u8]) -> Result<()> {
+    // Only the keys supported by tonic are accepted.
+    let has_key = rustls_pemfile::pkcs8_private_keys(&mut &pem[..])
+        .map(|keys| !keys.is_empty())
+        .unwrap_or(false)
+        || rustls_pemfile::rsa_private_keys(&mut &pem[..])
+            .map(|keys| !keys.is_empty())
+            .unwrap_or(false);
+    if !has_key {
+        return Err(Error::Client(
+            "No pkcs8 or rsa private key found in pem".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Config for retrying the failed requests.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// The max number of retries after the first attempt.
+    ///
+    /// Default value is 3.
+    pub max_retries: usize,
+    /// The backoff before the first retry.
+    ///
+    /// Default value is 100ms.
+    pub initial_backoff: Duration,
+    /// The upper bound of the backoff.
+    ///
+    /// Default value is 5s.
+    pub max_backoff: Duration,
+    /// The backoff will be multiplied by it after every retry.
+    ///
+    /// The one below 1.0 or NaN is taken as 1.0, and default value is 2.0.
+    pub multiplier: f64,
+    /// The codes of the [`Error::Server`](crate::Error::Server) which can be
+    /// retried.
+    ///
+    /// Only the codes meaning that the request is rejected as a whole should be
+    /// set here, because writes will be retried on them too. Default value is
+    /// `[429]`.
+    pub retryable_server_codes: Vec<u32>,
+}
+
+/// Config for the [`WriteBuffer`](crate::WriteBuffer).
+#[derive(Debug, Clone)]
+pub struct WriteBufferConfig {
+    /// The buffered points are flushed once their number reaches it.
+    ///
+    /// Default value is 1000.
+    pub max_rows: usize,
+    /// The buffered points are flushed at this interval even if `max_rows` is
+    /// not reached.
+    ///
+    /// It is at least 1ms, and default value is 1s.
+    pub flush_interval: Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +321,85 @@ impl Default for RpcConfig {
             default_write_timeout: Duration::from_secs(5),
             default_sql_query_timeout: Duration::from_secs(60),
             connect_timeout: Duration::from_secs(3),
+            channel_pool_size: 1,
+            default_health_check_timeout: Duration::from_secs(3),
+            max_inflight: None,
+            max_response_rows: None,
+            fail_on_partial_write: false,
+            permissive_decode: false,
+            slow_query_threshold: None,
+            max_reconnect_attempts: 3,
+            compression: Compression::None,
+            load_balancing: LoadBalancing::PickFirst,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+impl Default for WriteBufferConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 1000,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            retryable_server_codes: vec![StatusCode::TooManyRequests.as_u32()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::RpcConfig;
+    use crate::Error;
+
+    #[test]
+    fn test_validate_rpc_config() {
+        assert!(RpcConfig::default().validate().is_ok());
+
+        let invalid_configs = [
+            RpcConfig {
+                connect_timeout: Duration::ZERO,
+                ..Default::default()
+            },
+            RpcConfig {
+                keep_alive_interval: Duration::from_secs(3),
+                keep_alive_timeout: Duration::from_secs(3),
+                ..Default::default()
+            },
+            RpcConfig {
+                keep_alive_interval: Duration::from_secs(1),
+                keep_alive_timeout: Duration::from_secs(3),
+                ..Default::default()
+            },
+            RpcConfig {
+                max_inflight: Some(0),
+                ..Default::default()
+            },
+        ];
+        for config in invalid_configs {
+            assert!(matches!(config.validate(), Err(Error::Client(_))));
         }
+
+        // The keep alive settings are unused if it is disabled.
+        let config = RpcConfig {
+            keep_alive_while_idle: false,
+            keep_alive_interval: Duration::from_secs(1),
+            keep_alive_timeout: Duration::from_secs(3),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
     }
 }
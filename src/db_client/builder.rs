@@ -15,12 +15,19 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
+#[cfg(feature = "metrics")]
+use crate::metrics::ClientMetrics;
 use crate::{
-    db_client::{raw::RawImpl, route_based::RouteBasedImpl, DbClient},
-    rpc_client::RpcClientImplFactory,
-    Authorization, RpcConfig,
+    blocking::BlockingClient,
+    db_client::{
+        failover::FailoverClient, inner::InnerConfig, limit::ConcurrencyLimitedClient,
+        raw::RawImpl, retry::RetryableClient, route_based::RouteBasedImpl, DbClient,
+    },
+    model::route::Endpoint,
+    rpc_client::{RpcClientImplFactory, TokenCache, TokenProvider},
+    Authorization, Error, Result, RetryConfig, RpcConfig,
 };
 
 /// Access mode to HoraeDB server(s).
@@ -36,25 +43,48 @@ pub enum Mode {
     Proxy,
 }
 
+const DEFAULT_FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
 /// The builder for building [`DbClient`](DbClient).
 #[derive(Debug, Clone)]
 pub struct Builder {
     mode: Mode,
     endpoint: String,
+    failover_endpoints: Vec<String>,
+    failover_cooldown: Duration,
     default_database: Option<String>,
+    default_query_tables: Vec<String>,
     rpc_config: RpcConfig,
     authorization: Option<Authorization>,
+    client_id: Option<String>,
+    token_cache: Option<Arc<TokenCache>>,
+    retry_config: Option<RetryConfig>,
+    route_cache_ttl: Option<Duration>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<ClientMetrics>,
 }
 
+#[allow(clippy::result_large_err)]
 impl Builder {
     // We hide this detail new method for the convenience of users.
+    /// The `endpoint` is in the form of `host:port` or `[ipv6]:port`, and the
+    /// `http://` or `grpc://` scheme is stripped.
     pub fn new(endpoint: String, mode: Mode) -> Self {
         Self {
             mode,
             endpoint,
+            failover_endpoints: Vec::new(),
+            failover_cooldown: DEFAULT_FAILOVER_COOLDOWN,
             rpc_config: RpcConfig::default(),
             default_database: None,
+            default_query_tables: Vec::new(),
             authorization: None,
+            client_id: None,
+            token_cache: None,
+            retry_config: None,
+            route_cache_ttl: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
         }
     }
 
@@ -64,6 +94,17 @@ impl Builder {
         self
     }
 
+    /// The `tables` are set into the [`SqlQueryRequest`]s without any table at
+    /// call time, e.g. for routing them in the `Direct` mode, and the ones with
+    /// tables are kept.
+    ///
+    /// [`SqlQueryRequest`]: crate::SqlQueryRequest
+    #[inline]
+    pub fn default_query_tables(mut self, tables: Vec<String>) -> Self {
+        self.default_query_tables = tables;
+        self
+    }
+
     #[inline]
     pub fn rpc_config(mut self, rpc_config: RpcConfig) -> Self {
         self.rpc_config = rpc_config;
@@ -76,23 +117,244 @@ impl Builder {
         self
     }
 
+    /// Identify the client to the server by the `x-horaedb-client` header,
+    /// which is `horaedb-client/<version>` by default.
+    #[inline]
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Authorize the requests by the tokens of the `provider`, which are
+    /// cached for the `ttl` and shared by all the connections.
+    ///
+    /// It takes precedence over the [`authorization`](Builder::authorization).
+    #[inline]
+    pub fn token_provider(mut self, provider: Arc<dyn TokenProvider>, ttl: Duration) -> Self {
+        self.token_cache = Some(Arc::new(TokenCache::new(provider, ttl)));
+        self
+    }
+
+    /// Retry the failed requests according to the `retry_config`.
+    ///
+    /// The requests are not retried by default.
+    #[inline]
+    pub fn retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = Some(retry_config);
+        self
+    }
+
+    /// Refetch the cached routes older than `route_cache_ttl`, only used in
+    /// the `Direct` mode.
+    ///
+    /// The cached routes are kept until they are found outdated by default.
+    #[inline]
+    pub fn route_cache_ttl(mut self, route_cache_ttl: Duration) -> Self {
+        self.route_cache_ttl = Some(route_cache_ttl);
+        self
+    }
+
+    /// Fail over to the `endpoints` in order when the endpoint set in
+    /// [`Builder::new`] is unreachable, see [`FailoverClient`] for details.
+    ///
+    /// No failover is done by default.
+    #[inline]
+    pub fn failover_endpoints(mut self, endpoints: Vec<String>) -> Self {
+        self.failover_endpoints = endpoints;
+        self
+    }
+
+    /// The unreachable endpoints are skipped for the `cooldown`.
+    ///
+    /// Default value is 30s.
+    #[inline]
+    pub fn failover_cooldown(mut self, cooldown: Duration) -> Self {
+        self.failover_cooldown = cooldown;
+        self
+    }
+
+    /// Record the metrics of the operations into `metrics`, whose registry is
+    /// provided by the user.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn metrics(mut self, metrics: ClientMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     pub fn build(self) -> Arc<dyn DbClient> {
-        let rpc_client_factory = Arc::new(RpcClientImplFactory::new(
-            self.rpc_config,
-            self.authorization,
-        ));
-
-        match self.mode {
-            Mode::Direct => Arc::new(RouteBasedImpl::new(
-                rpc_client_factory,
-                self.endpoint,
-                self.default_database,
-            )),
-            Mode::Proxy => Arc::new(RawImpl::new(
-                rpc_client_factory,
-                self.endpoint,
-                self.default_database,
-            )),
+        let max_inflight = self.rpc_config.max_inflight;
+        let inner_config = InnerConfig::from(&self.rpc_config);
+        let rpc_client_factory = RpcClientImplFactory::new(self.rpc_config, self.authorization)
+            .with_client_id(self.client_id)
+            .with_token_cache(self.token_cache);
+        #[cfg(feature = "metrics")]
+        let rpc_client_factory = rpc_client_factory.with_metrics(self.metrics);
+        let rpc_client_factory = Arc::new(rpc_client_factory);
+
+        let build_client = |endpoint: String| -> Arc<dyn DbClient> {
+            // The malformed endpoint is kept as it is, and fails at the connection.
+            let endpoint = normalize_endpoint(&endpoint).unwrap_or(endpoint);
+            match self.mode {
+                Mode::Direct => Arc::new(
+                    RouteBasedImpl::new(
+                        rpc_client_factory.clone(),
+                        endpoint,
+                        self.default_database.clone(),
+                        self.route_cache_ttl,
+                        inner_config,
+                    )
+                    .with_default_tables(self.default_query_tables.clone()),
+                ),
+                Mode::Proxy => Arc::new(
+                    RawImpl::new(
+                        rpc_client_factory.clone(),
+                        endpoint,
+                        self.default_database.clone(),
+                        inner_config,
+                    )
+                    .with_default_tables(self.default_query_tables.clone()),
+                ),
+            }
+        };
+        let client = if self.failover_endpoints.is_empty() {
+            build_client(self.endpoint)
+        } else {
+            let clients = std::iter::once(self.endpoint)
+                .chain(self.failover_endpoints)
+                .map(build_client)
+                .collect();
+            // Never fails with the endpoint included.
+            let client = FailoverClient::new(clients, self.failover_cooldown).unwrap();
+            Arc::new(client)
+        };
+
+        let client: Arc<dyn DbClient> = match self.retry_config {
+            Some(retry_config) => Arc::new(RetryableClient::new(client, retry_config)),
+            None => client,
+        };
+
+        // The outermost, so that the retries of a request take up one place only.
+        match max_inflight {
+            Some(max_inflight) => Arc::new(ConcurrencyLimitedClient::new(client, max_inflight)),
+            None => client,
+        }
+    }
+
+    /// Same as [`build`](Builder::build), but check the endpoints and the
+    /// [`RpcConfig`] first so that the invalid ones fail here instead of at the
+    /// first request.
+    pub fn try_build(self) -> Result<Arc<dyn DbClient>> {
+        for endpoint in std::iter::once(&self.endpoint).chain(&self.failover_endpoints) {
+            normalize_endpoint(endpoint)?;
+        }
+        self.rpc_config.validate()?;
+        Ok(self.build())
+    }
+
+    /// Same as [`try_build`](Builder::try_build), but connect to the server
+    /// before returning the client, see [`DbClient::connect`].
+    pub async fn build_connected(self) -> Result<Arc<dyn DbClient>> {
+        let client = self.try_build()?;
+        client.connect().await?;
+        Ok(client)
+    }
+
+    /// Build the [`BlockingClient`] with an owned single-threaded runtime.
+    pub fn build_blocking(self) -> Result<BlockingClient> {
+        BlockingClient::new(self.build())
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn normalize_endpoint(endpoint: &str) -> Result<String> {
+    endpoint
+        .parse::<Endpoint>()
+        .map(|endpoint| endpoint.to_string())
+        .map_err(|e| Error::Client(format!("Invalid endpoint:{endpoint}, err:{e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{Builder, Mode};
+    use crate::{Error, RetryConfig, RpcConfig};
+
+    #[test]
+    fn test_try_build() {
+        let builder = Builder::new("127.0.0.1:8831".to_string(), Mode::Proxy);
+        assert!(builder.clone().try_build().is_ok());
+
+        let rpc_config = RpcConfig {
+            keep_alive_timeout: Duration::from_secs(700),
+            ..Default::default()
+        };
+        let res = builder.rpc_config(rpc_config).try_build();
+        assert!(matches!(res, Err(Error::Client(_))));
+    }
+
+    #[test]
+    fn test_endpoint() {
+        for mode in [Mode::Proxy, Mode::Direct] {
+            let client = Builder::new("127.0.0.1:8831".to_string(), mode).build();
+            assert_eq!(client.endpoint(), Some("127.0.0.1:8831"));
+        }
+
+        // The wrapping clients return the endpoint of the primary one.
+        let client = Builder::new("127.0.0.1:8831".to_string(), Mode::Proxy)
+            .failover_endpoints(vec!["127.0.0.1:8832".to_string()])
+            .retry_config(RetryConfig::default())
+            .rpc_config(RpcConfig {
+                max_inflight: Some(1),
+                ..Default::default()
+            })
+            .build();
+        assert_eq!(client.endpoint(), Some("127.0.0.1:8831"));
+    }
+
+    #[tokio::test]
+    async fn test_build_connected() {
+        // Find a closed port.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        for mode in [Mode::Proxy, Mode::Direct] {
+            let start = Instant::now();
+            let res = Builder::new(endpoint.clone(), mode)
+                .failover_endpoints(vec![endpoint.clone()])
+                .retry_config(RetryConfig::default())
+                .build_connected()
+                .await;
+            assert!(matches!(res, Err(Error::Connect { .. })));
+            // Refused at once instead of waiting for the connect timeout.
+            assert!(start.elapsed() < RpcConfig::default().connect_timeout);
+        }
+    }
+
+    #[test]
+    fn test_normalize_endpoint() {
+        for mode in [Mode::Proxy, Mode::Direct] {
+            let client = Builder::new("http://127.0.0.1:8831".to_string(), mode.clone())
+                .try_build()
+                .unwrap();
+            assert_eq!(client.endpoint(), Some("127.0.0.1:8831"));
+            let client = Builder::new("grpc://[::1]:8831/".to_string(), mode)
+                .try_build()
+                .unwrap();
+            assert_eq!(client.endpoint(), Some("[::1]:8831"));
+        }
+
+        let invalid_builders = [
+            Builder::new("127.0.0.1".to_string(), Mode::Proxy),
+            Builder::new("::1:8831".to_string(), Mode::Proxy),
+            Builder::new("https://127.0.0.1:8831".to_string(), Mode::Direct),
+            Builder::new("127.0.0.1:8831".to_string(), Mode::Proxy)
+                .failover_endpoints(vec!["127.0.0.1:port".to_string()]),
+        ];
+        for builder in invalid_builders {
+            assert!(matches!(builder.try_build(), Err(Error::Client(_))));
         }
     }
 }
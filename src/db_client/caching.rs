@@ -0,0 +1,518 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use crate::{
+    db_client::DbClient,
+    model::{
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Result,
+};
+
+/// The database, the sql and the tables of the cached query.
+type CacheKey = (Option<String>, String, Vec<String>);
+
+struct CacheEntry {
+    resp: SqlQueryResponse,
+    expire_at: Instant,
+    /// The tick of the latest access, and the least recently used entry has
+    /// the smallest one.
+    last_access: u64,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// The keys of the entries by their latest access ticks.
+    lru: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    /// Bumped by every invalidation, so that the responses of the queries
+    /// sent before it are not cached.
+    generation: u64,
+}
+
+/// Client caching the responses of the [`DbClient::sql_query`]s of the
+/// wrapped [`DbClient`], e.g. for the dashboards issuing the same queries
+/// repeatedly.
+///
+/// The responses are cached by the database, the sql and the tables of the
+/// queries for the `ttl`, and the least recently used one is evicted if there
+/// are more than `max_entries`. The failed queries are never cached, and
+/// neither are the sqls other than the reads, i.e. `SELECT`, `SHOW`,
+/// `DESCRIBE`, `EXPLAIN` and `WITH`, which are sent to the wrapped client
+/// without being counted in the hits or misses.
+///
+/// A write, or a non-read sql, invalidates the cached responses of the queries
+/// on the written tables, and of the queries without the tables since the
+/// tables they touch are unknown. A non-read sql without the tables invalidates
+/// all of them. The streaming and raw queries are never cached.
+pub struct CachingClient {
+    inner: Arc<dyn DbClient>,
+    ttl: Duration,
+    max_entries: usize,
+    cache: Mutex<Cache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CachingClient {
+    pub fn new(inner: Arc<dyn DbClient>, ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            inner,
+            ttl,
+            max_entries,
+            cache: Mutex::new(Cache::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of the queries served by the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of the queries sent to the wrapped client, because they are
+    /// not cached or expired.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// The cached response of the `key`, or the current generation to
+    /// [`put`](Self::put) the response of the query.
+    fn get(&self, key: &CacheKey) -> std::result::Result<SqlQueryResponse, u64> {
+        let mut cache = self.cache.lock().unwrap();
+        let cache = &mut *cache;
+        cache.tick += 1;
+        let tick = cache.tick;
+        match cache.entries.get_mut(key) {
+            Some(entry) if entry.expire_at > Instant::now() => {
+                cache.lru.remove(&entry.last_access);
+                cache.lru.insert(tick, key.clone());
+                entry.last_access = tick;
+                Ok(entry.resp.clone())
+            }
+            Some(entry) => {
+                cache.lru.remove(&entry.last_access);
+                cache.entries.remove(key);
+                Err(cache.generation)
+            }
+            None => Err(cache.generation),
+        }
+    }
+
+    /// Cache the response unless it is invalidated since the `generation`.
+    fn put(&self, key: CacheKey, resp: SqlQueryResponse, generation: u64) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        let cache = &mut *cache;
+        if cache.generation != generation {
+            return;
+        }
+
+        match cache.entries.get(&key) {
+            Some(entry) => {
+                cache.lru.remove(&entry.last_access);
+            }
+            None if cache.entries.len() >= self.max_entries => {
+                if let Some((_, lru)) = cache.lru.pop_first() {
+                    cache.entries.remove(&lru);
+                }
+            }
+            None => {}
+        }
+
+        cache.tick += 1;
+        let entry = CacheEntry {
+            resp,
+            expire_at: Instant::now() + self.ttl,
+            last_access: cache.tick,
+        };
+        cache.lru.insert(cache.tick, key.clone());
+        cache.entries.insert(key, entry);
+    }
+
+    fn invalidate(&self, is_written: impl Fn(&str) -> bool) {
+        let mut cache = self.cache.lock().unwrap();
+        let cache = &mut *cache;
+        cache.generation += 1;
+        cache.entries.retain(|(_, _, tables), _| {
+            !tables.is_empty() && tables.iter().all(|table| !is_written(table))
+        });
+        let entries = &cache.entries;
+        cache.lru.retain(|_, key| entries.contains_key(key));
+    }
+}
+
+/// The first keywords of the sqls reading only, whose responses can be cached.
+const READ_KEYWORDS: [&str; 6] = ["SELECT", "SHOW", "DESCRIBE", "DESC", "EXPLAIN", "WITH"];
+
+fn is_read(sql: &str) -> bool {
+    let first_word = sql
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    READ_KEYWORDS
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(first_word))
+}
+
+#[async_trait]
+impl DbClient for CachingClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        if !is_read(&req.sql) {
+            // Invalidated even if the sql fails, since it may be partially done.
+            let resp = self.inner.sql_query(ctx, req).await;
+            if req.tables.is_empty() {
+                self.invalidate(|_| true);
+            } else {
+                self.invalidate(|table| req.tables.iter().any(|written| written == table));
+            }
+            return resp;
+        }
+
+        let key = (ctx.database.clone(), req.sql.clone(), req.tables.clone());
+        let generation = match self.get(&key) {
+            Ok(resp) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(resp);
+            }
+            Err(generation) => generation,
+        };
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let resp = self.inner.sql_query(ctx, req).await?;
+        if !resp.is_mutation() {
+            self.put(key, resp.clone(), generation);
+        }
+        Ok(resp)
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        self.inner.stream_sql_query(ctx, req).await
+    }
+
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        self.inner.sql_query_raw(ctx, req).await
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        // Invalidated even if the write fails, since it may be partially done.
+        let resp = self.inner.write(ctx, req).await;
+        self.invalidate(|table| req.point_groups.contains_key(table));
+        resp
+    }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        self.inner.health_check(ctx).await
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    fn endpoint(&self) -> Option<&str> {
+        self.inner.endpoint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use super::CachingClient;
+    use crate::{
+        db_client::DbClient,
+        model::{
+            sql_query::{
+                row::{Column, Row},
+                Request as SqlQueryRequest, Response as SqlQueryResponse,
+            },
+            value::Value,
+            write::{point::PointBuilder, Request as WriteRequest, Response as WriteResponse},
+        },
+        rpc_client::RpcContext,
+        Result,
+    };
+
+    /// Client answering the number of the queries sent to it so far, or the
+    /// affected rows for the sqls other than `SELECT`.
+    #[derive(Default)]
+    struct CountingClient {
+        queries: AtomicUsize,
+        /// The delay of every query.
+        delay: Duration,
+    }
+
+    impl CountingClient {
+        fn queries(&self) -> usize {
+            self.queries.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl DbClient for CountingClient {
+        async fn sql_query(
+            &self,
+            _ctx: &RpcContext,
+            req: &SqlQueryRequest,
+        ) -> Result<SqlQueryResponse> {
+            let queries = self.queries.fetch_add(1, Ordering::SeqCst) + 1;
+            if !self.delay.is_zero() {
+                tokio::time::sleep(self.delay).await;
+            }
+            if !req.sql.starts_with("SELECT") {
+                return Ok(SqlQueryResponse {
+                    affected_rows: 1,
+                    ..Default::default()
+                });
+            }
+
+            let row = Row::new(vec![Column::new(
+                "n".to_string(),
+                Value::UInt64(queries as u64),
+            )]);
+            Ok(SqlQueryResponse::with_rows(vec![row]))
+        }
+
+        async fn write(&self, _ctx: &RpcContext, _req: &WriteRequest) -> Result<WriteResponse> {
+            Ok(WriteResponse::new(1, 0))
+        }
+    }
+
+    fn make_query(table: &str) -> SqlQueryRequest {
+        SqlQueryRequest {
+            tables: vec![table.to_string()],
+            sql: format!("SELECT * FROM {table}"),
+        }
+    }
+
+    fn make_write(table: &str) -> WriteRequest {
+        let point = PointBuilder::new(table.to_string())
+            .timestamp(1000)
+            .field("value".to_string(), Value::Int64(1))
+            .build()
+            .unwrap();
+        let mut req = WriteRequest::default();
+        req.add_point(point);
+        req
+    }
+
+    fn queried_n(resp: &SqlQueryResponse) -> &Value {
+        resp.rows[0].get("n").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit() {
+        let inner = Arc::new(CountingClient::default());
+        let client = CachingClient::new(inner.clone(), Duration::from_secs(60), 2);
+        let ctx = RpcContext::default().database("public".to_string());
+
+        let resp = client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(queried_n(&resp), &Value::UInt64(1));
+        let resp = client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(queried_n(&resp), &Value::UInt64(1));
+        assert_eq!((client.hits(), client.misses()), (1, 1));
+
+        // Another database is cached separately.
+        let other_ctx = RpcContext::default().database("other".to_string());
+        client
+            .sql_query(&other_ctx, &make_query("cpu"))
+            .await
+            .unwrap();
+        assert_eq!(inner.queries(), 2);
+
+        // Evicts the least recently used query of `other_ctx`.
+        client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        client.sql_query(&ctx, &make_query("mem")).await.unwrap();
+        client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(inner.queries(), 3);
+        client
+            .sql_query(&other_ctx, &make_query("cpu"))
+            .await
+            .unwrap();
+        assert_eq!(inner.queries(), 4);
+        assert_eq!((client.hits(), client.misses()), (3, 4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_expiry() {
+        let inner = Arc::new(CountingClient::default());
+        let client = CachingClient::new(inner.clone(), Duration::from_secs(60), 16);
+        let ctx = RpcContext::default();
+
+        client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        tokio::time::advance(Duration::from_secs(59)).await;
+        client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(inner.queries(), 1);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let resp = client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(queried_n(&resp), &Value::UInt64(2));
+        assert_eq!((client.hits(), client.misses()), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_cache_invalidated_by_write() {
+        let inner = Arc::new(CountingClient::default());
+        let client = CachingClient::new(inner.clone(), Duration::from_secs(60), 16);
+        let ctx = RpcContext::default();
+        let without_tables = SqlQueryRequest {
+            tables: vec![],
+            sql: "SELECT 1".to_string(),
+        };
+
+        client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        client.sql_query(&ctx, &make_query("mem")).await.unwrap();
+        client.sql_query(&ctx, &without_tables).await.unwrap();
+        assert_eq!(inner.queries(), 3);
+
+        client.write(&ctx, &make_write("cpu")).await.unwrap();
+        client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        client.sql_query(&ctx, &make_query("mem")).await.unwrap();
+        client.sql_query(&ctx, &without_tables).await.unwrap();
+        assert_eq!(inner.queries(), 5);
+        assert_eq!((client.hits(), client.misses()), (1, 5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_during_query() {
+        let inner = Arc::new(CountingClient {
+            delay: Duration::from_secs(1),
+            ..Default::default()
+        });
+        let client = CachingClient::new(inner.clone(), Duration::from_secs(60), 16);
+        let ctx = RpcContext::default();
+
+        // The response read before the write is not cached.
+        let write = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            client.write(&ctx, &make_write("cpu")).await.unwrap();
+        };
+        let req = make_query("cpu");
+        let (resp, _) = futures::join!(client.sql_query(&ctx, &req), write);
+        assert_eq!(queried_n(&resp.unwrap()), &Value::UInt64(1));
+
+        let resp = client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(queried_n(&resp), &Value::UInt64(2));
+        let resp = client.sql_query(&ctx, &make_query("cpu")).await.unwrap();
+        assert_eq!(queried_n(&resp), &Value::UInt64(2));
+        assert_eq!((client.hits(), client.misses()), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_mutation_not_cached() {
+        let inner = Arc::new(CountingClient::default());
+        let client = CachingClient::new(inner.clone(), Duration::from_secs(60), 16);
+        let ctx = RpcContext::default();
+        let insert = SqlQueryRequest {
+            tables: vec!["cpu".to_string()],
+            sql: "INSERT INTO cpu(t, value) VALUES(1, 1)".to_string(),
+        };
+
+        for _ in 0..2 {
+            let resp = client.sql_query(&ctx, &insert).await.unwrap();
+            assert_eq!(resp.affected_rows, 1);
+        }
+        assert_eq!(inner.queries(), 2);
+        assert_eq!((client.hits(), client.misses()), (0, 0));
+
+        // The read responding no result set is not cached either.
+        let show = SqlQueryRequest {
+            tables: vec![],
+            sql: "SHOW CREATE TABLE cpu".to_string(),
+        };
+        client.sql_query(&ctx, &show).await.unwrap();
+        client.sql_query(&ctx, &show).await.unwrap();
+        assert_eq!(inner.queries(), 4);
+    }
+
+    /// Query `cpu`, `mem` and without the tables.
+    async fn query_all(client: &CachingClient, ctx: &RpcContext) {
+        let without_tables = SqlQueryRequest {
+            tables: vec![],
+            sql: "SELECT 1".to_string(),
+        };
+        client.sql_query(ctx, &make_query("cpu")).await.unwrap();
+        client.sql_query(ctx, &make_query("mem")).await.unwrap();
+        client.sql_query(ctx, &without_tables).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_invalidated_by_sql() {
+        let inner = Arc::new(CountingClient::default());
+        let client = CachingClient::new(inner.clone(), Duration::from_secs(60), 16);
+        let ctx = RpcContext::default();
+
+        query_all(&client, &ctx).await;
+        assert_eq!(inner.queries(), 3);
+
+        let delete = SqlQueryRequest {
+            tables: vec!["cpu".to_string()],
+            sql: " delete FROM cpu WHERE t < 1".to_string(),
+        };
+        client.sql_query(&ctx, &delete).await.unwrap();
+        query_all(&client, &ctx).await;
+        // The delete, `cpu` and the one without the tables.
+        assert_eq!(inner.queries(), 6);
+
+        let drop = SqlQueryRequest {
+            tables: vec![],
+            sql: "DROP TABLE mem".to_string(),
+        };
+        client.sql_query(&ctx, &drop).await.unwrap();
+        query_all(&client, &ctx).await;
+        assert_eq!(inner.queries(), 10);
+    }
+}
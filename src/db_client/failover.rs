@@ -0,0 +1,228 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use tonic::Code;
+
+use crate::{
+    db_client::DbClient,
+    model::{
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+/// Client failing over among the wrapped [`DbClient`]s of different
+/// endpoints, for the deployments without a load balancer in front.
+///
+/// The requests are sent to the first healthy client in order. A client is
+/// marked unhealthy for the cooldown once it is found unreachable, that is to
+/// say, the request fails with the [`Unavailable`](Code::Unavailable) grpc
+/// code, and the request is sent to the next one then. The unhealthy clients
+/// are still tried at last if all the clients are unhealthy.
+pub struct FailoverClient {
+    clients: Vec<Arc<dyn DbClient>>,
+    unhealthy_until: Vec<Mutex<Option<Instant>>>,
+    cooldown: Duration,
+}
+
+#[allow(clippy::result_large_err)]
+impl FailoverClient {
+    /// Fail over among the `clients` in order, and
+    /// [`Error::Client`](crate::Error::Client) is returned if there is none.
+    pub fn new(clients: Vec<Arc<dyn DbClient>>, cooldown: Duration) -> Result<Self> {
+        if clients.is_empty() {
+            return Err(Error::Client("No clients to fail over".to_string()));
+        }
+
+        let unhealthy_until = clients.iter().map(|_| Mutex::new(None)).collect();
+        Ok(Self {
+            clients,
+            unhealthy_until,
+            cooldown,
+        })
+    }
+
+    async fn failover<'a, T, Fut>(&'a self, op: impl Fn(&'a dyn DbClient) -> Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let now = Instant::now();
+        let (healthy, unhealthy): (Vec<_>, Vec<_>) = (0..self.clients.len()).partition(|idx| {
+            match *self.unhealthy_until[*idx].lock().unwrap() {
+                Some(until) => until <= now,
+                None => true,
+            }
+        });
+
+        let mut last_err = None;
+        for idx in healthy.into_iter().chain(unhealthy) {
+            match op(self.clients[idx].as_ref()).await {
+                Err(e) if e.grpc_code() == Some(Code::Unavailable) => {
+                    *self.unhealthy_until[idx].lock().unwrap() =
+                        Some(Instant::now() + self.cooldown);
+                    last_err = Some(e);
+                }
+                result => {
+                    *self.unhealthy_until[idx].lock().unwrap() = None;
+                    return result;
+                }
+            }
+        }
+
+        Err(last_err.unwrap())
+    }
+}
+
+#[async_trait]
+impl DbClient for FailoverClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.failover(|client| client.sql_query(ctx, req)).await
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        // Only the establishment of the stream can fail over.
+        self.failover(|client| client.stream_sql_query(ctx, req))
+            .await
+    }
+
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        self.failover(|client| client.sql_query_raw(ctx, req)).await
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        self.failover(|client| client.write(ctx, req)).await
+    }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        self.failover(|client| client.health_check(ctx)).await
+    }
+
+    /// Connect the clients in order until one of them succeeds.
+    async fn connect(&self) -> Result<()> {
+        let mut last_err = None;
+        for client in &self.clients {
+            match client.connect().await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_err = Some(e),
+            }
+        }
+
+        Err(last_err.unwrap())
+    }
+
+    /// The endpoint of the primary client, i.e. the first one.
+    fn endpoint(&self) -> Option<&str> {
+        self.clients[0].endpoint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use super::FailoverClient;
+    use crate::{
+        db_client::{scripted::ScriptedClient, DbClient},
+        model::write::Request as WriteRequest,
+        rpc_client::RpcContext,
+        Error,
+    };
+
+    /// Client of the endpoint, which is unreachable if it is `down`.
+    fn make_endpoint_client(down: bool) -> Arc<ScriptedClient> {
+        let client = ScriptedClient::default();
+        if !down {
+            return Arc::new(client);
+        }
+        Arc::new(client.with_write_error(|| Error::Connect {
+            addr: "down:8831".to_string(),
+            source: "connection refused".into(),
+        }))
+    }
+
+    /// The number of the writes sent to the `client`.
+    fn sent_writes(client: &ScriptedClient) -> usize {
+        client.contexts.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn test_failover_when_first_down() {
+        let (down, up) = (make_endpoint_client(true), make_endpoint_client(false));
+        let client =
+            FailoverClient::new(vec![down.clone(), up.clone()], Duration::from_millis(50)).unwrap();
+        let (ctx, req) = (RpcContext::default(), WriteRequest::default());
+
+        assert!(client.write(&ctx, &req).await.is_ok());
+        assert_eq!(sent_writes(&down), 1);
+        assert_eq!(sent_writes(&up), 1);
+
+        // The down endpoint is skipped during the cooldown.
+        assert!(client.write(&ctx, &req).await.is_ok());
+        assert_eq!(sent_writes(&down), 1);
+
+        // And it is tried again after the cooldown.
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(client.write(&ctx, &req).await.is_ok());
+        assert_eq!(sent_writes(&down), 2);
+        assert_eq!(sent_writes(&up), 3);
+    }
+
+    #[tokio::test]
+    async fn test_all_down() {
+        let (first, second) = (make_endpoint_client(true), make_endpoint_client(true));
+        let client =
+            FailoverClient::new(vec![first.clone(), second.clone()], Duration::from_secs(60))
+                .unwrap();
+        let (ctx, req) = (RpcContext::default(), WriteRequest::default());
+
+        for _ in 0..2 {
+            let err = client.write(&ctx, &req).await.unwrap_err();
+            assert!(matches!(err, Error::Connect { .. }));
+        }
+        // The unhealthy ones are still tried.
+        assert_eq!(sent_writes(&first), 2);
+        assert_eq!(sent_writes(&second), 2);
+    }
+
+    #[test]
+    fn test_no_clients() {
+        assert!(matches!(
+            FailoverClient::new(vec![], Duration::from_secs(60)),
+            Err(Error::Client(_))
+        ));
+    }
+}
@@ -15,43 +15,173 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
 
+use futures::{stream, StreamExt, TryStreamExt};
 use horaedbproto::storage;
-use tokio::sync::OnceCell;
+use tokio::{sync::Mutex, time::Instant};
 
 use crate::{
     model::{
-        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
         write::{Request as WriteRequest, Response as WriteResponse, WriteTableRequestPbsBuilder},
     },
     rpc_client::{RpcClient, RpcClientFactory, RpcContext},
-    Result,
+    Error, Result, RpcConfig,
 };
 
+/// The backoff between the attempts to reconnect to a broken endpoint.
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The options of the [`InnerClient`] taken from the [`RpcConfig`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct InnerConfig {
+    pub max_response_rows: Option<usize>,
+    pub permissive_decode: bool,
+    pub slow_query_threshold: Option<Duration>,
+    pub max_reconnect_attempts: usize,
+}
+
+impl From<&RpcConfig> for InnerConfig {
+    fn from(rpc_config: &RpcConfig) -> Self {
+        Self {
+            max_response_rows: rpc_config.max_response_rows,
+            permissive_decode: rpc_config.permissive_decode,
+            slow_query_threshold: rpc_config.slow_query_threshold,
+            max_reconnect_attempts: rpc_config.max_reconnect_attempts,
+        }
+    }
+}
+
 /// Inner client for both standalone and route based modes.
 ///
-/// Now, [`InnerClient`] just wraps [`RpcClient`] simply.
+/// Now, [`InnerClient`] just wraps [`RpcClient`] simply, and rebuilds it on
+/// the next call after it fails with `Unavailable`.
 pub(crate) struct InnerClient<F: RpcClientFactory> {
     factory: Arc<F>,
     endpoint: String,
-    inner_client: OnceCell<Arc<dyn RpcClient>>,
+    config: InnerConfig,
+    inner_client: RwLock<Option<Arc<dyn RpcClient>>>,
+    /// Serializes the builds of the client.
+    build_lock: Mutex<()>,
+    /// Whether the last built client is dropped for being broken.
+    broken: AtomicBool,
 }
 
+#[allow(clippy::result_large_err)]
 impl<F: RpcClientFactory> InnerClient<F> {
-    pub fn new(factory: Arc<F>, endpoint: String) -> Self {
+    pub fn new(factory: Arc<F>, endpoint: String, config: InnerConfig) -> Self {
         InnerClient {
             factory,
             endpoint,
-            inner_client: OnceCell::new(),
+            config,
+            inner_client: RwLock::new(None),
+            build_lock: Mutex::new(()),
+            broken: AtomicBool::new(false),
         }
     }
 
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
     #[inline]
     async fn init(&self) -> Result<Arc<dyn RpcClient>> {
         self.factory.build(self.endpoint.clone()).await
     }
 
+    #[inline]
+    fn cached_client(&self) -> Option<Arc<dyn RpcClient>> {
+        self.inner_client.read().unwrap().clone()
+    }
+
+    /// Get the built client, or build it if it is not built yet or dropped for
+    /// being broken.
+    async fn client(&self) -> Result<Arc<dyn RpcClient>> {
+        if let Some(client) = self.cached_client() {
+            return Ok(client);
+        }
+
+        let _guard = self.build_lock.lock().await;
+        // It may be built by another call while waiting for the lock.
+        if let Some(client) = self.cached_client() {
+            return Ok(client);
+        }
+        let client = if self.broken.load(Ordering::Acquire) {
+            self.reconnect().await?
+        } else {
+            self.init().await?
+        };
+        self.broken.store(false, Ordering::Release);
+        *self.inner_client.write().unwrap() = Some(client.clone());
+
+        Ok(client)
+    }
+
+    /// Rebuild the broken client, and give up after
+    /// [`InnerConfig::max_reconnect_attempts`] attempts.
+    async fn reconnect(&self) -> Result<Arc<dyn RpcClient>> {
+        let attempts = self.config.max_reconnect_attempts.max(1);
+        let mut last_err = None;
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                tokio::time::sleep(RECONNECT_BACKOFF).await;
+            }
+            match self.init().await {
+                Ok(client) => return Ok(client),
+                Err(e) if e.grpc_code() == Some(tonic::Code::Unavailable) => last_err = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(Error::Connect {
+            addr: self.endpoint.clone(),
+            source: format!(
+                "endpoint is still down after {attempts} reconnect attempts, last err:{}",
+                last_err.unwrap()
+            )
+            .into(),
+        })
+    }
+
+    /// Drop the client if the call fails with `Unavailable`, so that the next
+    /// call reconnects instead of using the broken channel.
+    fn check_broken<T>(&self, client: &Arc<dyn RpcClient>, result: &Result<T>) {
+        let is_broken = matches!(
+            result,
+            Err(e) if e.grpc_code() == Some(tonic::Code::Unavailable)
+        );
+        if !is_broken {
+            return;
+        }
+
+        let mut cached = self.inner_client.write().unwrap();
+        // It may be rebuilt by another call already.
+        if cached
+            .as_ref()
+            .is_some_and(|cached| Arc::ptr_eq(cached, client))
+        {
+            *cached = None;
+            self.broken.store(true, Ordering::Release);
+        }
+    }
+
+    #[inline]
+    fn is_slow(&self, elapsed: Duration) -> bool {
+        self.config
+            .slow_query_threshold
+            .is_some_and(|threshold| elapsed > threshold)
+    }
+
     pub async fn sql_query_internal(
         &self,
         ctx: &RpcContext,
@@ -59,7 +189,54 @@ impl<F: RpcClientFactory> InnerClient<F> {
     ) -> Result<SqlQueryResponse> {
         assert!(ctx.database.is_some());
 
-        let client_handle = self.inner_client.get_or_try_init(|| self.init()).await?;
+        let query = async {
+            let client_handle = self.client().await?;
+            let req_ctx = storage::RequestContext {
+                database: ctx.database.clone().unwrap(),
+            };
+            let req_pb = storage::SqlQueryRequest {
+                context: Some(req_ctx),
+                tables: req.tables.clone(),
+                sql: req.sql.clone(),
+            };
+
+            let resp_pb = client_handle.as_ref().sql_query(ctx, req_pb).await;
+            self.check_broken(&client_handle, &resp_pb);
+            resp_pb.and_then(|resp_pb| {
+                SqlQueryResponse::from_pb(
+                    resp_pb,
+                    self.config.max_response_rows,
+                    self.config.permissive_decode,
+                )
+            })
+        };
+
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let resp = crate::trace::trace_sql_query(ctx, req, query).await;
+        #[cfg(not(feature = "tracing"))]
+        let resp = query.await;
+
+        let elapsed = start.elapsed();
+        if self.is_slow(elapsed) {
+            log::warn!(
+                "Slow sql query, database:{}, sql:{}, elapsed:{elapsed:?}",
+                ctx.database.as_deref().unwrap_or_default(),
+                req.sql
+            );
+        }
+
+        resp
+    }
+
+    pub async fn sql_query_raw_internal(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        assert!(ctx.database.is_some());
+
+        let client_handle = self.client().await?;
         let req_ctx = storage::RequestContext {
             database: ctx.database.clone().unwrap(),
         };
@@ -69,33 +246,529 @@ impl<F: RpcClientFactory> InnerClient<F> {
             sql: req.sql.clone(),
         };
 
-        client_handle
-            .as_ref()
-            .sql_query(ctx, req_pb)
-            .await
-            .and_then(SqlQueryResponse::try_from)
+        let resp_pb = client_handle.sql_query(ctx, req_pb).await;
+        self.check_broken(&client_handle, &resp_pb);
+        resp_pb.and_then(SqlQueryRawResponse::try_from)
     }
 
-    pub async fn write_internal(
+    pub async fn stream_sql_query_internal(
         &self,
         ctx: &RpcContext,
-        req: &WriteRequest,
-    ) -> Result<WriteResponse> {
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
         assert!(ctx.database.is_some());
 
-        let client_handle = self.inner_client.get_or_try_init(|| self.init()).await?;
+        let client_handle = self.client().await?;
         let req_ctx = storage::RequestContext {
             database: ctx.database.clone().unwrap(),
         };
-        let write_table_request_pbs = WriteTableRequestPbsBuilder(req.clone()).build();
-        let req_pb = storage::WriteRequest {
+        let req_pb = storage::SqlQueryRequest {
             context: Some(req_ctx),
-            table_requests: write_table_request_pbs,
+            tables: req.tables.clone(),
+            sql: req.sql.clone(),
+        };
+
+        let resp_stream = client_handle.as_ref().stream_sql_query(ctx, req_pb).await;
+        self.check_broken(&client_handle, &resp_stream);
+        let resp_stream = resp_stream?;
+        let permissive_decode = self.config.permissive_decode;
+        let row_stream = resp_stream
+            .and_then(move |resp_pb| async move {
+                // The streaming queries are not limited by the max response rows.
+                let resp = SqlQueryResponse::from_pb(resp_pb, None, permissive_decode)?;
+                Ok(stream::iter(resp.rows.into_iter().map(Ok)))
+            })
+            .try_flatten();
+
+        Ok(row_stream.boxed())
+    }
+
+    pub async fn write_internal(
+        &self,
+        ctx: &RpcContext,
+        req: &WriteRequest,
+    ) -> Result<WriteResponse> {
+        assert!(ctx.database.is_some());
+
+        let write = async {
+            let client_handle = self.client().await?;
+            let req_ctx = storage::RequestContext {
+                database: ctx.database.clone().unwrap(),
+            };
+            let write_table_request_pbs = WriteTableRequestPbsBuilder(req.clone()).build();
+            let req_pb = storage::WriteRequest {
+                context: Some(req_ctx),
+                table_requests: write_table_request_pbs,
+            };
+
+            let resp_pb = client_handle.write(ctx, req_pb).await;
+            self.check_broken(&client_handle, &resp_pb);
+            resp_pb.map(|resp_pb| resp_pb.into())
         };
 
-        client_handle
-            .write(ctx, req_pb)
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let resp = crate::trace::trace_write(ctx, req, write).await;
+        #[cfg(not(feature = "tracing"))]
+        let resp = write.await;
+
+        let elapsed = start.elapsed();
+        if self.is_slow(elapsed) {
+            let points: usize = req.point_groups.values().map(|points| points.len()).sum();
+            log::warn!(
+                "Slow write, database:{}, tables:{}, points:{points}, elapsed:{elapsed:?}",
+                ctx.database.as_deref().unwrap_or_default(),
+                req.point_groups.len()
+            );
+        }
+
+        resp
+    }
+
+    pub async fn connect_internal(&self) -> Result<()> {
+        self.client().await?;
+        Ok(())
+    }
+
+    pub async fn health_check_internal(&self, ctx: &RpcContext) -> Result<()> {
+        let client_handle = self.client().await?;
+        let result = client_handle.health_check(ctx).await;
+        self.check_broken(&client_handle, &result);
+        result
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::result_large_err)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use arrow::{
+        array::{Int32Array, StringArray},
+        datatypes::{DataType, Field, Schema},
+        ipc::writer::StreamWriter,
+        record_batch::RecordBatch,
+    };
+    use async_trait::async_trait;
+    use futures::TryStreamExt;
+    use horaedbproto::storage::{
+        arrow_payload::Compression, sql_query_response::Output, ArrowPayload,
+        SqlQueryResponse as SqlQueryResponsePb,
+    };
+
+    use super::{InnerClient, InnerConfig};
+    use crate::{
+        errors::ServerError,
+        model::{
+            sql_query::{PayloadCompression, Request as SqlQueryRequest},
+            value::Value,
+            write::Request as WriteRequest,
+        },
+        rpc_client::{
+            MockRpcClient, MockRpcClientFactory, RpcClient, RpcClientFactory, RpcContext,
+        },
+        Error, Result,
+    };
+
+    fn make_arrow_response(values: Vec<i32>) -> SqlQueryResponsePb {
+        let schema = Arc::new(Schema::new(vec![Field::new("int", DataType::Int32, false)]));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))]).unwrap();
+        encode_arrow_response(&schema, &batch)
+    }
+
+    fn encode_arrow_response(schema: &Schema, batch: &RecordBatch) -> SqlQueryResponsePb {
+        let mut buf = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buf, schema).unwrap();
+            writer.write(batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        SqlQueryResponsePb {
+            header: None,
+            output: Some(Output::Arrow(ArrowPayload {
+                record_batches: vec![buf],
+                compression: Compression::None as i32,
+            })),
+        }
+    }
+
+    /// Factory of the rpc client streaming two responses and then a server
+    /// error, or responding one for the unary query.
+    ///
+    /// A batch with an extra column is appended to the first streamed response
+    /// if it is `mismatched`.
+    fn make_streaming_factory(mismatched: bool) -> Arc<MockRpcClientFactory> {
+        let client = MockRpcClient::default()
+            .with_query(|_, _| Ok(make_arrow_response(vec![1, 2])))
+            .with_stream_query(move |_, _| {
+                let mut first = make_arrow_response(vec![1, 2]);
+                if mismatched {
+                    let schema = Arc::new(Schema::new(vec![
+                        Field::new("int", DataType::Int32, false),
+                        Field::new("extra", DataType::Int32, false),
+                    ]));
+                    let column = Arc::new(Int32Array::from(vec![9]));
+                    let batch =
+                        RecordBatch::try_new(schema.clone(), vec![column.clone(), column]).unwrap();
+                    let Some(Output::Arrow(extra)) = encode_arrow_response(&schema, &batch).output
+                    else {
+                        unreachable!()
+                    };
+                    if let Some(Output::Arrow(payload)) = first.output.as_mut() {
+                        payload.record_batches.extend(extra.record_batches);
+                    }
+                }
+                vec![
+                    Ok(first),
+                    Ok(make_arrow_response(vec![3])),
+                    Err(Error::Server(ServerError {
+                        code: 500,
+                        msg: "internal error".to_string(),
+                        retry_after: None,
+                        request_id: None,
+                    })),
+                ]
+            });
+
+        Arc::new(MockRpcClientFactory {
+            client: Arc::new(client),
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_queries_of_databases() {
+        let databases = ["db_a", "db_b", "db_c"];
+        // Respond the databases in the context and in the request.
+        let rpc_client = MockRpcClient::default()
+            .with_delay(Duration::from_millis(10))
+            .with_query(|ctx, req| {
+                let schema = Arc::new(Schema::new(vec![
+                    Field::new("ctx_database", DataType::Utf8, true),
+                    Field::new("req_database", DataType::Utf8, true),
+                ]));
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(StringArray::from(vec![ctx.database.clone()])),
+                        Arc::new(StringArray::from(vec![req
+                            .context
+                            .as_ref()
+                            .map(|c| c.database.clone())])),
+                    ],
+                )
+                .unwrap();
+                Ok(encode_arrow_response(&schema, &batch))
+            });
+        let factory = MockRpcClientFactory {
+            client: Arc::new(rpc_client),
+        };
+        let client = InnerClient::new(
+            Arc::new(factory),
+            "test".to_string(),
+            InnerConfig::default(),
+        );
+        let req = SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        };
+
+        // All the queries are in flight at the same time because of the delay,
+        // and every one gets its own database.
+        let queries = databases.iter().map(|database| {
+            let ctx = RpcContext::default().database(database.to_string());
+            let (client, req) = (&client, &req);
+            async move { client.sql_query_internal(&ctx, req).await }
+        });
+        let resps = futures::future::try_join_all(queries).await.unwrap();
+        for (database, resp) in databases.iter().zip(resps) {
+            let expected = Value::String(database.to_string());
+            let row = &resp.rows[0];
+            assert_eq!(row.get("ctx_database"), Some(&expected));
+            assert_eq!(row.get("req_database"), Some(&expected));
+        }
+    }
+
+    /// Logger capturing the warnings, which is installed once for all the tests.
+    struct CapturingLogger {
+        warnings: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.warnings
+                    .lock()
+                    .unwrap()
+                    .push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        warnings: Mutex::new(Vec::new()),
+    };
+
+    /// The captured warnings containing the `pattern`, since the other tests
+    /// may log concurrently.
+    fn captured_warnings(pattern: &str) -> Vec<String> {
+        let warnings = LOGGER.warnings.lock().unwrap();
+        warnings
+            .iter()
+            .filter(|warning| warning.contains(pattern))
+            .cloned()
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_query_log() {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Warn);
+        }
+
+        let make_client = |delay| {
+            let rpc_client = MockRpcClient::default()
+                .with_delay(delay)
+                .with_query(|_, _| Ok(make_arrow_response(vec![1])));
+            InnerClient::new(
+                Arc::new(MockRpcClientFactory {
+                    client: Arc::new(rpc_client),
+                }),
+                "test".to_string(),
+                InnerConfig {
+                    slow_query_threshold: Some(Duration::from_millis(100)),
+                    ..Default::default()
+                },
+            )
+        };
+        let ctx = RpcContext::default().database("slow_db".to_string());
+        let make_req = |sql: &str| SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: sql.to_string(),
+        };
+
+        let client = make_client(Duration::from_millis(10));
+        client
+            .sql_query_internal(&ctx, &make_req("select fast_query"))
+            .await
+            .unwrap();
+        assert!(captured_warnings("fast_query").is_empty());
+
+        let client = make_client(Duration::from_millis(200));
+        client
+            .sql_query_internal(&ctx, &make_req("select slow_query"))
+            .await
+            .unwrap();
+        let warnings = captured_warnings("slow_query");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("database:slow_db"), "{}", warnings[0]);
+        assert!(warnings[0].contains("elapsed:"), "{}", warnings[0]);
+
+        client
+            .write_internal(&ctx, &WriteRequest::default())
             .await
-            .map(|resp_pb| resp_pb.into())
+            .unwrap();
+        assert_eq!(captured_warnings("Slow write, database:slow_db").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_sql_query() {
+        let client = InnerClient::new(
+            make_streaming_factory(false),
+            "test".to_string(),
+            InnerConfig::default(),
+        );
+        let ctx = RpcContext::default().database("public".to_string());
+        let req = SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        };
+
+        let mut stream = client.stream_sql_query_internal(&ctx, &req).await.unwrap();
+        for expected in [1, 2, 3] {
+            let row = stream.try_next().await.unwrap().unwrap();
+            assert_eq!(row.get("int"), Some(&Value::Int32(expected)));
+        }
+        assert!(matches!(
+            stream.try_next().await,
+            Err(Error::Server(ServerError { code: 500, .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_response_rows() {
+        let ctx = RpcContext::default().database("public".to_string());
+        let req = SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        };
+
+        let client = InnerClient::new(
+            make_streaming_factory(false),
+            "test".to_string(),
+            InnerConfig {
+                max_response_rows: Some(1),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            client.sql_query_internal(&ctx, &req).await,
+            Err(Error::Client(msg)) if msg == "row limit exceeded"
+        ));
+        // The streaming queries are not limited.
+        let mut stream = client.stream_sql_query_internal(&ctx, &req).await.unwrap();
+        for _ in 0..3 {
+            assert!(stream.try_next().await.unwrap().is_some());
+        }
+
+        let client = InnerClient::new(
+            make_streaming_factory(false),
+            "test".to_string(),
+            InnerConfig {
+                max_response_rows: Some(2),
+                ..Default::default()
+            },
+        );
+        let resp = client.sql_query_internal(&ctx, &req).await.unwrap();
+        assert_eq!(resp.rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_permissive_decode() {
+        let ctx = RpcContext::default().database("public".to_string());
+        let req = SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        };
+        let factory = make_streaming_factory(true);
+
+        let client = InnerClient::new(factory.clone(), "test".to_string(), InnerConfig::default());
+        let mut stream = client.stream_sql_query_internal(&ctx, &req).await.unwrap();
+        assert!(matches!(stream.try_next().await, Err(Error::Convert(_))));
+
+        let config = InnerConfig {
+            permissive_decode: true,
+            ..Default::default()
+        };
+        let client = InnerClient::new(factory, "test".to_string(), config);
+        let mut stream = client.stream_sql_query_internal(&ctx, &req).await.unwrap();
+        for expected in [1, 2, 9, 3] {
+            let row = stream.try_next().await.unwrap().unwrap();
+            assert_eq!(row.get("int"), Some(&Value::Int32(expected)));
+            assert_eq!(row.get("extra"), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_raw() {
+        let client = InnerClient::new(
+            make_streaming_factory(false),
+            "test".to_string(),
+            InnerConfig::default(),
+        );
+        let ctx = RpcContext::default().database("public".to_string());
+        let req = SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        };
+
+        let resp = client.sql_query_raw_internal(&ctx, &req).await.unwrap();
+        let Some(Output::Arrow(expected)) = make_arrow_response(vec![1, 2]).output else {
+            unreachable!()
+        };
+        assert_eq!(resp.record_batches, expected.record_batches);
+        assert_eq!(resp.compression, PayloadCompression::None);
+        assert_eq!(resp.affected_rows, 0);
+    }
+
+    /// Factory failing to connect while the endpoint is down.
+    struct FlakyRpcClientFactory {
+        down: Arc<AtomicBool>,
+        builds: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl RpcClientFactory for FlakyRpcClientFactory {
+        async fn build(&self, endpoint: String) -> Result<Arc<dyn RpcClient>> {
+            self.builds.fetch_add(1, Ordering::SeqCst);
+            if self.down.load(Ordering::SeqCst) {
+                return Err(Error::Connect {
+                    addr: endpoint,
+                    source: "connection refused".into(),
+                });
+            }
+            // Fail with `Unavailable` while the endpoint is down.
+            let down = self.down.clone();
+            let client = MockRpcClient::default().with_query(move |_, _| {
+                if down.load(Ordering::SeqCst) {
+                    return Err(Error::Rpc(tonic::Status::unavailable("channel is broken")));
+                }
+                Ok(make_arrow_response(vec![1]))
+            });
+            Ok(Arc::new(client))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_broken_channel() {
+        let down = Arc::new(AtomicBool::new(false));
+        let factory = Arc::new(FlakyRpcClientFactory {
+            down: down.clone(),
+            builds: AtomicUsize::new(0),
+        });
+        let client = InnerClient::new(
+            factory.clone(),
+            "test".to_string(),
+            InnerConfig {
+                max_reconnect_attempts: 3,
+                ..Default::default()
+            },
+        );
+        let ctx = RpcContext::default().database("public".to_string());
+        let req = SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        };
+
+        client.sql_query_internal(&ctx, &req).await.unwrap();
+        client.sql_query_internal(&ctx, &req).await.unwrap();
+        assert_eq!(factory.builds.load(Ordering::SeqCst), 1);
+
+        // The broken client is dropped, and the next call gives up after the
+        // bounded attempts.
+        down.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            client.sql_query_internal(&ctx, &req).await,
+            Err(Error::Rpc(status)) if status.code() == tonic::Code::Unavailable
+        ));
+        assert_eq!(factory.builds.load(Ordering::SeqCst), 1);
+        match client.sql_query_internal(&ctx, &req).await {
+            Err(Error::Connect { addr, source }) => {
+                assert_eq!(addr, "test");
+                assert!(source.to_string().contains("after 3 reconnect attempts"));
+            }
+            other => panic!("unexpected result:{other:?}"),
+        }
+        assert_eq!(factory.builds.load(Ordering::SeqCst), 4);
+
+        // Reconnected transparently once the endpoint recovers.
+        down.store(false, Ordering::SeqCst);
+        let resp = client.sql_query_internal(&ctx, &req).await.unwrap();
+        assert_eq!(resp.rows[0].get("int"), Some(&Value::Int32(1)));
+        client.sql_query_internal(&ctx, &req).await.unwrap();
+        assert_eq!(factory.builds.load(Ordering::SeqCst), 5);
     }
 }
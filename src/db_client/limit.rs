@@ -0,0 +1,199 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+
+use crate::{
+    db_client::DbClient,
+    model::{
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+/// Client limiting the number of the inflight queries and writes of the
+/// wrapped [`DbClient`], and the exceeding ones wait in a fifo queue.
+///
+/// A streaming query takes up its place until the stream is dropped, and the
+/// health checks are never limited.
+pub struct ConcurrencyLimitedClient {
+    inner: Arc<dyn DbClient>,
+    semaphore: Arc<Semaphore>,
+}
+
+#[allow(clippy::result_large_err)]
+impl ConcurrencyLimitedClient {
+    /// The `max_inflight` is at least 1, otherwise no request could be sent.
+    pub fn new(inner: Arc<dyn DbClient>, max_inflight: usize) -> Self {
+        Self {
+            inner,
+            semaphore: Arc::new(Semaphore::new(max_inflight.max(1))),
+        }
+    }
+
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        // The semaphore is never closed.
+        self.semaphore.clone().acquire_owned().await.unwrap()
+    }
+
+    fn try_acquire(&self) -> Result<OwnedSemaphorePermit> {
+        self.semaphore
+            .clone()
+            .try_acquire_owned()
+            .map_err(|e| match e {
+                TryAcquireError::NoPermits => Error::Client("too many inflight".to_string()),
+                TryAcquireError::Closed => unreachable!("the semaphore is never closed"),
+            })
+    }
+}
+
+#[async_trait]
+#[allow(clippy::result_large_err)]
+impl DbClient for ConcurrencyLimitedClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        let _permit = self.acquire().await;
+        self.inner.sql_query(ctx, req).await
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        let permit = self.acquire().await;
+        let stream = self.inner.stream_sql_query(ctx, req).await?;
+        let stream = stream.map(move |row| {
+            // Released when the stream is dropped.
+            let _permit = &permit;
+            row
+        });
+        Ok(stream.boxed())
+    }
+
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        let _permit = self.acquire().await;
+        self.inner.sql_query_raw(ctx, req).await
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        let _permit = self.acquire().await;
+        self.inner.write(ctx, req).await
+    }
+
+    async fn try_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryResponse> {
+        let _permit = self.try_acquire()?;
+        self.inner.sql_query(ctx, req).await
+    }
+
+    async fn try_write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        let _permit = self.try_acquire()?;
+        self.inner.write(ctx, req).await
+    }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        self.inner.health_check(ctx).await
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    fn endpoint(&self) -> Option<&str> {
+        self.inner.endpoint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{atomic::Ordering, Arc},
+        time::Duration,
+    };
+
+    use futures::future;
+
+    use super::ConcurrencyLimitedClient;
+    use crate::{
+        db_client::{scripted::ScriptedClient, DbClient},
+        model::write::Request as WriteRequest,
+        rpc_client::RpcContext,
+        Error,
+    };
+
+    /// Client writing in 20ms like a slow server.
+    fn make_slow_client() -> Arc<ScriptedClient> {
+        Arc::new(ScriptedClient::default().with_delay(Duration::from_millis(20)))
+    }
+
+    #[tokio::test]
+    async fn test_limit_inflight() {
+        let inner = make_slow_client();
+        let client = ConcurrencyLimitedClient::new(inner.clone(), 2);
+        let ctx = RpcContext::default();
+        let req = WriteRequest::default();
+
+        let writes = (0..6).map(|_| client.write(&ctx, &req));
+        let resps = future::join_all(writes).await;
+        assert!(resps.iter().all(|resp| resp.is_ok()));
+        assert_eq!(inner.max_inflight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_max_inflight() {
+        let inner = make_slow_client();
+        let client = ConcurrencyLimitedClient::new(inner.clone(), 0);
+        let ctx = RpcContext::default();
+
+        client.write(&ctx, &WriteRequest::default()).await.unwrap();
+        assert_eq!(inner.max_inflight.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_try_write_rejected() {
+        let inner = make_slow_client();
+        let client = ConcurrencyLimitedClient::new(inner.clone(), 1);
+        let ctx = RpcContext::default();
+        let req = WriteRequest::default();
+
+        let (first, second) = tokio::join!(client.write(&ctx, &req), async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            client.try_write(&ctx, &req).await
+        });
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(Error::Client(msg)) if msg == "too many inflight"));
+
+        // The permit is released after the first write.
+        assert!(client.try_write(&ctx, &req).await.is_ok());
+    }
+}
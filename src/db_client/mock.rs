@@ -0,0 +1,273 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! In-memory [`DbClient`] for the tests of the applications, enabled by the
+//! `test-util` feature.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use crate::{
+    db_client::DbClient,
+    model::{
+        sql_query::{
+            row::{Column, Row},
+            Request as SqlQueryRequest, Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        value::Value,
+        write::{point::Point, Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+const TIMESTAMP_COLUMN: &str = "timestamp";
+
+/// [`DbClient`] keeping the written points in memory.
+///
+/// The sql is not interpreted, and a query just scans all the points of the
+/// tables in the request, whose rows consist of the `timestamp` column, the tags
+/// and then the fields. The scans can be replaced by the canned results pushed
+/// by [`push_sql_query_result`], and the writes can be failed by the errors
+/// pushed by [`push_write_error`], both of which are consumed in order, one by
+/// each call.
+///
+/// [`push_sql_query_result`]: MockDbClient::push_sql_query_result
+/// [`push_write_error`]: MockDbClient::push_write_error
+#[derive(Debug, Default)]
+pub struct MockDbClient {
+    tables: Mutex<HashMap<String, Vec<Point>>>,
+    sql_query_results: Mutex<VecDeque<Result<SqlQueryResponse>>>,
+    write_errors: Mutex<VecDeque<Error>>,
+}
+
+#[allow(clippy::result_large_err)]
+impl MockDbClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next query with `result` instead of scanning the tables.
+    pub fn push_sql_query_result(&self, result: Result<SqlQueryResponse>) {
+        self.sql_query_results.lock().unwrap().push_back(result);
+    }
+
+    /// Fail the next write with `error`, and nothing is written by it.
+    pub fn push_write_error(&self, error: Error) {
+        self.write_errors.lock().unwrap().push_back(error);
+    }
+
+    /// The points written into the `table` in order.
+    pub fn points(&self, table: &str) -> Vec<Point> {
+        self.tables
+            .lock()
+            .unwrap()
+            .get(table)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn scan(&self, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        if let Some(result) = self.sql_query_results.lock().unwrap().pop_front() {
+            return result;
+        }
+
+        let tables = self.tables.lock().unwrap();
+        let rows = req
+            .tables
+            .iter()
+            .filter_map(|table| tables.get(table))
+            .flatten()
+            .map(point_to_row)
+            .collect::<Vec<_>>();
+        // The points may have different tags and fields, take the first one as the
+        // schema.
+        let (column_names, column_types): (Vec<_>, _) = rows
+            .first()
+            .map(|row: &Row| {
+                row.iter()
+                    .map(|(name, value)| (name.to_string(), value.data_type()))
+                    .unzip()
+            })
+            .unwrap_or_default();
+
+        Ok(SqlQueryResponse {
+            affected_rows: 0,
+            rows,
+            column_nullables: vec![true; column_names.len()],
+            column_names,
+            column_types,
+            truncated: false,
+        })
+    }
+}
+
+fn point_to_row(point: &Point) -> Row {
+    let mut columns = Vec::with_capacity(1 + point.tags.len() + point.fields.len());
+    columns.push(Column::new(
+        TIMESTAMP_COLUMN.to_string(),
+        Value::Timestamp(point.timestamp),
+    ));
+    for (name, value) in point.tags.iter().chain(point.fields.iter()) {
+        columns.push(Column::new(name.clone(), value.clone()));
+    }
+
+    Row::new(columns)
+}
+
+#[async_trait]
+impl DbClient for MockDbClient {
+    async fn sql_query(
+        &self,
+        _ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryResponse> {
+        self.scan(req)
+    }
+
+    async fn stream_sql_query(
+        &self,
+        _ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        let resp = self.scan(req)?;
+        Ok(stream::iter(resp.rows.into_iter().map(Ok)).boxed())
+    }
+
+    async fn write(&self, _ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        if let Some(e) = self.write_errors.lock().unwrap().pop_front() {
+            return Err(e);
+        }
+
+        let mut tables = self.tables.lock().unwrap();
+        let mut success = 0;
+        for (table, points) in &req.point_groups {
+            success += points.len() as u32;
+            tables
+                .entry(table.clone())
+                .or_default()
+                .extend(points.iter().cloned());
+        }
+
+        Ok(WriteResponse::new(success, 0))
+    }
+
+    async fn health_check(&self, _ctx: &RpcContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::TryStreamExt;
+
+    use super::MockDbClient;
+    use crate::{
+        db_client::DbClient,
+        errors::ServerError,
+        model::{
+            sql_query::Request as SqlQueryRequest,
+            value::Value,
+            write::{point::PointBuilder, Request as WriteRequest},
+        },
+        rpc_client::RpcContext,
+        Error, SqlQueryResponse,
+    };
+
+    fn make_write_request() -> WriteRequest {
+        let mut req = WriteRequest::default();
+        for ts in [1, 2] {
+            let point = PointBuilder::new("cpu")
+                .timestamp(ts)
+                .tag("host", Value::String("a".to_string()))
+                .field("usage", Value::Double(0.5))
+                .build()
+                .unwrap();
+            req.add_point(point);
+        }
+        req
+    }
+
+    fn make_sql_query_request() -> SqlQueryRequest {
+        SqlQueryRequest {
+            tables: vec!["cpu".to_string()],
+            sql: "select * from cpu".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_and_scan() {
+        let client = MockDbClient::new();
+        let ctx = RpcContext::default();
+
+        let resp = client.write(&ctx, &make_write_request()).await.unwrap();
+        assert_eq!(resp.success, 2);
+        assert_eq!(client.points("cpu").len(), 2);
+
+        let resp = client
+            .sql_query(&ctx, &make_sql_query_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.rows.len(), 2);
+        let row = &resp.rows[1];
+        let names: Vec<_> = row.columns().iter().map(|col| col.name()).collect();
+        assert_eq!(names, vec!["timestamp", "host", "usage"]);
+        assert_eq!(row.get("timestamp"), Some(&Value::Timestamp(2)));
+        assert_eq!(row.get("usage"), Some(&Value::Double(0.5)));
+
+        let rows: Vec<_> = client
+            .stream_sql_query(&ctx, &make_sql_query_request())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_injected_results() {
+        let client = MockDbClient::new();
+        let ctx = RpcContext::default();
+
+        client.push_sql_query_result(Ok(SqlQueryResponse {
+            affected_rows: 7,
+            ..Default::default()
+        }));
+        client.push_sql_query_result(Err(Error::Server(ServerError {
+            code: 500,
+            msg: "internal error".to_string(),
+            retry_after: None,
+            request_id: None,
+        })));
+        let req = make_sql_query_request();
+        assert_eq!(client.sql_query(&ctx, &req).await.unwrap().affected_rows, 7);
+        assert!(client.sql_query(&ctx, &req).await.is_err());
+        assert!(client.sql_query(&ctx, &req).await.unwrap().rows.is_empty());
+
+        client.push_write_error(Error::Client("forced".to_string()));
+        assert!(client.write(&ctx, &make_write_request()).await.is_err());
+        assert!(client.points("cpu").is_empty());
+        client.write(&ctx, &make_write_request()).await.unwrap();
+        assert_eq!(client.points("cpu").len(), 2);
+    }
+}
@@ -18,28 +18,551 @@
 //! This module provides the definition and implementations of the `DbClient`.
 
 mod builder;
+mod caching;
+mod failover;
 mod inner;
+mod limit;
+#[cfg(feature = "test-util")]
+mod mock;
 mod raw;
+mod retry;
 mod route_based;
+#[cfg(test)]
+mod scripted;
+mod sharded;
+mod write_buffer;
+
+use std::{borrow::Cow, time::Duration};
 
 use async_trait::async_trait;
 pub use builder::{Builder, Mode};
+pub use caching::CachingClient;
+pub use failover::FailoverClient;
+use futures::{
+    future::{self, Either},
+    stream, StreamExt, TryStreamExt,
+};
+pub use limit::ConcurrencyLimitedClient;
+#[cfg(feature = "test-util")]
+pub use mock::MockDbClient;
+pub use retry::RetryableClient;
+pub use sharded::ShardedWriteClient;
+use tokio_util::sync::CancellationToken;
+pub use write_buffer::WriteBuffer;
 
 use crate::{
     model::{
-        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
-        write::{Request as WriteRequest, Response as WriteResponse},
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        value::{DataType, TimestampMs, Value},
+        write::{point::PointBuilder, Request as WriteRequest, Response as WriteResponse},
     },
     rpc_client::RpcContext,
-    Result,
+    util::StatusCode,
+    Error, Result,
 };
 
+/// The field name of the values written by [`DbClient::write_series`].
+const SERIES_VALUE_FIELD: &str = "value";
+
 #[async_trait]
+#[allow(clippy::result_large_err)]
 pub trait DbClient: Send + Sync {
     async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse>;
+    /// Query by the sql, and return the rows as a stream instead of buffering
+    /// all of them in memory.
+    ///
+    /// By default the rows are fetched by [`sql_query`](DbClient::sql_query)
+    /// and streamed afterwards, so they are still buffered in memory.
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        let resp = self.sql_query(ctx, req).await?;
+        Ok(stream::iter(resp.rows.into_iter().map(Ok)).boxed())
+    }
     async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse>;
+    /// Query by the sql, but return the arrow payload as sent by the server
+    /// without decoding it into rows.
+    ///
+    /// Only the clients built by the [`Builder`] support it, and
+    /// `Error::Client` is returned by the others by default.
+    async fn sql_query_raw(
+        &self,
+        _ctx: &RpcContext,
+        _req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        Err(Error::Client("Raw sql query is not supported".to_string()))
+    }
+    /// Same as [`sql_query`](DbClient::sql_query) but with the default
+    /// [`RpcContext`], that is to say, the default database set in the
+    /// [`Builder`] and the default timeout are used.
+    async fn sql_query_default(&self, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.sql_query(&RpcContext::default(), req).await
+    }
+    /// Same as [`sql_query`](DbClient::sql_query), but the query is aborted
+    /// once the `cancel` is cancelled, and `Error::Client("cancelled")` is
+    /// returned then.
+    ///
+    /// The underlying grpc call is dropped when aborted, which resets its
+    /// stream so that the server can stop it too.
+    async fn sql_query_with_cancel(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        cancel: CancellationToken,
+    ) -> Result<SqlQueryResponse> {
+        let query = self.sql_query(ctx, req);
+        let cancelled = cancel.cancelled();
+        futures::pin_mut!(query, cancelled);
+        match future::select(cancelled, query).await {
+            Either::Left(_) => Err(Error::Client("cancelled".to_string())),
+            Either::Right((resp, _)) => resp,
+        }
+    }
+    /// Same as [`sql_query`](DbClient::sql_query), but the rows arrived before
+    /// the `deadline` are returned with [`SqlQueryResponse::truncated`] set
+    /// instead of failing once it expires, e.g. for the dashboards.
+    ///
+    /// [`Error::Timeout`] is still returned if no rows arrive before the
+    /// `deadline`, e.g. the server is unreachable, since there is nothing
+    /// meaningful to return.
+    ///
+    /// The rows are streamed by [`stream_sql_query`](DbClient::stream_sql_query),
+    /// so the columns are taken from the first row, see
+    /// [`SqlQueryResponse::column_types`].
+    async fn sql_query_best_effort(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        deadline: Duration,
+    ) -> Result<SqlQueryResponse> {
+        let begin = tokio::time::Instant::now();
+        let mut rows = Vec::new();
+        let collect = async {
+            let mut stream = self.stream_sql_query(ctx, req).await?;
+            while let Some(row) = stream.try_next().await? {
+                rows.push(row);
+            }
+            Ok::<_, Error>(())
+        };
+        let truncated = match tokio::time::timeout(deadline, collect).await {
+            Ok(result) => {
+                result?;
+                false
+            }
+            Err(_) if rows.is_empty() => {
+                return Err(Error::Timeout {
+                    elapsed: begin.elapsed(),
+                    limit: deadline,
+                    request_id: ctx.request_id.clone(),
+                });
+            }
+            Err(_) => true,
+        };
+
+        Ok(SqlQueryResponse {
+            truncated,
+            ..SqlQueryResponse::with_rows(rows)
+        })
+    }
+    /// Query by the sql like [`sql_query`](DbClient::sql_query), but the rows
+    /// are collected into the `buf` reusing the capacity of its rows, instead
+    /// of a new response, e.g. for the dashboards polling in a hot loop.
+    ///
+    /// Everything in the `buf` is replaced, and it is left without rows if the
+    /// query fails. The rows are streamed by
+    /// [`stream_sql_query`](DbClient::stream_sql_query) instead, so unlike
+    /// `sql_query`:
+    /// - The columns are taken from the first row, see
+    ///   [`SqlQueryResponse::column_types`], so they are unknown for the empty
+    ///   results.
+    /// - The [`SqlQueryResponse::affected_rows`] is always 0, so it doesn't
+    ///   suit the mutations.
+    /// - The rows are not limited by [`RpcConfig::max_response_rows`], and the
+    ///   query is not logged by [`RpcConfig::slow_query_threshold`].
+    ///
+    /// [`RpcConfig::max_response_rows`]: crate::RpcConfig::max_response_rows
+    /// [`RpcConfig::slow_query_threshold`]: crate::RpcConfig::slow_query_threshold
+    async fn sql_query_into(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        buf: &mut SqlQueryResponse,
+    ) -> Result<()> {
+        let mut rows = std::mem::take(&mut buf.rows);
+        rows.clear();
+        let collect = async {
+            let mut stream = self.stream_sql_query(ctx, req).await?;
+            while let Some(row) = stream.try_next().await? {
+                rows.push(row);
+            }
+            Ok::<_, Error>(())
+        };
+        if let Err(e) = collect.await {
+            rows.clear();
+            buf.rows = rows;
+            return Err(e);
+        }
+
+        *buf = SqlQueryResponse::with_rows(rows);
+        Ok(())
+    }
+    /// Query one page of at most `page_size` rows of the `req`, starting from
+    /// the `cursor` returned for the last page, or from the first row if it is
+    /// `None`.
+    ///
+    /// The cursor of the next page is returned, and it is `None` after the
+    /// last page. The sql must be a `SELECT`, which is wrapped by `LIMIT` and
+    /// `OFFSET`, so the pages are only consistent if it has an `ORDER BY` and
+    /// the data doesn't change in the meantime.
+    async fn sql_query_page(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        page_size: usize,
+        cursor: Option<&str>,
+    ) -> Result<(SqlQueryResponse, Option<String>)> {
+        if page_size == 0 {
+            return Err(Error::Client("Page size must not be zero".to_string()));
+        }
+        let offset: usize = match cursor {
+            Some(cursor) => cursor
+                .parse()
+                .map_err(|e| Error::Client(format!("Invalid cursor:{cursor}, err:{e}")))?,
+            None => 0,
+        };
+
+        // One more row is fetched to know whether there is a next page.
+        let (limit, next_offset) = page_size
+            .checked_add(1)
+            .zip(offset.checked_add(page_size))
+            .ok_or_else(|| {
+                Error::Client(format!(
+                    "Page out of range, page_size:{page_size}, offset:{offset}"
+                ))
+            })?;
+        let page_req = SqlQueryRequest {
+            tables: req.tables.clone(),
+            sql: format!(
+                "SELECT * FROM ({}) LIMIT {limit} OFFSET {offset}",
+                req.sql.trim().trim_end_matches(';'),
+            ),
+        };
+        let mut resp = self.sql_query(ctx, &page_req).await?;
+        let next_cursor = (resp.rows.len() > page_size).then(|| {
+            resp.rows.truncate(page_size);
+            next_offset.to_string()
+        });
+
+        Ok((resp, next_cursor))
+    }
+    /// Query the plan of the `req` by prefixing its sql with `EXPLAIN`, see
+    /// [`explain_with`](DbClient::explain_with) for the other keywords.
+    async fn explain(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.explain_with(ctx, req, "EXPLAIN").await
+    }
+    /// Query the plan of the `req` by prefixing its sql with the `keyword`,
+    /// e.g. `EXPLAIN ANALYZE`, unless the sql already starts with it.
+    ///
+    /// The plan is returned in the rows as is, and the error of the server not
+    /// supporting the keyword is returned unchanged.
+    async fn explain_with(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+        keyword: &str,
+    ) -> Result<SqlQueryResponse> {
+        let explain_req = SqlQueryRequest {
+            tables: req.tables.clone(),
+            sql: prefix_keyword(&req.sql, keyword),
+        };
+        self.sql_query(ctx, &explain_req).await
+    }
+    /// Same as [`sql_query`](DbClient::sql_query), but fails with
+    /// `Error::Client("too many inflight")` at once instead of waiting if
+    /// [`RpcConfig::max_inflight`] requests are in flight.
+    ///
+    /// [`RpcConfig::max_inflight`]: crate::RpcConfig::max_inflight
+    async fn try_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryResponse> {
+        self.sql_query(ctx, req).await
+    }
+    /// Same as [`write`](DbClient::write), but fails at once if too many
+    /// requests are in flight, see [`try_sql_query`](DbClient::try_sql_query).
+    async fn try_write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        self.write(ctx, req).await
+    }
+    /// Same as [`write`](DbClient::write) but with the default [`RpcContext`].
+    async fn write_default(&self, req: &WriteRequest) -> Result<WriteResponse> {
+        self.write(&RpcContext::default(), req).await
+    }
+    /// Write the requests together, that is to say, the points are merged into
+    /// one request so that the ones to the same endpoint are sent in one rpc.
+    async fn write_batch(
+        &self,
+        ctx: &RpcContext,
+        reqs: Vec<WriteRequest>,
+    ) -> Result<WriteResponse> {
+        let mut merged = WriteRequest::default();
+        for req in reqs {
+            merged.merge(req);
+        }
+        if merged.point_groups.is_empty() {
+            return Ok(WriteResponse::new(0, 0));
+        }
+
+        self.write(ctx, &merged).await
+    }
+    /// Write the request in chunks one by one, whose estimated encoded sizes are
+    /// not larger than `max_bytes`, see [`WriteRequest::split_by_size`].
+    ///
+    /// [`Error::ChunkedWrite`] is returned if any chunk fails, which tells how
+    /// many chunks are written before it. The chunks carry the
+    /// [`RpcContext::idempotency_key`] suffixed by their indexes if it is set.
+    async fn write_chunked(
+        &self,
+        ctx: &RpcContext,
+        req: &WriteRequest,
+        max_bytes: usize,
+    ) -> Result<WriteResponse> {
+        let chunks = req.clone().split_by_size(max_bytes);
+        let total_chunks = chunks.len();
+        let mut written = WriteResponse::new(0, 0);
+        for (idx, chunk) in chunks.iter().enumerate() {
+            match self.write(&ctx.for_sub_write(idx), chunk).await {
+                Ok(resp) => {
+                    written.merge(resp);
+                }
+                Err(e) => {
+                    return Err(Error::ChunkedWrite {
+                        succeeded_chunks: idx,
+                        total_chunks,
+                        written,
+                        source: Box::new(e),
+                    })
+                }
+            }
+        }
+
+        Ok(written)
+    }
+    /// Write the points of one series, that is to say, the `(timestamp,
+    /// value)` pairs with the same `tags`, into the `table`.
+    ///
+    /// The values are written into the field named `value`, and nothing is
+    /// sent if `points` is empty.
+    async fn write_series(
+        &self,
+        ctx: &RpcContext,
+        table: &str,
+        tags: &[(&str, &str)],
+        points: &[(TimestampMs, f64)],
+    ) -> Result<WriteResponse> {
+        if points.is_empty() {
+            return Ok(WriteResponse::new(0, 0));
+        }
+
+        let mut req = WriteRequest::default();
+        for (timestamp, value) in points {
+            let point = tags
+                .iter()
+                .fold(
+                    PointBuilder::new(table).timestamp(*timestamp),
+                    |builder, (name, tag)| builder.tag(*name, Value::String(tag.to_string())),
+                )
+                .field(SERIES_VALUE_FIELD, Value::Double(*value))
+                .build()
+                .map_err(|e| Error::Client(format!("Failed to build point, err:{e}")))?;
+            req.add_point(point);
+        }
+
+        self.write(ctx, &req).await
+    }
+    /// Query by the requests concurrently, at most `concurrency` of them at a
+    /// time, and merge the responses in the order of the requests.
+    ///
+    /// It is useful to split a heavy query over many tables into the ones
+    /// over fewer tables, which may be routed to different servers. Error
+    /// will be returned if any query fails or the responses have different
+    /// columns, see [`SqlQueryResponse::merge`].
+    async fn sql_query_parallel(
+        &self,
+        ctx: &RpcContext,
+        reqs: &[SqlQueryRequest],
+        concurrency: usize,
+    ) -> Result<SqlQueryResponse> {
+        // The queries are lazy, so only `concurrency` of them run at a time.
+        let queries: Vec<_> = reqs.iter().map(|req| self.sql_query(ctx, req)).collect();
+        let mut resps = stream::iter(queries).buffered(concurrency.max(1));
+
+        let mut merged = match resps.try_next().await? {
+            Some(resp) => resp,
+            None => return Ok(SqlQueryResponse::default()),
+        };
+        while let Some(resp) = resps.try_next().await? {
+            merged.merge(resp)?;
+        }
+
+        Ok(merged)
+    }
+    /// Query by the requests concurrently, at most `concurrency` of them at a
+    /// time, and return the result of every query in the order of the
+    /// requests, e.g. for the independent queries of a dashboard.
+    ///
+    /// Different from [`sql_query_parallel`](DbClient::sql_query_parallel),
+    /// the responses are not merged, and a failed query doesn't abort the
+    /// others.
+    async fn sql_query_multi(
+        &self,
+        ctx: &RpcContext,
+        reqs: &[SqlQueryRequest],
+        concurrency: usize,
+    ) -> Vec<Result<SqlQueryResponse>> {
+        let queries: Vec<_> = reqs.iter().map(|req| self.sql_query(ctx, req)).collect();
+        stream::iter(queries)
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+    /// List the names of the tables in the database of the `ctx` by `SHOW
+    /// TABLES`, e.g. for building the catalogs.
+    ///
+    /// The sql involves no table so that it can't be routed, and it works in
+    /// [`Mode::Proxy`] only.
+    async fn list_tables(&self, ctx: &RpcContext) -> Result<Vec<String>> {
+        let req = SqlQueryRequest {
+            tables: vec![],
+            sql: "SHOW TABLES".to_string(),
+        };
+        let resp = self.sql_query(ctx, &req).await?;
+
+        resp.rows
+            .iter()
+            .map(
+                |row| match row.columns().first().map(|column| column.value()) {
+                    Some(Value::String(table)) => Ok(table.clone()),
+                    value => Err(Error::Client(format!(
+                        "Failed to parse table name, value:{value:?}"
+                    ))),
+                },
+            )
+            .collect()
+    }
+    /// Get the names and the data types of the columns of the `table` by a
+    /// query returning no rows, without fetching any data.
+    ///
+    /// The [`Error::Server`] is returned if the table doesn't exist.
+    async fn describe_table(
+        &self,
+        ctx: &RpcContext,
+        table: &str,
+    ) -> Result<Vec<(String, DataType)>> {
+        let req = SqlQueryRequest {
+            tables: vec![table.to_string()],
+            sql: format!("SELECT * FROM `{}` LIMIT 0", table.replace('`', "``")),
+        };
+        let resp = self.sql_query(ctx, &req).await?;
+        if !resp.has_schema() {
+            return Err(Error::Client(format!(
+                "No schema is returned for table:{table}"
+            )));
+        }
+
+        Ok(resp
+            .column_names
+            .into_iter()
+            .zip(resp.column_types)
+            .collect())
+    }
+    /// Get the version of the server by `SELECT version()`, e.g. for gating
+    /// the features by the capabilities of the server.
+    ///
+    /// [`Error::Unsupported`] is returned if the server doesn't support it,
+    /// that is to say, the sql is rejected as an invalid one. Like
+    /// [`list_tables`](DbClient::list_tables), it works in [`Mode::Proxy`]
+    /// only.
+    async fn server_version(&self, ctx: &RpcContext) -> Result<String> {
+        let req = SqlQueryRequest {
+            tables: vec![],
+            sql: "SELECT version()".to_string(),
+        };
+        let resp = match self.sql_query(ctx, &req).await {
+            Ok(resp) => resp,
+            Err(Error::Server(e))
+                if e.code == StatusCode::InvalidArgument.as_u32()
+                    || e.code == StatusCode::NotFound.as_u32() =>
+            {
+                return Err(Error::Unsupported(format!(
+                    "Failed to query server version, err:{e}"
+                )));
+            }
+            Err(Error::Rpc(status)) if status.code() == tonic::Code::Unimplemented => {
+                return Err(Error::Unsupported(format!(
+                    "Failed to query server version, err:{status}"
+                )));
+            }
+            Err(e) => return Err(e),
+        };
+
+        match resp
+            .rows
+            .first()
+            .and_then(|row| row.columns().first())
+            .map(|column| column.value())
+        {
+            Some(Value::String(version)) => Ok(version.clone()),
+            value => Err(Error::Client(format!(
+                "Failed to parse server version, value:{value:?}"
+            ))),
+        }
+    }
+    /// Check whether the server is reachable, e.g. for the startup probes.
+    ///
+    /// The clients built by the [`Builder`] check the server by the cheap route
+    /// rpc, of which the timeout is [`RpcConfig::default_health_check_timeout`]
+    /// unless it is set in the `ctx`.
+    ///
+    /// By default the server is checked by a trivial query involving no table
+    /// through [`sql_query`](DbClient::sql_query), so that the timeout of the
+    /// queries applies instead, and it works in [`Mode::Proxy`] only, like
+    /// [`list_tables`](DbClient::list_tables).
+    ///
+    /// [`RpcConfig::default_health_check_timeout`]: crate::RpcConfig::default_health_check_timeout
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        let req = SqlQueryRequest {
+            tables: vec![],
+            sql: "SELECT 1".to_string(),
+        };
+        self.sql_query(ctx, &req).await.map(|_| ())
+    }
+    /// Connect to the server in advance, so that the first request doesn't pay
+    /// for the connection.
+    ///
+    /// Otherwise the connection is made at the first request. It fails within
+    /// [`RpcConfig::connect_timeout`] if the server is unreachable, and nothing
+    /// is done by default.
+    ///
+    /// [`RpcConfig::connect_timeout`]: crate::RpcConfig::connect_timeout
+    async fn connect(&self) -> Result<()> {
+        Ok(())
+    }
+    /// The endpoint which the client is built for, e.g. for logging.
+    ///
+    /// It is the router endpoint in [`Mode::Direct`], and `None` is returned by
+    /// default.
+    fn endpoint(&self) -> Option<&str> {
+        None
+    }
 }
 
+#[allow(clippy::result_large_err)]
 pub(crate) fn resolve_database(
     ctx: &RpcContext,
     default_database: &Option<String>,
@@ -53,3 +576,730 @@ pub(crate) fn resolve_database(
         (None, None) => Err(crate::Error::NoDatabase),
     }
 }
+
+/// Fill the empty tables of the `req` with the `default_tables`, and the
+/// non-empty ones are kept.
+pub(crate) fn resolve_tables<'a>(
+    req: &'a SqlQueryRequest,
+    default_tables: &[String],
+) -> Cow<'a, SqlQueryRequest> {
+    if !req.tables.is_empty() || default_tables.is_empty() {
+        return Cow::Borrowed(req);
+    }
+
+    Cow::Owned(SqlQueryRequest {
+        tables: default_tables.to_vec(),
+        sql: req.sql.clone(),
+    })
+}
+
+/// Prefix the `sql` with the `keyword` unless it already starts with it, where
+/// the words are compared case-insensitively.
+fn prefix_keyword(sql: &str, keyword: &str) -> String {
+    let sql = sql.trim_start();
+    let mut sql_words = sql.split_whitespace();
+    let has_keyword = keyword.split_whitespace().all(|word| {
+        sql_words
+            .next()
+            .is_some_and(|sql_word| sql_word.eq_ignore_ascii_case(word))
+    });
+    if has_keyword {
+        return sql.to_string();
+    }
+
+    format!(
+        "{} {sql}",
+        keyword.split_whitespace().collect::<Vec<_>>().join(" ")
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::result_large_err)]
+mod test {
+    use std::{
+        sync::atomic::Ordering,
+        time::{Duration, Instant},
+    };
+
+    use futures::TryStreamExt;
+    use tokio_util::sync::CancellationToken;
+
+    use super::{
+        prefix_keyword, resolve_database, resolve_tables, scripted::ScriptedClient, DbClient,
+    };
+    use crate::{
+        errors::ServerError,
+        model::{
+            sql_query::{
+                row::{Column, Row},
+                Request as SqlQueryRequest, Response as SqlQueryResponse,
+            },
+            value::{DataType as ValueDataType, Value},
+            write::{estimate_point_size, point::PointBuilder, Request as WriteRequest},
+        },
+        rpc_client::RpcContext,
+        Error, Result,
+    };
+
+    /// Answer one row of the `value` column for every table, or of the `other`
+    /// column for the tables named `other`, and fail for the tables named
+    /// `missing`.
+    fn answer_tables(ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        if req.tables.is_empty() {
+            // Respond the tables for `SHOW TABLES`, and none if asked.
+            let tables = if ctx.database.as_deref() == Some("empty") {
+                vec![]
+            } else {
+                vec!["t1", "t2"]
+            };
+            let rows = tables
+                .into_iter()
+                .map(|table| {
+                    Row::new(vec![Column::new(
+                        "Tables".to_string(),
+                        Value::String(table.to_string()),
+                    )])
+                })
+                .collect();
+            return Ok(SqlQueryResponse {
+                affected_rows: 0,
+                rows,
+                column_names: vec!["Tables".to_string()],
+                column_types: vec![ValueDataType::String],
+                column_nullables: vec![false],
+                truncated: false,
+            });
+        }
+
+        if req.tables == ["missing"] {
+            return Err(Error::Server(ServerError::new(404, "table not found")));
+        }
+        let column = if req.tables == ["other"] {
+            "other"
+        } else {
+            "value"
+        };
+        let rows = req
+            .tables
+            .iter()
+            .map(|table| {
+                Row::new(vec![Column::new(
+                    column.to_string(),
+                    Value::String(table.clone()),
+                )])
+            })
+            .collect();
+        Ok(SqlQueryResponse {
+            affected_rows: 0,
+            rows,
+            column_names: vec![column.to_string()],
+            column_types: vec![ValueDataType::String],
+            column_nullables: vec![false],
+            truncated: false,
+        })
+    }
+
+    /// The client answering by [`answer_tables`] 10ms later.
+    fn make_table_client() -> ScriptedClient {
+        ScriptedClient::new(answer_tables).with_delay(Duration::from_millis(10))
+    }
+
+    /// Answer the schema of the `cpu` table without any rows, like the server
+    /// does for the queries of `LIMIT 0`.
+    fn answer_schema(_ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        match req.tables.as_slice() {
+            [table] if table == "cpu" => Ok(SqlQueryResponse {
+                column_names: vec!["t".to_string(), "host".to_string(), "value".to_string()],
+                column_types: vec![
+                    ValueDataType::Timestamp,
+                    ValueDataType::String,
+                    ValueDataType::Double,
+                ],
+                ..Default::default()
+            }),
+            // The affected rows only.
+            [table] if table == "no_schema" => Ok(SqlQueryResponse::default()),
+            _ => Err(Error::Server(ServerError::new(
+                400,
+                format!("Table not found, table:{}", req.tables[0]),
+            ))),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_table() {
+        let client = ScriptedClient::new(answer_schema);
+        let ctx = RpcContext::default();
+        let schema = client.describe_table(&ctx, "cpu").await.unwrap();
+        assert_eq!(client.sqls(), vec!["SELECT * FROM `cpu` LIMIT 0"]);
+        assert_eq!(
+            schema,
+            vec![
+                ("t".to_string(), ValueDataType::Timestamp),
+                ("host".to_string(), ValueDataType::String),
+                ("value".to_string(), ValueDataType::Double),
+            ]
+        );
+
+        assert!(matches!(
+            client.describe_table(&ctx, "missing").await,
+            Err(Error::Server(ServerError { code: 400, .. }))
+        ));
+        assert!(matches!(
+            client.describe_table(&ctx, "no_schema").await,
+            Err(Error::Client(_))
+        ));
+    }
+
+    /// The client answering the rows of `0..total` in the range of the `LIMIT`
+    /// and `OFFSET` at the end of the sql.
+    fn make_paged_client(total: i64) -> ScriptedClient {
+        ScriptedClient::new(move |_, req| {
+            let mut words = req.sql.rsplit(' ');
+            let offset: i64 = words.next().unwrap().parse().unwrap();
+            assert_eq!(words.next(), Some("OFFSET"));
+            let limit: i64 = words.next().unwrap().parse().unwrap();
+            assert_eq!(words.next(), Some("LIMIT"));
+
+            let rows = (offset..total.min(offset + limit))
+                .map(|v| Row::new(vec![Column::new("value".to_string(), Value::Int64(v))]))
+                .collect();
+            Ok(SqlQueryResponse::with_rows(rows))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_page() {
+        let ctx = RpcContext::default();
+        let req = make_table_request("t");
+
+        for (total, expected_pages) in [(5, vec![2, 2, 1]), (4, vec![2, 2]), (0, vec![0])] {
+            let client = make_paged_client(total);
+            let (mut pages, mut values, mut cursor) = (Vec::new(), Vec::new(), None);
+            loop {
+                let (resp, next_cursor) = client
+                    .sql_query_page(&ctx, &req, 2, cursor.as_deref())
+                    .await
+                    .unwrap();
+                pages.push(resp.rows.len());
+                values.extend(resp.iter_rows().map(|row| row.get_i64("value").unwrap()));
+                match next_cursor {
+                    Some(next_cursor) => cursor = Some(next_cursor),
+                    None => break,
+                }
+            }
+            assert_eq!(pages, expected_pages);
+            assert_eq!(values, (0..total).collect::<Vec<_>>());
+        }
+
+        let client = make_paged_client(5);
+        assert!(matches!(
+            client.sql_query_page(&ctx, &req, 0, None).await,
+            Err(Error::Client(_))
+        ));
+        assert!(matches!(
+            client.sql_query_page(&ctx, &req, 2, Some("invalid")).await,
+            Err(Error::Client(_))
+        ));
+
+        // The overflowing limit or next offset is rejected without querying.
+        let max_cursor = usize::MAX.to_string();
+        for (page_size, cursor) in [(usize::MAX, None), (2, Some(max_cursor.as_str()))] {
+            assert!(matches!(
+                client.sql_query_page(&ctx, &req, page_size, cursor).await,
+                Err(Error::Client(_))
+            ));
+        }
+    }
+
+    /// Answer the sql as the plan, and fail for `EXPLAIN ANALYZE`.
+    fn answer_explain(_ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        if req.sql.starts_with("EXPLAIN ANALYZE") {
+            return Err(Error::Server(ServerError::new(
+                400,
+                "EXPLAIN ANALYZE is not supported",
+            )));
+        }
+        let row = Row::new(vec![Column::new(
+            "plan".to_string(),
+            Value::String(req.sql.clone()),
+        )]);
+        Ok(SqlQueryResponse::with_rows(vec![row]))
+    }
+
+    #[tokio::test]
+    async fn test_explain() {
+        let client = ScriptedClient::new(answer_explain);
+        let ctx = RpcContext::default();
+        for sql in [
+            "SELECT * FROM t",
+            "EXPLAIN SELECT * FROM t",
+            "  explain SELECT * FROM t",
+        ] {
+            let req = SqlQueryRequest {
+                tables: vec!["t".to_string()],
+                sql: sql.to_string(),
+            };
+            let resp = client.explain(&ctx, &req).await.unwrap();
+            let plan = resp.rows[0].get_string("plan").unwrap().to_string();
+            assert_eq!(plan.to_uppercase(), "EXPLAIN SELECT * FROM T");
+        }
+
+        // The error of the server is returned unchanged.
+        let req = make_table_request("t");
+        match client.explain_with(&ctx, &req, "EXPLAIN ANALYZE").await {
+            Err(Error::Server(e)) => {
+                assert_eq!(e.code, 400);
+                assert_eq!(e.msg, "EXPLAIN ANALYZE is not supported");
+            }
+            other => panic!("unexpected result:{other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_prefix_keyword() {
+        let cases = [
+            ("SELECT 1", "EXPLAIN", "EXPLAIN SELECT 1"),
+            ("EXPLAIN SELECT 1", "EXPLAIN", "EXPLAIN SELECT 1"),
+            (
+                "EXPLAIN SELECT 1",
+                "EXPLAIN  ANALYZE",
+                "EXPLAIN ANALYZE EXPLAIN SELECT 1",
+            ),
+            (
+                "Explain Analyze SELECT 1",
+                "EXPLAIN ANALYZE",
+                "Explain Analyze SELECT 1",
+            ),
+            ("EXPLAINED", "EXPLAIN", "EXPLAIN EXPLAINED"),
+        ];
+        for (sql, keyword, expected) in cases {
+            assert_eq!(prefix_keyword(sql, keyword), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_tables() {
+        let client = make_table_client();
+        let tables = client.list_tables(&RpcContext::default()).await.unwrap();
+        assert_eq!(tables, vec!["t1".to_string(), "t2".to_string()]);
+
+        let ctx = RpcContext::default().database("empty".to_string());
+        assert!(client.list_tables(&ctx).await.unwrap().is_empty());
+    }
+
+    /// Answer as the server whose version is `1.2.0`, or the older one not
+    /// supporting the version in the database `old`.
+    fn answer_version(ctx: &RpcContext, _req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        match ctx.database.as_deref() {
+            Some("old") => Err(Error::Server(ServerError::new(400, "failed"))),
+            Some("down") => Err(Error::Server(ServerError::new(500, "failed"))),
+            _ => Ok(SqlQueryResponse::with_rows(vec![Row::new(vec![
+                Column::new("version()".to_string(), Value::String("1.2.0".to_string())),
+            ])])),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_version() {
+        let client = ScriptedClient::new(answer_version);
+        let version = client.server_version(&RpcContext::default()).await.unwrap();
+        assert_eq!(version, "1.2.0");
+        assert_eq!(client.sqls(), vec!["SELECT version()"]);
+
+        let ctx = RpcContext::default().database("old".to_string());
+        assert!(matches!(
+            client.server_version(&ctx).await,
+            Err(Error::Unsupported(_))
+        ));
+        // The other failures are not regarded as unsupported.
+        let ctx = RpcContext::default().database("down".to_string());
+        assert!(matches!(
+            client.server_version(&ctx).await,
+            Err(Error::Server(ServerError { code: 500, .. }))
+        ));
+    }
+
+    fn make_table_request(table: &str) -> SqlQueryRequest {
+        SqlQueryRequest {
+            tables: vec![table.to_string()],
+            sql: format!("select * from {table}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_sql_query() {
+        let client = make_table_client();
+        let ctx = RpcContext::default();
+
+        let stream = client
+            .stream_sql_query(&ctx, &make_table_request("t1"))
+            .await
+            .unwrap();
+        let rows: Vec<_> = stream.try_collect().await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_string("value").unwrap(), "t1");
+
+        let res = client
+            .stream_sql_query(&ctx, &make_table_request("missing"))
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Server(ServerError { code: 404, .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_health_check() {
+        let client = make_table_client();
+        client.health_check(&RpcContext::default()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_parallel() {
+        let client = make_table_client();
+        let ctx = RpcContext::default();
+        let reqs: Vec<_> = ["t1", "t2", "t3", "t4", "t5"]
+            .into_iter()
+            .map(make_table_request)
+            .collect();
+
+        let resp = client.sql_query_parallel(&ctx, &reqs, 2).await.unwrap();
+        let tables: Vec<_> = resp
+            .iter_rows()
+            .map(|row| row.get_string("value").unwrap())
+            .collect();
+        assert_eq!(tables, vec!["t1", "t2", "t3", "t4", "t5"]);
+        assert_eq!(resp.column_names, vec!["value".to_string()]);
+        assert_eq!(client.max_inflight.load(Ordering::SeqCst), 2);
+
+        let resp = client.sql_query_parallel(&ctx, &[], 2).await.unwrap();
+        assert!(resp.rows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_multi() {
+        let client = make_table_client();
+        let ctx = RpcContext::default();
+        let reqs: Vec<_> = ["t1", "missing", "t3"]
+            .into_iter()
+            .map(make_table_request)
+            .collect();
+
+        let results = client.sql_query_multi(&ctx, &reqs, 2).await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(client.max_inflight.load(Ordering::SeqCst), 2);
+        // The failed query doesn't abort the others.
+        assert!(matches!(
+            &results[1],
+            Err(Error::Server(ServerError { code: 404, .. }))
+        ));
+        for (result, table) in [(&results[0], "t1"), (&results[2], "t3")] {
+            let resp = result.as_ref().unwrap();
+            assert_eq!(resp.rows.len(), 1);
+            assert_eq!(resp.rows[0].get_string("value").unwrap(), table);
+        }
+
+        assert!(client.sql_query_multi(&ctx, &[], 2).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_parallel_with_different_columns() {
+        let client = make_table_client();
+        let reqs = vec![make_table_request("t1"), make_table_request("other")];
+
+        let err = client
+            .sql_query_parallel(&RpcContext::default(), &reqs, 2)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Client(_)));
+    }
+
+    fn make_request(table: &str, timestamps: &[i64]) -> WriteRequest {
+        let mut req = WriteRequest::default();
+        for ts in timestamps {
+            let point = PointBuilder::new(table.to_string())
+                .timestamp(*ts)
+                .tag("host".to_string(), Value::String("a".to_string()))
+                .field("value".to_string(), Value::Int64(*ts))
+                .build()
+                .unwrap();
+            req.add_point(point);
+        }
+        req
+    }
+
+    /// The client streaming the rows of `null, 1, 2, 3, 4` one by one every
+    /// 100ms.
+    fn make_slow_stream_client() -> ScriptedClient {
+        ScriptedClient::new(|_, _| {
+            let rows = (0..5)
+                .map(|v| {
+                    let value = if v == 0 { Value::Null } else { Value::Int64(v) };
+                    Row::new(vec![Column::new("value".to_string(), value)])
+                })
+                .collect();
+            Ok(SqlQueryResponse::with_rows(rows))
+        })
+        .with_delay(Duration::from_millis(100))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sql_query_best_effort() {
+        let client = make_slow_stream_client();
+        let ctx = RpcContext::default();
+        let req = make_table_request("t1");
+
+        let resp = client
+            .sql_query_best_effort(&ctx, &req, Duration::from_millis(350))
+            .await
+            .unwrap();
+        assert!(resp.truncated);
+        let values: Vec<_> = resp.iter_rows().map(|row| row.get_i64("value")).collect();
+        assert_eq!(values, vec![None, Some(1), Some(2)]);
+        assert_eq!(resp.column_names, vec!["value".to_string()]);
+        // Typed by the first non-null value.
+        assert_eq!(resp.column_types, vec![ValueDataType::Int64]);
+
+        let resp = client
+            .sql_query_best_effort(&ctx, &req, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(!resp.truncated);
+        assert_eq!(resp.rows.len(), 5);
+
+        // Nothing arrives before the deadline.
+        let res = client
+            .sql_query_best_effort(&ctx, &req, Duration::from_millis(50))
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Timeout { limit, .. }) if limit == Duration::from_millis(50)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sql_query_into() {
+        let client = make_slow_stream_client();
+        let ctx = RpcContext::default();
+        let req = make_table_request("t1");
+
+        let mut buf = SqlQueryResponse::default();
+        client.sql_query_into(&ctx, &req, &mut buf).await.unwrap();
+        assert_eq!(buf.rows.len(), 5);
+        assert_eq!(buf.column_names, vec!["value".to_string()]);
+        let (ptr, capacity) = (buf.rows.as_ptr(), buf.rows.capacity());
+
+        // The capacity of the rows is reused.
+        for _ in 0..3 {
+            client.sql_query_into(&ctx, &req, &mut buf).await.unwrap();
+            assert_eq!(buf.rows.len(), 5);
+            assert_eq!((buf.rows.as_ptr(), buf.rows.capacity()), (ptr, capacity));
+        }
+        let values: Vec<_> = buf.iter_rows().map(|row| row.get_i64("value")).collect();
+        assert_eq!(values, vec![None, Some(1), Some(2), Some(3), Some(4)]);
+    }
+
+    #[tokio::test]
+    async fn test_write_chunked() {
+        let client = ScriptedClient::default();
+        let ctx = RpcContext::default();
+        let req = make_request("t1", &[1, 2, 3, 4, 5]);
+        let point_bytes = estimate_point_size(&req.point_groups["t1"][0]);
+
+        let resp = client
+            .write_chunked(&ctx, &req, point_bytes * 2)
+            .await
+            .unwrap();
+        assert_eq!(resp.success, 5);
+        let chunk_sizes: Vec<_> = client
+            .writes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|req| req.point_groups["t1"].len())
+            .collect();
+        assert_eq!(chunk_sizes, vec![2, 2, 1]);
+
+        // Every chunk carries its own idempotency key.
+        let client = ScriptedClient::default();
+        let keyed_ctx = RpcContext::default().idempotency_key("write-1");
+        client
+            .write_chunked(&keyed_ctx, &req, point_bytes * 2)
+            .await
+            .unwrap();
+        let keys: Vec<_> = client
+            .contexts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|ctx| ctx.idempotency_key.clone().unwrap())
+            .collect();
+        assert_eq!(keys, vec!["write-1-0", "write-1-1", "write-1-2"]);
+
+        let client = ScriptedClient::default().with_fail_write_at(1);
+        let err = client
+            .write_chunked(&ctx, &req, point_bytes * 2)
+            .await
+            .unwrap_err();
+        match err {
+            Error::ChunkedWrite {
+                succeeded_chunks,
+                total_chunks,
+                written,
+                source,
+            } => {
+                assert_eq!(succeeded_chunks, 1);
+                assert_eq!(total_chunks, 3);
+                assert_eq!(written.success, 2);
+                assert!(matches!(*source, Error::Rpc(_)));
+            }
+            _ => panic!("unexpected error:{err:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_batch() {
+        let client = ScriptedClient::default();
+        let ctx = RpcContext::default();
+        let reqs = vec![
+            make_request("t1", &[1, 2]),
+            make_request("t2", &[3]),
+            make_request("t1", &[4]),
+        ];
+
+        let resp = client.write_batch(&ctx, reqs).await.unwrap();
+        assert_eq!(resp.success, 4);
+        assert_eq!(resp.failed, 0);
+
+        let writes = client.writes.lock().unwrap();
+        assert_eq!(writes.len(), 1);
+        let timestamps: Vec<_> = writes[0].point_groups["t1"]
+            .iter()
+            .map(|point| point.timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![1, 2, 4]);
+        assert_eq!(writes[0].point_groups["t2"].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_empty_batch() {
+        let client = ScriptedClient::default();
+        let resp = client
+            .write_batch(&RpcContext::default(), vec![])
+            .await
+            .unwrap();
+        assert_eq!(resp.success, 0);
+        assert!(client.writes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_series() {
+        let client = ScriptedClient::default();
+        let ctx = RpcContext::default();
+        let resp = client
+            .write_series(&ctx, "cpu", &[("host", "a")], &[(1, 0.5), (2, 0.7)])
+            .await
+            .unwrap();
+        assert_eq!(resp.success, 2);
+
+        {
+            let writes = client.writes.lock().unwrap();
+            let points = &writes[0].point_groups["cpu"];
+            assert_eq!(points.len(), 2);
+            assert_eq!(points[1].timestamp, 2);
+            assert_eq!(points[1].tags["host"], Value::String("a".to_string()));
+            assert_eq!(points[1].fields["value"], Value::Double(0.7));
+        }
+
+        let resp = client.write_series(&ctx, "cpu", &[], &[]).await.unwrap();
+        assert_eq!(resp.success, 0);
+        assert_eq!(client.writes.lock().unwrap().len(), 1);
+
+        let err = client
+            .write_series(&ctx, "cpu", &[("timestamp", "a")], &[(1, 0.5)])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Client(_)));
+    }
+
+    #[test]
+    fn test_resolve_database() {
+        let default_database = Some("default".to_string());
+        let ctx = RpcContext {
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let resolved = resolve_database(&ctx, &default_database).unwrap();
+        assert_eq!(resolved.database.as_deref(), Some("default"));
+        assert_eq!(resolved.timeout, ctx.timeout);
+
+        // The database in the context overrides the default one.
+        let ctx = RpcContext::default().database("explicit".to_string());
+        let resolved = resolve_database(&ctx, &default_database).unwrap();
+        assert_eq!(resolved.database.as_deref(), Some("explicit"));
+        let resolved = resolve_database(&ctx, &None).unwrap();
+        assert_eq!(resolved.database.as_deref(), Some("explicit"));
+
+        assert!(matches!(
+            resolve_database(&RpcContext::default(), &None),
+            Err(Error::NoDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_resolve_tables() {
+        let default_tables = vec!["cpu".to_string(), "mem".to_string()];
+        let req = SqlQueryRequest {
+            tables: vec![],
+            sql: "select * from cpu".to_string(),
+        };
+        let resolved = resolve_tables(&req, &default_tables);
+        assert_eq!(resolved.tables, default_tables);
+        assert_eq!(resolved.sql, req.sql);
+        assert!(resolve_tables(&req, &[]).tables.is_empty());
+
+        // The explicit tables override the default ones.
+        let req = make_table_request("disk");
+        let resolved = resolve_tables(&req, &default_tables);
+        assert_eq!(resolved.tables, vec!["disk".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_write_default() {
+        let client = ScriptedClient::default();
+        let resp = client
+            .write_default(&make_request("t1", &[1]))
+            .await
+            .unwrap();
+        assert_eq!(resp.success, 1);
+
+        let contexts = client.contexts.lock().unwrap();
+        assert!(contexts[0].database.is_none());
+        assert!(contexts[0].timeout.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sql_query_with_cancel() {
+        // Like a slow query.
+        let client = ScriptedClient::default().with_delay(Duration::from_secs(60));
+        let req = SqlQueryRequest {
+            tables: vec!["t1".to_string()],
+            sql: "select * from t1".to_string(),
+        };
+        let cancel = CancellationToken::new();
+        let cancel_clone = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancel_clone.cancel();
+        });
+
+        let begin = Instant::now();
+        let err = client
+            .sql_query_with_cancel(&RpcContext::default(), &req, cancel)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Client(msg) if msg == "cancelled"));
+        assert!(begin.elapsed() < Duration::from_secs(10));
+    }
+}
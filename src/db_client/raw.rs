@@ -20,9 +20,15 @@ use std::sync::Arc;
 use async_trait::async_trait;
 
 use crate::{
-    db_client::{inner::InnerClient, DbClient},
+    db_client::{
+        inner::{InnerClient, InnerConfig},
+        DbClient,
+    },
     model::{
-        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
         write::{Request as WriteRequest, Response as WriteResponse},
     },
     rpc_client::{RpcClientFactory, RpcContext},
@@ -35,26 +41,78 @@ use crate::{
 pub struct RawImpl<F: RpcClientFactory> {
     inner_client: InnerClient<F>,
     default_database: Option<String>,
+    default_tables: Vec<String>,
 }
 
 impl<F: RpcClientFactory> RawImpl<F> {
-    pub fn new(factory: Arc<F>, endpoint: String, default_database: Option<String>) -> Self {
+    pub fn new(
+        factory: Arc<F>,
+        endpoint: String,
+        default_database: Option<String>,
+        inner_config: InnerConfig,
+    ) -> Self {
         Self {
-            inner_client: InnerClient::new(factory, endpoint),
+            inner_client: InnerClient::new(factory, endpoint, inner_config),
             default_database,
+            default_tables: Vec::new(),
         }
     }
+
+    /// The `default_tables` are queried by the requests without any table.
+    pub fn with_default_tables(mut self, default_tables: Vec<String>) -> Self {
+        self.default_tables = default_tables;
+        self
+    }
 }
 
 #[async_trait]
 impl<F: RpcClientFactory> DbClient for RawImpl<F> {
     async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        let req = crate::db_client::resolve_tables(req, &self.default_tables);
+        req.validate()?;
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        self.inner_client.sql_query_internal(&ctx, &req).await
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        let req = crate::db_client::resolve_tables(req, &self.default_tables);
+        req.validate()?;
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        self.inner_client
+            .stream_sql_query_internal(&ctx, &req)
+            .await
+    }
+
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        let req = crate::db_client::resolve_tables(req, &self.default_tables);
+        req.validate()?;
         let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
-        self.inner_client.sql_query_internal(&ctx, req).await
+        self.inner_client.sql_query_raw_internal(&ctx, &req).await
     }
 
     async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
         let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
         self.inner_client.write_internal(&ctx, req).await
     }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        self.inner_client.health_check_internal(&ctx).await
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.inner_client.connect_internal().await
+    }
+
+    fn endpoint(&self) -> Option<&str> {
+        Some(self.inner_client.endpoint())
+    }
 }
@@ -0,0 +1,477 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tonic::Code;
+use uuid::Uuid;
+
+use crate::{
+    db_client::DbClient,
+    errors::ServerError,
+    model::{
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result, RetryConfig,
+};
+
+/// Client retrying the failed requests of the wrapped [`DbClient`] with
+/// exponential backoff.
+///
+/// Queries are retried on the connection failures, the unavailable or timeout
+/// grpc errors and the configured server errors. Writes are never retried once
+/// they may have been applied, so they are retried only on the connection
+/// failures and the configured server errors. The unavailable grpc errors are
+/// excluded for them, since they are returned as well for the connection reset
+/// after the request is sent.
+///
+/// The backoff is replaced by the delay if it is hinted by the server in the
+/// [`Error::Server`](crate::Error::Server), which is capped by
+/// [`RetryConfig::max_backoff`] as well. All the attempts of a write carry
+/// the same [`RpcContext::idempotency_key`], so that the server can dedupe
+/// them.
+pub struct RetryableClient {
+    inner: Arc<dyn DbClient>,
+    config: RetryConfig,
+}
+
+#[allow(clippy::result_large_err)]
+impl RetryableClient {
+    pub fn new(inner: Arc<dyn DbClient>, config: RetryConfig) -> Self {
+        Self { inner, config }
+    }
+
+    async fn retry<T, Fut>(
+        &self,
+        op: impl Fn() -> Fut,
+        should_retry: impl Fn(&Error) -> bool,
+    ) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = self.config.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match op().await {
+                Err(e) if retries < self.config.max_retries && should_retry(&e) => {
+                    // The delay hinted by the server takes precedence, but a misbehaving
+                    // server must not stall the client for longer than the max backoff.
+                    let delay = match &e {
+                        Error::Server(ServerError {
+                            retry_after: Some(retry_after),
+                            ..
+                        }) => (*retry_after).min(self.config.max_backoff),
+                        _ => backoff,
+                    };
+                    tokio::time::sleep(delay).await;
+                    backoff = self.next_backoff(backoff);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn next_backoff(&self, backoff: Duration) -> Duration {
+        // `Duration::mul_f64` panics on the negative, NaN or too large results.
+        let multiplier = self.config.multiplier;
+        if multiplier.is_nan() || multiplier < 1.0 {
+            return backoff.min(self.config.max_backoff);
+        }
+        Duration::try_from_secs_f64(backoff.as_secs_f64() * multiplier)
+            .map_or(self.config.max_backoff, |backoff| {
+                backoff.min(self.config.max_backoff)
+            })
+    }
+
+    fn should_retry_query(&self, e: &Error) -> bool {
+        match e {
+            Error::Connect { .. } | Error::Timeout { .. } => true,
+            Error::Rpc(status) => {
+                matches!(status.code(), Code::Unavailable | Code::DeadlineExceeded)
+            }
+            Error::Server(server_error) => self.is_retryable_server_code(server_error.code),
+            _ => false,
+        }
+    }
+
+    fn should_retry_write(&self, e: &Error) -> bool {
+        match e {
+            Error::Connect { .. } => true,
+            Error::Server(server_error) => self.is_retryable_server_code(server_error.code),
+            _ => false,
+        }
+    }
+
+    #[inline]
+    fn is_retryable_server_code(&self, code: u32) -> bool {
+        self.config.retryable_server_codes.contains(&code)
+    }
+}
+
+#[async_trait]
+impl DbClient for RetryableClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.retry(
+            || self.inner.sql_query(ctx, req),
+            |e| self.should_retry_query(e),
+        )
+        .await
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        // Only the establishment of the stream can be retried.
+        self.retry(
+            || self.inner.stream_sql_query(ctx, req),
+            |e| self.should_retry_query(e),
+        )
+        .await
+    }
+
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        self.retry(
+            || self.inner.sql_query_raw(ctx, req),
+            |e| self.should_retry_query(e),
+        )
+        .await
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        let ctx = &match ctx.idempotency_key {
+            Some(_) => ctx.clone(),
+            None => ctx.clone().idempotency_key(Uuid::new_v4().to_string()),
+        };
+        self.retry(
+            || self.inner.write(ctx, req),
+            |e| self.should_retry_write(e),
+        )
+        .await
+    }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        // Not retried, so that the failures are reported to the probes at once.
+        self.inner.health_check(ctx).await
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    fn endpoint(&self) -> Option<&str> {
+        self.inner.endpoint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    use super::RetryableClient;
+    use crate::{
+        db_client::DbClient,
+        errors::ServerError,
+        model::{
+            sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
+            write::{Request as WriteRequest, Response as WriteResponse},
+        },
+        rpc_client::RpcContext,
+        Error, Result, RetryConfig,
+    };
+
+    /// Client failing with the error made by `make_error` for the first
+    /// `failures` calls.
+    struct FlakyClient {
+        failures: usize,
+        make_error: fn() -> Error,
+        calls: AtomicUsize,
+        /// The idempotency keys of the writes.
+        write_keys: Mutex<Vec<Option<String>>>,
+    }
+
+    fn make_connect_error() -> Error {
+        Error::Connect {
+            addr: "127.0.0.1:8831".to_string(),
+            source: "connection refused".into(),
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    impl FlakyClient {
+        fn new(failures: usize, make_error: fn() -> Error) -> Self {
+            Self {
+                failures,
+                make_error,
+                calls: AtomicUsize::new(0),
+                write_keys: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn call(&self) -> Result<()> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst);
+            if calls < self.failures {
+                Err((self.make_error)())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl DbClient for FlakyClient {
+        async fn sql_query(
+            &self,
+            _ctx: &RpcContext,
+            _req: &SqlQueryRequest,
+        ) -> Result<SqlQueryResponse> {
+            self.call().map(|_| SqlQueryResponse::default())
+        }
+
+        async fn write(&self, ctx: &RpcContext, _req: &WriteRequest) -> Result<WriteResponse> {
+            self.write_keys
+                .lock()
+                .await
+                .push(ctx.idempotency_key.clone());
+            self.call().map(|_| WriteResponse::new(1, 0))
+        }
+
+        async fn health_check(&self, _ctx: &RpcContext) -> Result<()> {
+            self.call()
+        }
+    }
+
+    fn make_retryable_client(inner: Arc<FlakyClient>) -> RetryableClient {
+        let config = RetryConfig {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            ..Default::default()
+        };
+        RetryableClient::new(inner, config)
+    }
+
+    fn make_sql_query_request() -> SqlQueryRequest {
+        SqlQueryRequest {
+            tables: vec!["test".to_string()],
+            sql: "select * from test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_next_backoff() {
+        let make_client = |multiplier| {
+            let config = RetryConfig {
+                initial_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(1),
+                multiplier,
+                ..Default::default()
+            };
+            RetryableClient::new(Arc::new(FlakyClient::new(0, || unreachable!())), config)
+        };
+        let backoff = Duration::from_millis(100);
+
+        assert_eq!(
+            make_client(2.0).next_backoff(backoff),
+            Duration::from_millis(200)
+        );
+        assert_eq!(
+            make_client(100.0).next_backoff(backoff),
+            Duration::from_secs(1)
+        );
+        // The invalid multipliers don't panic.
+        assert_eq!(
+            make_client(f64::INFINITY).next_backoff(backoff),
+            Duration::from_secs(1)
+        );
+        for multiplier in [0.5, -1.0, f64::NAN, f64::NEG_INFINITY] {
+            assert_eq!(make_client(multiplier).next_backoff(backoff), backoff);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let inner = Arc::new(FlakyClient::new(2, || {
+            Error::Rpc(tonic::Status::unavailable("unavailable"))
+        }));
+        let client = make_retryable_client(inner.clone());
+
+        let ctx = RpcContext::default();
+        client
+            .sql_query(&ctx, &make_sql_query_request())
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let inner = Arc::new(FlakyClient::new(10, || {
+            Error::Server(ServerError {
+                code: 429,
+                msg: "too many requests".to_string(),
+                retry_after: None,
+                request_id: None,
+            })
+        }));
+        let client = make_retryable_client(inner.clone());
+
+        let ctx = RpcContext::default();
+        let res = client.write(&ctx, &WriteRequest::default()).await;
+        assert!(matches!(res, Err(Error::Server(_))));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_hinted_delay() {
+        let inner = Arc::new(FlakyClient::new(1, || {
+            Error::Server(ServerError {
+                code: 429,
+                msg: "too many requests".to_string(),
+                retry_after: Some(Duration::from_millis(50)),
+                request_id: None,
+            })
+        }));
+        let config = RetryConfig {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let client = RetryableClient::new(inner.clone(), config);
+
+        let begin = Instant::now();
+        let ctx = RpcContext::default();
+        client.write(&ctx, &WriteRequest::default()).await.unwrap();
+        // Much longer than the initial backoff.
+        assert!(begin.elapsed() >= Duration::from_millis(50));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_after_capped_by_max_backoff() {
+        let inner = Arc::new(FlakyClient::new(1, || {
+            Error::Server(ServerError {
+                code: 429,
+                msg: "too many requests".to_string(),
+                retry_after: Some(Duration::from_secs(3600)),
+                request_id: None,
+            })
+        }));
+        let client = make_retryable_client(inner.clone());
+
+        let begin = tokio::time::Instant::now();
+        let ctx = RpcContext::default();
+        client.write(&ctx, &WriteRequest::default()).await.unwrap();
+        assert!(begin.elapsed() < Duration::from_secs(1));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_not_retry_applied_write() {
+        let make_errors: [fn() -> Error; 2] = [
+            || Error::Rpc(tonic::Status::deadline_exceeded("timeout")),
+            || Error::Rpc(tonic::Status::unavailable("connection reset")),
+        ];
+        for make_error in make_errors {
+            let inner = Arc::new(FlakyClient::new(1, make_error));
+            let client = make_retryable_client(inner.clone());
+
+            let ctx = RpcContext::default();
+            let res = client.write(&ctx, &WriteRequest::default()).await;
+            assert!(matches!(res, Err(Error::Rpc(_))));
+            assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+            // They are retried for queries.
+            client
+                .sql_query(&ctx, &make_sql_query_request())
+                .await
+                .unwrap();
+            assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_not_retry_partial_write() {
+        let inner = Arc::new(FlakyClient::new(1, || Error::PartialWrite {
+            success: 8,
+            failed: 2,
+            request_id: None,
+        }));
+        let config = RetryConfig {
+            initial_backoff: Duration::from_millis(1),
+            retryable_server_codes: vec![429, 500],
+            ..Default::default()
+        };
+        let client = RetryableClient::new(inner.clone(), config);
+
+        let ctx = RpcContext::default();
+        let res = client.write(&ctx, &WriteRequest::default()).await;
+        assert!(matches!(res, Err(Error::PartialWrite { .. })));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_idempotency_key() {
+        let inner = Arc::new(FlakyClient::new(2, make_connect_error));
+        let client = make_retryable_client(inner.clone());
+
+        // The generated key is reused by all the attempts of one write.
+        let ctx = RpcContext::default();
+        client.write(&ctx, &WriteRequest::default()).await.unwrap();
+        let keys = inner.write_keys.lock().await.clone();
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0].is_some());
+        assert!(keys.iter().all(|key| key == &keys[0]));
+
+        // But not by the next write.
+        client.write(&ctx, &WriteRequest::default()).await.unwrap();
+        let keys = inner.write_keys.lock().await.clone();
+        assert_eq!(keys.len(), 4);
+        assert!(keys[3].is_some());
+        assert_ne!(keys[3], keys[0]);
+
+        // The key set by the user is kept.
+        let inner = Arc::new(FlakyClient::new(1, make_connect_error));
+        let client = make_retryable_client(inner.clone());
+        let ctx = RpcContext::default().idempotency_key("write-1");
+        client.write(&ctx, &WriteRequest::default()).await.unwrap();
+        let keys = inner.write_keys.lock().await.clone();
+        assert_eq!(keys, vec![Some("write-1".to_string()); 2]);
+    }
+}
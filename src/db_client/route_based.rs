@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use dashmap::DashMap;
@@ -23,11 +23,17 @@ use futures::future::join_all;
 use tokio::sync::OnceCell;
 
 use crate::{
-    db_client::{inner::InnerClient, DbClient},
+    db_client::{
+        inner::{InnerClient, InnerConfig},
+        DbClient,
+    },
     errors::RouteBasedWriteError,
     model::{
         route::Endpoint,
-        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
         write::{Request as WriteRequest, Response as WriteResponse},
     },
     router::{Router, RouterImpl},
@@ -43,44 +49,72 @@ pub struct RouteBasedImpl<F: RpcClientFactory> {
     router: OnceCell<Box<dyn Router>>,
     standalone_pool: DirectClientPool<F>,
     default_database: Option<String>,
+    default_tables: Vec<String>,
+    route_cache_ttl: Option<Duration>,
 }
 
+#[allow(clippy::result_large_err)]
 impl<F: RpcClientFactory> RouteBasedImpl<F> {
-    pub fn new(factory: Arc<F>, router_endpoint: String, default_database: Option<String>) -> Self {
+    pub fn new(
+        factory: Arc<F>,
+        router_endpoint: String,
+        default_database: Option<String>,
+        route_cache_ttl: Option<Duration>,
+        inner_config: InnerConfig,
+    ) -> Self {
         Self {
             factory: factory.clone(),
             router_endpoint,
             router: OnceCell::new(),
-            standalone_pool: DirectClientPool::new(factory),
+            standalone_pool: DirectClientPool::new(factory, inner_config),
             default_database,
+            default_tables: Vec::new(),
+            route_cache_ttl,
         }
     }
 
-    async fn init_router(&self) -> Result<Box<dyn Router>> {
-        let router_client = self.factory.build(self.router_endpoint.clone()).await?;
-        let default_endpoint: Endpoint = self.router_endpoint.parse().map_err(|e| {
+    /// The `default_tables` are queried by the requests without any table.
+    pub fn with_default_tables(mut self, default_tables: Vec<String>) -> Self {
+        self.default_tables = default_tables;
+        self
+    }
+
+    fn parse_router_endpoint(&self) -> Result<Endpoint> {
+        self.router_endpoint.parse().map_err(|e| {
             Error::Client(format!(
                 "Failed to parse default endpoint:{}, err:{}",
                 self.router_endpoint, e
             ))
-        })?;
-        Ok(Box::new(RouterImpl::new(default_endpoint, router_client)))
+        })
     }
-}
 
-#[async_trait]
-impl<F: RpcClientFactory> DbClient for RouteBasedImpl<F> {
-    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+    async fn init_router(&self) -> Result<Box<dyn Router>> {
+        let router_client = self.factory.build(self.router_endpoint.clone()).await?;
+        let default_endpoint = self.parse_router_endpoint()?;
+        Ok(Box::new(RouterImpl::new(
+            default_endpoint,
+            router_client,
+            self.route_cache_ttl,
+        )))
+    }
+
+    /// Find the client for the endpoint of the first table in the query
+    /// request.
+    async fn route_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<(Arc<InnerClient<F>>, &dyn Router)> {
+        req.validate()?;
         if req.tables.is_empty() {
             return Err(Error::Unknown(
                 "tables in query request can't be empty in route based mode".to_string(),
             ));
         }
-        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
 
         let router_handle = self.router.get_or_try_init(|| self.init_router()).await?;
 
-        let endpoint = match router_handle.route(&req.tables, &ctx).await {
+        let endpoint = match router_handle.route(&req.tables, ctx).await {
             Ok(mut eps) => {
                 if let Some(ep) = eps[0].take() {
                     ep
@@ -97,10 +131,51 @@ impl<F: RpcClientFactory> DbClient for RouteBasedImpl<F> {
 
         let client = self.standalone_pool.get_or_create(&endpoint).clone();
 
-        client.sql_query_internal(&ctx, req).await.map_err(|e| {
-            router_handle.evict(&req.tables);
-            e
-        })
+        Ok((client, router_handle.as_ref()))
+    }
+}
+
+#[async_trait]
+impl<F: RpcClientFactory> DbClient for RouteBasedImpl<F> {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        let req = crate::db_client::resolve_tables(req, &self.default_tables);
+        let (client, router_handle) = self.route_sql_query(&ctx, &req).await?;
+
+        client
+            .sql_query_internal(&ctx, &req)
+            .await
+            .inspect_err(|_| router_handle.evict(&req.tables))
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        let req = crate::db_client::resolve_tables(req, &self.default_tables);
+        let (client, router_handle) = self.route_sql_query(&ctx, &req).await?;
+
+        client
+            .stream_sql_query_internal(&ctx, &req)
+            .await
+            .inspect_err(|_| router_handle.evict(&req.tables))
+    }
+
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        let req = crate::db_client::resolve_tables(req, &self.default_tables);
+        let (client, router_handle) = self.route_sql_query(&ctx, &req).await?;
+
+        client
+            .sql_query_raw_internal(&ctx, &req)
+            .await
+            .inspect_err(|_| router_handle.evict(&req.tables))
     }
 
     async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
@@ -133,6 +208,11 @@ impl<F: RpcClientFactory> DbClient for RouteBasedImpl<F> {
             });
 
         // Get client and send.
+        // The endpoints are sorted to keep the indexes of the sub-writes, and so
+        // their idempotency keys, the same across the retries.
+        let mut partition_by_endpoint: Vec<_> = partition_by_endpoint.into_iter().collect();
+        partition_by_endpoint
+            .sort_by(|(a, _), (b, _)| (a.addr.as_str(), a.port).cmp(&(b.addr.as_str(), b.port)));
         let mut write_tables = vec![Vec::new(); partition_by_endpoint.len()];
         let client_req_paris: Vec<_> = partition_by_endpoint
             .into_iter()
@@ -144,9 +224,9 @@ impl<F: RpcClientFactory> DbClient for RouteBasedImpl<F> {
             })
             .collect();
         let mut futures = Vec::with_capacity(client_req_paris.len());
-        for (client, req) in client_req_paris {
-            let ctx_clone = ctx.clone();
-            futures.push(async move { client.write_internal(&ctx_clone, &req).await })
+        for (idx, (client, req)) in client_req_paris.into_iter().enumerate() {
+            let sub_ctx = ctx.for_sub_write(idx).into_owned();
+            futures.push(async move { client.write_internal(&sub_ctx, &req).await })
         }
 
         // Await rpc results and collect results.
@@ -193,19 +273,42 @@ impl<F: RpcClientFactory> DbClient for RouteBasedImpl<F> {
             Err(Error::RouteBasedWriteError(route_based_error))
         }
     }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        let ctx = crate::db_client::resolve_database(ctx, &self.default_database)?;
+        // Only the router endpoint is checked, because the data nodes are only
+        // known after routing.
+        let endpoint = self.parse_router_endpoint()?;
+        let client = self.standalone_pool.get_or_create(&endpoint);
+
+        client.health_check_internal(&ctx).await
+    }
+
+    /// Only the router is connected, because the data nodes are only known
+    /// after routing.
+    async fn connect(&self) -> Result<()> {
+        self.router.get_or_try_init(|| self.init_router()).await?;
+        Ok(())
+    }
+
+    fn endpoint(&self) -> Option<&str> {
+        Some(&self.router_endpoint)
+    }
 }
 
 /// DirectClientPool is the pool actually holding connections to data nodes.
 struct DirectClientPool<F: RpcClientFactory> {
     pool: DashMap<Endpoint, Arc<InnerClient<F>>>,
     factory: Arc<F>,
+    inner_config: InnerConfig,
 }
 
 impl<F: RpcClientFactory> DirectClientPool<F> {
-    fn new(factory: Arc<F>) -> Self {
+    fn new(factory: Arc<F>, inner_config: InnerConfig) -> Self {
         Self {
             pool: DashMap::new(),
             factory,
+            inner_config,
         }
     }
 
@@ -220,8 +323,77 @@ impl<F: RpcClientFactory> DirectClientPool<F> {
                 .or_insert(Arc::new(InnerClient::new(
                     self.factory.clone(),
                     endpoint.to_string(),
+                    self.inner_config,
                 )))
                 .clone()
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use super::RouteBasedImpl;
+    use crate::{
+        db_client::{inner::InnerConfig, DbClient},
+        model::{
+            route::Endpoint,
+            value::Value,
+            write::{point::PointBuilder, Request as WriteRequest},
+        },
+        rpc_client::{MockRpcClient, MockRpcClientFactory, RpcContext},
+    };
+
+    #[tokio::test]
+    async fn test_write_idempotency_keys() {
+        let route_table = Arc::new(DashMap::default());
+        route_table.insert("t1".to_string(), Endpoint::new("node_b".to_string(), 1));
+        route_table.insert("t2".to_string(), Endpoint::new("node_a".to_string(), 1));
+        let rpc_client = Arc::new(MockRpcClient::default().with_route_table(route_table));
+        let factory = MockRpcClientFactory {
+            client: rpc_client.clone(),
+        };
+        let client = RouteBasedImpl::new(
+            Arc::new(factory),
+            "router:8831".to_string(),
+            Some("public".to_string()),
+            None,
+            InnerConfig::default(),
+        );
+
+        let mut req = WriteRequest::default();
+        for table in ["t1", "t2"] {
+            let point = PointBuilder::new(table)
+                .timestamp(1)
+                .field("value", Value::Int64(1))
+                .build()
+                .unwrap();
+            req.add_point(point);
+        }
+        let ctx = RpcContext::default().idempotency_key("write-1");
+        client.write(&ctx, &req).await.unwrap();
+
+        // Every endpoint carries its own key, indexed in the order of the
+        // endpoints.
+        let mut keys: Vec<_> = rpc_client
+            .writes()
+            .iter()
+            .zip(rpc_client.write_contexts())
+            .map(|(req, ctx)| {
+                let table = req.table_requests[0].table.clone();
+                (table, ctx.idempotency_key.unwrap())
+            })
+            .collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                ("t1".to_string(), "write-1-1".to_string()),
+                ("t2".to_string(), "write-1-0".to_string()),
+            ]
+        );
+    }
+}
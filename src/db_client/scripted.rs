@@ -0,0 +1,170 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The scripted [`DbClient`] shared by the tests.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use crate::{
+    db_client::DbClient,
+    model::{
+        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse, RowStream},
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+/// The script answering the queries sent to the [`ScriptedClient`].
+type Answer = Box<dyn Fn(&RpcContext, &SqlQueryRequest) -> Result<SqlQueryResponse> + Send + Sync>;
+
+/// Client answering the queries by the script after the delay, and
+/// recording the queries and the writes sent to it.
+pub(crate) struct ScriptedClient {
+    answer: Answer,
+    /// The delay of every query and write, or of every row if it is streamed.
+    delay: Duration,
+    /// Fail the write of the index if set.
+    fail_write_at: Option<usize>,
+    /// Fail every write with the made error if set.
+    write_error: Option<fn() -> Error>,
+    pub(crate) queries: Mutex<Vec<SqlQueryRequest>>,
+    /// The writes written so far.
+    pub(crate) writes: Mutex<Vec<WriteRequest>>,
+    /// The contexts of all the writes sent, including the failed ones.
+    pub(crate) contexts: Mutex<Vec<RpcContext>>,
+    inflight: AtomicUsize,
+    /// The max number of the concurrent queries and writes.
+    pub(crate) max_inflight: AtomicUsize,
+}
+
+impl ScriptedClient {
+    pub(crate) fn new(
+        answer: impl Fn(&RpcContext, &SqlQueryRequest) -> Result<SqlQueryResponse>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            answer: Box::new(answer),
+            delay: Duration::ZERO,
+            fail_write_at: None,
+            write_error: None,
+            queries: Mutex::new(Vec::new()),
+            writes: Mutex::new(Vec::new()),
+            contexts: Mutex::new(Vec::new()),
+            inflight: AtomicUsize::new(0),
+            max_inflight: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub(crate) fn with_fail_write_at(mut self, idx: usize) -> Self {
+        self.fail_write_at = Some(idx);
+        self
+    }
+
+    /// Fail every write with the error made by `make_error`, like an
+    /// unreachable endpoint.
+    pub(crate) fn with_write_error(mut self, make_error: fn() -> Error) -> Self {
+        self.write_error = Some(make_error);
+        self
+    }
+
+    /// The sqls of the queries sent so far.
+    pub(crate) fn sqls(&self) -> Vec<String> {
+        let queries = self.queries.lock().unwrap();
+        queries.iter().map(|req| req.sql.clone()).collect()
+    }
+
+    /// The number of the points of the writes written so far.
+    pub(crate) fn written_points(&self) -> Vec<usize> {
+        let writes = self.writes.lock().unwrap();
+        writes.iter().map(count_points).collect()
+    }
+
+    /// Wait for the delay, counting the request in flight meanwhile.
+    async fn serve(&self) {
+        let inflight = self.inflight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_inflight.fetch_max(inflight, Ordering::SeqCst);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        self.inflight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn count_points(req: &WriteRequest) -> usize {
+    req.point_groups.values().map(|points| points.len()).sum()
+}
+
+#[allow(clippy::result_large_err)]
+impl Default for ScriptedClient {
+    /// Answer every query with the empty response.
+    fn default() -> Self {
+        Self::new(|_, _| Ok(SqlQueryResponse::default()))
+    }
+}
+
+#[async_trait]
+impl DbClient for ScriptedClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        self.queries.lock().unwrap().push(req.clone());
+        self.serve().await;
+
+        (self.answer)(ctx, req)
+    }
+
+    async fn stream_sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<RowStream> {
+        self.queries.lock().unwrap().push(req.clone());
+        let resp = (self.answer)(ctx, req)?;
+
+        let delay = self.delay;
+        let rows = stream::iter(resp.rows).then(move |row| async move {
+            tokio::time::sleep(delay).await;
+            Ok(row)
+        });
+        Ok(rows.boxed())
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        self.contexts.lock().unwrap().push(ctx.clone());
+        if let Some(make_error) = self.write_error {
+            return Err(make_error());
+        }
+        if self.fail_write_at == Some(self.writes.lock().unwrap().len()) {
+            return Err(Error::Rpc(tonic::Status::unavailable("unavailable")));
+        }
+        self.serve().await;
+
+        self.writes.lock().unwrap().push(req.clone());
+        Ok(WriteResponse::new(count_points(req) as u32, 0))
+    }
+}
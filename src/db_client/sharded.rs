@@ -0,0 +1,471 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use futures::{future, stream, StreamExt};
+
+use crate::{
+    db_client::DbClient,
+    errors::RouteBasedWriteError,
+    model::{
+        sql_query::{
+            RawResponse as SqlQueryRawResponse, Request as SqlQueryRequest,
+            Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+/// Client sharding the tables among the wrapped [`DbClient`]s of different
+/// endpoints by the hashes of their names, which spreads the writes without
+/// routing them by the server.
+///
+/// A table is always assigned to the same shard as long as the number of the
+/// shards is kept, see [`shard_of`](ShardedWriteClient::shard_of). The write
+/// is split by the shards of its tables, and [`Error::RouteBasedWriteError`]
+/// holding the written tables is returned if it fails in some of the shards.
+/// The query is sent to the shards of its tables, or all of them if it
+/// involves no table, and the responses are merged, so the sql should return
+/// the same columns in every shard.
+pub struct ShardedWriteClient {
+    shards: Vec<Arc<dyn DbClient>>,
+}
+
+#[allow(clippy::result_large_err)]
+impl ShardedWriteClient {
+    /// Shard the tables among the `shards`, and
+    /// [`Error::Client`](crate::Error::Client) is returned if there is none.
+    pub fn new(shards: Vec<Arc<dyn DbClient>>) -> Result<Self> {
+        if shards.is_empty() {
+            return Err(Error::Client("No shards to write".to_string()));
+        }
+
+        Ok(Self { shards })
+    }
+
+    /// The index of the shard which the `table` is assigned to.
+    pub fn shard_of(&self, table: &str) -> usize {
+        (fnv1a(table.as_bytes()) % self.shards.len() as u64) as usize
+    }
+
+    /// Split the query into the ones of the tables in the same shards.
+    fn split_query(&self, req: &SqlQueryRequest) -> Vec<(usize, SqlQueryRequest)> {
+        if req.tables.is_empty() {
+            return (0..self.shards.len())
+                .map(|idx| (idx, req.clone()))
+                .collect();
+        }
+
+        let mut tables_by_shard: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for table in &req.tables {
+            tables_by_shard
+                .entry(self.shard_of(table))
+                .or_default()
+                .push(table.clone());
+        }
+        tables_by_shard
+            .into_iter()
+            .map(|(idx, tables)| {
+                let req = SqlQueryRequest {
+                    tables,
+                    sql: req.sql.clone(),
+                };
+                (idx, req)
+            })
+            .collect()
+    }
+}
+
+/// The 64-bit FNV-1a hash, which is stable across the processes and the
+/// versions unlike the hashers of std.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+#[async_trait]
+impl DbClient for ShardedWriteClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: &SqlQueryRequest) -> Result<SqlQueryResponse> {
+        let reqs = self.split_query(req);
+        let queries = reqs
+            .iter()
+            .map(|(idx, req)| self.shards[*idx].sql_query(ctx, req));
+        let mut resps = future::try_join_all(queries).await?.into_iter();
+
+        let mut merged = resps.next().unwrap();
+        for resp in resps {
+            merged.merge(resp)?;
+        }
+        Ok(merged)
+    }
+
+    /// The rows of the shards are streamed one shard after another.
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryStream> {
+        let reqs = self.split_query(req);
+        let queries = reqs
+            .iter()
+            .map(|(idx, req)| self.shards[*idx].stream_sql_query(ctx, req));
+        let streams = future::try_join_all(queries).await?;
+
+        Ok(stream::iter(streams).flatten().boxed())
+    }
+
+    /// The raw payloads can't be merged, so the tables must be in one shard.
+    async fn sql_query_raw(
+        &self,
+        ctx: &RpcContext,
+        req: &SqlQueryRequest,
+    ) -> Result<SqlQueryRawResponse> {
+        let mut reqs = self.split_query(req);
+        if reqs.len() > 1 {
+            return Err(Error::Client(format!(
+                "Raw query of tables in different shards is not supported, tables:{:?}",
+                req.tables
+            )));
+        }
+
+        let (idx, req) = reqs.pop().unwrap();
+        self.shards[idx].sql_query_raw(ctx, &req).await
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+        let mut reqs: BTreeMap<_, WriteRequest> = BTreeMap::new();
+        for (table, points) in &req.point_groups {
+            reqs.entry(self.shard_of(table))
+                .or_default()
+                .point_groups
+                .insert(table.clone(), points.clone());
+        }
+        // Sent as it is if all the tables are in one shard.
+        if reqs.len() <= 1 {
+            let idx = reqs.into_keys().next().unwrap_or_default();
+            return self.shards[idx].write(ctx, req).await;
+        }
+
+        // The sub-writes are keyed by the shards, which are stable across the
+        // retries.
+        let writes = reqs.iter().map(|(idx, req)| async move {
+            let tables = req.point_groups.keys().cloned().collect::<Vec<_>>();
+            let sub_ctx = ctx.for_sub_write(*idx);
+            (tables, self.shards[*idx].write(&sub_ctx, req).await)
+        });
+        let write_error: RouteBasedWriteError = future::join_all(writes).await.into();
+        if write_error.all_ok() {
+            Ok(write_error.ok.1)
+        } else {
+            Err(Error::RouteBasedWriteError(write_error))
+        }
+    }
+
+    /// All the shards must be healthy.
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        future::try_join_all(self.shards.iter().map(|shard| shard.health_check(ctx))).await?;
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<()> {
+        future::try_join_all(self.shards.iter().map(|shard| shard.connect())).await?;
+        Ok(())
+    }
+
+    /// The endpoint of the first shard.
+    fn endpoint(&self) -> Option<&str> {
+        self.shards[0].endpoint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::{fnv1a, ShardedWriteClient};
+    use crate::{
+        db_client::DbClient,
+        errors::ServerError,
+        model::{
+            sql_query::{
+                row::{Column, Row},
+                Request as SqlQueryRequest, Response as SqlQueryResponse,
+                RowStream as SqlQueryStream,
+            },
+            value::Value,
+            write::{point::PointBuilder, Request as WriteRequest, Response as WriteResponse},
+        },
+        rpc_client::RpcContext,
+        Error, Result,
+    };
+
+    /// Client recording the tables of the requests, and responding its index
+    /// as the rows.
+    #[derive(Default)]
+    struct ShardClient {
+        idx: i64,
+        fail_write: bool,
+        queried: Mutex<Vec<Vec<String>>>,
+        written: Mutex<Vec<Vec<String>>>,
+        idempotency_keys: Mutex<Vec<Option<String>>>,
+    }
+
+    impl ShardClient {
+        fn new(idx: i64) -> Self {
+            Self {
+                idx,
+                ..Default::default()
+            }
+        }
+
+        fn row(&self) -> Row {
+            Row::new(vec![Column::new(
+                "shard".to_string(),
+                Value::Int64(self.idx),
+            )])
+        }
+    }
+
+    #[async_trait]
+    impl DbClient for ShardClient {
+        async fn sql_query(
+            &self,
+            _ctx: &RpcContext,
+            req: &SqlQueryRequest,
+        ) -> Result<SqlQueryResponse> {
+            self.queried.lock().unwrap().push(req.tables.clone());
+            Ok(SqlQueryResponse::with_rows(vec![self.row()]))
+        }
+
+        async fn stream_sql_query(
+            &self,
+            _ctx: &RpcContext,
+            req: &SqlQueryRequest,
+        ) -> Result<SqlQueryStream> {
+            self.queried.lock().unwrap().push(req.tables.clone());
+            Ok(futures::stream::iter(vec![Ok(self.row())]).boxed())
+        }
+
+        async fn write(&self, ctx: &RpcContext, req: &WriteRequest) -> Result<WriteResponse> {
+            self.idempotency_keys
+                .lock()
+                .unwrap()
+                .push(ctx.idempotency_key.clone());
+            let mut tables: Vec<_> = req.point_groups.keys().cloned().collect();
+            tables.sort();
+            self.written.lock().unwrap().push(tables);
+            if self.fail_write {
+                return Err(Error::Server(ServerError {
+                    code: 500,
+                    msg: "internal".to_string(),
+                    retry_after: None,
+                    request_id: None,
+                }));
+            }
+
+            let points: usize = req.point_groups.values().map(|points| points.len()).sum();
+            Ok(WriteResponse::new(points as u32, 0))
+        }
+    }
+
+    fn make_shards() -> Vec<Arc<ShardClient>> {
+        (0..3).map(|idx| Arc::new(ShardClient::new(idx))).collect()
+    }
+
+    fn make_client(shards: &[Arc<ShardClient>]) -> ShardedWriteClient {
+        ShardedWriteClient::new(
+            shards
+                .iter()
+                .map(|shard| shard.clone() as Arc<dyn DbClient>)
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn make_write_request(tables: &[&str]) -> WriteRequest {
+        let mut req = WriteRequest::default();
+        for table in tables {
+            let point = PointBuilder::new(table.to_string())
+                .timestamp(1000)
+                .field("value".to_string(), Value::Int64(1))
+                .build()
+                .unwrap();
+            req.add_point(point);
+        }
+        req
+    }
+
+    #[test]
+    fn test_shard_of() {
+        // The well known vectors of the 64-bit FNV-1a.
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        // The assignment only depends on the name and the number of shards.
+        let tables = ["cpu", "mem", "disk", "net", "load"];
+        let expected = [2, 2, 1, 1, 0];
+        for _ in 0..2 {
+            let client = make_client(&make_shards());
+            let assigned: Vec<_> = tables.iter().map(|table| client.shard_of(table)).collect();
+            assert_eq!(assigned, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sharded_write() {
+        let shards = make_shards();
+        let client = make_client(&shards);
+        let ctx = RpcContext::default();
+
+        let resp = client
+            .write(&ctx, &make_write_request(&["cpu", "mem", "disk", "load"]))
+            .await
+            .unwrap();
+        assert_eq!(resp.success, 4);
+        let written: Vec<_> = shards
+            .iter()
+            .map(|shard| shard.written.lock().unwrap().clone())
+            .collect();
+        assert_eq!(
+            written,
+            vec![
+                vec![vec!["load".to_string()]],
+                vec![vec!["disk".to_string()]],
+                vec![vec!["cpu".to_string(), "mem".to_string()]],
+            ]
+        );
+
+        // The tables of the failed shard are reported.
+        let mut shards = make_shards();
+        shards[1] = Arc::new(ShardClient {
+            fail_write: true,
+            ..ShardClient::new(1)
+        });
+        let client = make_client(&shards);
+        match client
+            .write(&ctx, &make_write_request(&["cpu", "disk", "net"]))
+            .await
+        {
+            Err(Error::RouteBasedWriteError(e)) => {
+                assert_eq!(e.ok.0, vec!["cpu".to_string()]);
+                assert_eq!(e.ok.1.success, 1);
+                let mut failed = e.errors[0].0.clone();
+                failed.sort();
+                assert_eq!(failed, vec!["disk".to_string(), "net".to_string()]);
+            }
+            other => panic!("unexpected result:{other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sharded_write_idempotency_keys() {
+        let shards = make_shards();
+        let client = make_client(&shards);
+        let ctx = RpcContext::default().idempotency_key("write-1");
+
+        // Every shard carries its own key, suffixed by its index.
+        client
+            .write(&ctx, &make_write_request(&["cpu", "disk", "load"]))
+            .await
+            .unwrap();
+        let keys: Vec<_> = shards
+            .iter()
+            .map(|shard| shard.idempotency_keys.lock().unwrap().clone())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                vec![Some("write-1-0".to_string())],
+                vec![Some("write-1-1".to_string())],
+                vec![Some("write-1-2".to_string())],
+            ]
+        );
+
+        // The key is kept as it is if the write is not split.
+        let shards = make_shards();
+        let client = make_client(&shards);
+        client
+            .write(&ctx, &make_write_request(&["cpu", "mem"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            shards[2].idempotency_keys.lock().unwrap().clone(),
+            vec![Some("write-1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_no_shards() {
+        assert!(matches!(
+            ShardedWriteClient::new(vec![]),
+            Err(Error::Client(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sharded_query() {
+        let shards = make_shards();
+        let client = make_client(&shards);
+        let ctx = RpcContext::default();
+
+        // Fan out to the shards of the tables, and the rows are merged.
+        let req = SqlQueryRequest {
+            tables: vec!["cpu".to_string(), "disk".to_string(), "mem".to_string()],
+            sql: "select * from t".to_string(),
+        };
+        let resp = client.sql_query(&ctx, &req).await.unwrap();
+        let values: Vec<_> = resp.iter_rows().map(|row| row.get_i64("shard")).collect();
+        assert_eq!(values, vec![Some(1), Some(2)]);
+        assert!(shards[0].queried.lock().unwrap().is_empty());
+        assert_eq!(
+            shards[2].queried.lock().unwrap().clone(),
+            vec![vec!["cpu".to_string(), "mem".to_string()]]
+        );
+
+        let rows: Vec<_> = client
+            .stream_sql_query(&ctx, &req)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(
+            client.sql_query_raw(&ctx, &req).await,
+            Err(Error::Client(_))
+        ));
+
+        // All the shards without any table.
+        let req = SqlQueryRequest {
+            tables: vec![],
+            sql: "SHOW TABLES".to_string(),
+        };
+        let resp = client.sql_query(&ctx, &req).await.unwrap();
+        assert_eq!(resp.rows.len(), 3);
+    }
+}
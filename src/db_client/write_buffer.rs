@@ -0,0 +1,360 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    future::{self, Either},
+    stream::BoxStream,
+    StreamExt,
+};
+use tokio::sync::Mutex as AsyncMutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    config::WriteBufferConfig,
+    db_client::DbClient,
+    model::write::{point::Point, Request as WriteRequest, Response as WriteResponse},
+    rpc_client::RpcContext,
+    Result,
+};
+
+/// The lower bound of [`WriteBufferConfig::flush_interval`].
+const MIN_FLUSH_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Buffer batching the points pushed one by one, and writing them by the
+/// [`DbClient`] when [`WriteBufferConfig::max_rows`] points are buffered or
+/// every [`WriteBufferConfig::flush_interval`], whichever comes first.
+///
+/// The results of these flushes are sent to the stream returned along with the
+/// buffer. The buffered points are discarded once the buffer is dropped, so
+/// [`flush`](WriteBuffer::flush) should be called before that, e.g. on the
+/// shutdown. The flush in progress is completed rather than cancelled by the
+/// drop though.
+///
+/// The flushes carry the [`RpcContext::idempotency_key`] suffixed by their
+/// sequence numbers if it is set, since they are different writes.
+///
+/// The points of a failed flush are dropped rather than buffered again, and
+/// only the error is reported, so the `client` should be a
+/// [`RetryableClient`](crate::RetryableClient) if they are expected to
+/// survive the transient failures.
+pub struct WriteBuffer {
+    shared: Arc<Shared>,
+    /// Stop the interval flushes.
+    stopped: CancellationToken,
+}
+
+struct Shared {
+    client: Arc<dyn DbClient>,
+    ctx: RpcContext,
+    max_rows: usize,
+    buffered: Mutex<Buffered>,
+    /// Held from taking the buffered points until they are written, so that
+    /// the flushes are serialized.
+    flushing: AsyncMutex<()>,
+    /// The number of the flushes sent.
+    flushes: AtomicUsize,
+    results: UnboundedSender<Result<WriteResponse>>,
+}
+
+#[derive(Default)]
+struct Buffered {
+    req: WriteRequest,
+    rows: usize,
+}
+
+impl Shared {
+    fn take(&self) -> Option<WriteRequest> {
+        let mut buffered = self.buffered.lock().unwrap();
+        if buffered.rows == 0 {
+            return None;
+        }
+
+        Some(mem::take(&mut *buffered).req)
+    }
+
+    async fn flush(&self) -> Option<Result<WriteResponse>> {
+        let _flushing = self.flushing.lock().await;
+        let req = self.take()?;
+        let seq = self.flushes.fetch_add(1, Ordering::Relaxed);
+        Some(self.client.write(&self.ctx.for_sub_write(seq), &req).await)
+    }
+
+    async fn auto_flush(&self) {
+        if let Some(result) = self.flush().await {
+            // Nobody cares about the results if the stream is dropped.
+            let _ = self.results.unbounded_send(result);
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl WriteBuffer {
+    /// Make the buffer writing by the `client` with the `ctx`, and the stream
+    /// of the results of the automatic flushes.
+    ///
+    /// It must be called in the tokio runtime, where the interval flushes run.
+    pub fn new(
+        client: Arc<dyn DbClient>,
+        ctx: RpcContext,
+        config: WriteBufferConfig,
+    ) -> (Self, BoxStream<'static, Result<WriteResponse>>) {
+        let (results, results_rx) = mpsc::unbounded();
+        let shared = Arc::new(Shared {
+            client,
+            ctx,
+            max_rows: config.max_rows.max(1),
+            buffered: Mutex::new(Buffered::default()),
+            flushing: AsyncMutex::new(()),
+            flushes: AtomicUsize::new(0),
+            results,
+        });
+        let stopped = CancellationToken::new();
+        tokio::spawn(Self::tick(Arc::downgrade(&shared), config, stopped.clone()));
+
+        (Self { shared, stopped }, results_rx.boxed())
+    }
+
+    async fn tick(shared: Weak<Shared>, config: WriteBufferConfig, stopped: CancellationToken) {
+        // The interval panics on the zero period.
+        let period = config.flush_interval.max(MIN_FLUSH_INTERVAL);
+        let mut interval = tokio::time::interval(period);
+        // The first tick completes immediately.
+        interval.tick().await;
+        loop {
+            // Only the wait for the tick is stopped, but not the flush.
+            let tick = interval.tick();
+            let cancelled = stopped.cancelled();
+            futures::pin_mut!(tick, cancelled);
+            if let Either::Right(_) = future::select(tick, cancelled).await {
+                return;
+            }
+
+            match shared.upgrade() {
+                Some(shared) => shared.auto_flush().await,
+                None => return,
+            }
+        }
+    }
+
+    /// Buffer the point, and flush the buffered points if
+    /// [`WriteBufferConfig::max_rows`] is reached.
+    pub async fn push(&self, point: Point) {
+        let full = {
+            let mut buffered = self.shared.buffered.lock().unwrap();
+            buffered.req.add_point(point);
+            buffered.rows += 1;
+            buffered.rows >= self.shared.max_rows
+        };
+        if full {
+            self.shared.auto_flush().await;
+        }
+    }
+
+    /// The number of the buffered points.
+    pub fn buffered_rows(&self) -> usize {
+        self.shared.buffered.lock().unwrap().rows
+    }
+
+    /// Write the buffered points at once, and its result is returned instead
+    /// of being sent to the stream.
+    ///
+    /// It waits for the automatic flush in progress if any, of which the result
+    /// is still sent to the stream.
+    pub async fn flush(&self) -> Result<WriteResponse> {
+        self.shared
+            .flush()
+            .await
+            .unwrap_or_else(|| Ok(WriteResponse::new(0, 0)))
+    }
+}
+
+impl Drop for WriteBuffer {
+    fn drop(&mut self) {
+        self.stopped.cancel();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use futures::StreamExt;
+
+    use super::WriteBuffer;
+    use crate::{
+        config::WriteBufferConfig,
+        db_client::scripted::ScriptedClient,
+        model::{
+            value::Value,
+            write::point::{Point, PointBuilder},
+        },
+        rpc_client::RpcContext,
+    };
+
+    fn make_point(table: &str, ts: i64) -> Point {
+        PointBuilder::new(table)
+            .timestamp(ts)
+            .field("value", Value::Int64(ts))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_size_triggered_flush() {
+        let client = Arc::new(ScriptedClient::default());
+        let config = WriteBufferConfig {
+            max_rows: 3,
+            flush_interval: Duration::from_secs(3600),
+        };
+        let (buffer, mut results) = WriteBuffer::new(client.clone(), RpcContext::default(), config);
+
+        for ts in 0..7 {
+            let table = if ts % 2 == 0 { "t1" } else { "t2" };
+            buffer.push(make_point(table, ts)).await;
+        }
+        assert_eq!(client.written_points(), vec![3, 3]);
+        assert_eq!(buffer.buffered_rows(), 1);
+        for _ in 0..2 {
+            assert_eq!(results.next().await.unwrap().unwrap().success, 3);
+        }
+
+        // The remaining point is drained by the final flush.
+        assert_eq!(buffer.flush().await.unwrap().success, 1);
+        assert_eq!(buffer.buffered_rows(), 0);
+        assert_eq!(buffer.flush().await.unwrap().success, 0);
+        assert_eq!(client.written_points(), vec![3, 3, 1]);
+
+        drop(buffer);
+        assert!(results.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_time_triggered_flush() {
+        let client = Arc::new(ScriptedClient::default());
+        let config = WriteBufferConfig {
+            max_rows: 100,
+            flush_interval: Duration::from_secs(1),
+        };
+        let (buffer, mut results) = WriteBuffer::new(client.clone(), RpcContext::default(), config);
+
+        buffer.push(make_point("t1", 1)).await;
+        buffer.push(make_point("t1", 2)).await;
+        assert_eq!(results.next().await.unwrap().unwrap().success, 2);
+        assert_eq!(buffer.buffered_rows(), 0);
+
+        // Nothing is written if no points are buffered.
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        buffer.push(make_point("t1", 3)).await;
+        assert_eq!(results.next().await.unwrap().unwrap().success, 1);
+        assert_eq!(client.written_points(), vec![2, 1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_zero_flush_interval() {
+        let client = Arc::new(ScriptedClient::default());
+        let config = WriteBufferConfig {
+            max_rows: 100,
+            flush_interval: Duration::ZERO,
+        };
+        let (buffer, mut results) = WriteBuffer::new(client.clone(), RpcContext::default(), config);
+
+        buffer.push(make_point("t1", 1)).await;
+        assert_eq!(results.next().await.unwrap().unwrap().success, 1);
+        assert_eq!(buffer.buffered_rows(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flush_during_tick_flush() {
+        let client = Arc::new(ScriptedClient::default().with_delay(Duration::from_secs(1)));
+        let config = WriteBufferConfig {
+            max_rows: 100,
+            flush_interval: Duration::from_millis(100),
+        };
+        let (buffer, mut results) = WriteBuffer::new(client.clone(), RpcContext::default(), config);
+
+        // The tick flush is in progress.
+        buffer.push(make_point("t1", 1)).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(buffer.buffered_rows(), 0);
+        assert!(client.written_points().is_empty());
+
+        // The flush returns after the tick flush is done.
+        assert_eq!(buffer.flush().await.unwrap().success, 0);
+        assert_eq!(client.written_points(), vec![1]);
+
+        drop(buffer);
+        assert_eq!(results.next().await.unwrap().unwrap().success, 1);
+        assert!(results.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drop_during_tick_flush() {
+        let client = Arc::new(ScriptedClient::default().with_delay(Duration::from_secs(1)));
+        let config = WriteBufferConfig {
+            max_rows: 100,
+            flush_interval: Duration::from_millis(100),
+        };
+        let (buffer, mut results) = WriteBuffer::new(client.clone(), RpcContext::default(), config);
+
+        buffer.push(make_point("t1", 1)).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        drop(buffer);
+
+        // The flush in progress is not cancelled by the drop.
+        assert_eq!(results.next().await.unwrap().unwrap().success, 1);
+        assert!(results.next().await.is_none());
+        assert_eq!(client.written_points(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_flush_idempotency_key() {
+        let client = Arc::new(ScriptedClient::default());
+        let config = WriteBufferConfig {
+            max_rows: 1,
+            flush_interval: Duration::from_secs(3600),
+        };
+        let ctx = RpcContext::default().idempotency_key("buffer-1");
+        let (buffer, _results) = WriteBuffer::new(client.clone(), ctx, config);
+
+        buffer.push(make_point("t1", 1)).await;
+        buffer.push(make_point("t1", 2)).await;
+        let keys: Vec<_> = client
+            .contexts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|ctx| ctx.idempotency_key.clone())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                Some("buffer-1-0".to_string()),
+                Some("buffer-1-1".to_string())
+            ]
+        );
+    }
+}
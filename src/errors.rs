@@ -15,12 +15,19 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
+use arrow::{datatypes::DataType as ArrowDataType, error::ArrowError};
 use thiserror::Error as ThisError;
 
-use crate::model::write::Response;
+use crate::{
+    model::{value::DataType as ValueDataType, write::Response},
+    rpc_client::REQUEST_ID_KEY,
+};
 
+// The `Rpc` variant makes the error large, and the functions returning it
+// allow `clippy::result_large_err`, as boxing it would only add an allocation
+// on the error path.
 /// An error generated by the client.
 #[derive(Debug, ThisError)]
 pub enum Error {
@@ -43,6 +50,21 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    /// Error from the request exceeding its timeout, see
+    /// [`RpcContext::timeout`](crate::RpcContext::timeout).
+    ///
+    /// The `limit` is the timeout of the request, and its [`grpc_code`] is
+    /// `DeadlineExceeded`.
+    ///
+    /// [`grpc_code`]: Error::grpc_code
+    #[error("timed out, elapsed:{elapsed:?}, limit:{limit:?}")]
+    Timeout {
+        elapsed: Duration,
+        limit: Duration,
+        /// The id of the timed out request, see [`Error::request_id`].
+        request_id: Option<String>,
+    },
+
     /// Error from the client and basically the rpc request has not been called
     /// yet or the rpc request has already been finished successfully.
     #[error("failed in client, msg:{0}")]
@@ -57,19 +79,51 @@ pub enum Error {
     #[error("failed to write with route based client, err:{0}")]
     RouteBasedWriteError(RouteBasedWriteError),
 
+    /// Error from the write of which some rows are written but the others
+    /// fail, see [`RpcConfig::fail_on_partial_write`].
+    ///
+    /// It is never retried, since the written rows would be written again.
+    ///
+    /// [`RpcConfig::fail_on_partial_write`]: crate::RpcConfig::fail_on_partial_write
+    #[error("partial write failure, success:{success}, failed:{failed}")]
+    PartialWrite {
+        success: u32,
+        failed: u32,
+        /// The id of the failed request.
+        request_id: Option<String>,
+    },
+
+    /// Error from the chunked write, the chunks before the failed one are
+    /// written, and the ones after it are not written.
+    #[error("failed to write chunk:{succeeded_chunks} of chunks:{total_chunks}, err:{source}")]
+    ChunkedWrite {
+        /// The number of the chunks written successfully.
+        succeeded_chunks: usize,
+        total_chunks: usize,
+        /// The response merged from the written chunks.
+        written: Response,
+        source: Box<Error>,
+    },
+
     /// Error unknown
     #[error("unknown error, msg:{0}")]
     Unknown(String),
 
-    #[error("failed to decode, msg:{0}")]
-    BuildRows(String),
-
-    #[error("failed to decode arrow payload, msg:{0}")]
-    DecodeArrowPayload(Box<dyn std::error::Error + Send + Sync>),
+    /// Error from converting the queried arrow payload into the rows.
+    #[error("failed to convert queried rows, err:{0}")]
+    Convert(ConvertError),
 
     #[error("failed to find a database")]
     NoDatabase,
 
+    /// Error from deserializing the rows into the user defined types.
+    #[error("failed to deserialize row, msg:{0}")]
+    Deserialize(String),
+
+    /// Error from the server not supporting the call, e.g. the older ones.
+    #[error("unsupported by server, msg:{0}")]
+    Unsupported(String),
+
     #[error(transparent)]
     Other {
         #[from]
@@ -77,6 +131,41 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// The grpc status code of the error, which tells e.g. the unavailable
+    /// server from the invalid argument without matching the variants.
+    ///
+    /// The connection failures are regarded as `Unavailable` as the grpc
+    /// does, and `None` is returned for the errors not from the grpc.
+    pub fn grpc_code(&self) -> Option<tonic::Code> {
+        match self {
+            Error::Rpc(status) => Some(status.code()),
+            Error::Connect { .. } => Some(tonic::Code::Unavailable),
+            Error::Timeout { .. } => Some(tonic::Code::DeadlineExceeded),
+            _ => None,
+        }
+    }
+
+    /// The id of the failed request, see
+    /// [`RpcContext::request_id`](crate::RpcContext::request_id).
+    ///
+    /// It is known only for the errors from the server and the grpc, and the
+    /// timeouts of the requests sent.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Error::Server(e) => e.request_id.as_deref(),
+            Error::PartialWrite { request_id, .. } | Error::Timeout { request_id, .. } => {
+                request_id.as_deref()
+            }
+            Error::Rpc(status) => status
+                .metadata()
+                .get(REQUEST_ID_KEY)
+                .and_then(|id| id.to_str().ok()),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RouteBasedWriteError {
     pub ok: (Vec<String>, Response),       // (tables, write_response)
@@ -85,15 +174,13 @@ pub struct RouteBasedWriteError {
 
 impl From<Vec<(Vec<String>, Result<Response>)>> for RouteBasedWriteError {
     fn from(write_results: Vec<(Vec<String>, Result<Response>)>) -> Self {
-        let mut success_total = 0;
-        let mut failed_total = 0;
+        let mut ok_resp = Response::new(0, 0);
         let mut ok_tables = Vec::new();
         let mut errors = Vec::new();
         for (tables, write_result) in write_results {
             match write_result {
                 Ok(write_resp) => {
-                    success_total += write_resp.success;
-                    failed_total += write_resp.failed;
+                    ok_resp.merge(write_resp);
                     ok_tables.extend(tables);
                 }
                 Err(e) => {
@@ -103,7 +190,7 @@ impl From<Vec<(Vec<String>, Result<Response>)>> for RouteBasedWriteError {
         }
 
         Self {
-            ok: (ok_tables, Response::new(success_total, failed_total)),
+            ok: (ok_tables, ok_resp),
             errors,
         }
     }
@@ -124,10 +211,83 @@ impl Display for RouteBasedWriteError {
     }
 }
 
+/// The reason why the queried arrow payload can't be converted into the rows.
+///
+/// The payload consists of the byte batches, and `batch_index` is the index of
+/// the failed one in them.
+#[derive(Debug, ThisError)]
+pub enum ConvertError {
+    #[error("failed to decompress batch:{batch_index}, err:{source}")]
+    Decompress {
+        batch_index: usize,
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse schema of batch:{batch_index}, err:{source}")]
+    SchemaParse {
+        batch_index: usize,
+        source: ArrowError,
+    },
+
+    #[error("failed to parse rows of batch:{batch_index}, err:{source}")]
+    RowParse {
+        batch_index: usize,
+        source: ArrowError,
+    },
+
+    /// The rows of the batch are of the different width from the ones of the
+    /// first batch.
+    #[error(
+        "mismatched column count of batch:{batch_index}, expected:{expected}, actual:{actual}"
+    )]
+    ColumnCountMismatch {
+        batch_index: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("unsupported arrow type:{0}")]
+    UnsupportedType(ArrowDataType),
+
+    /// The value of the column is out of the range of the type it is converted
+    /// to, e.g. the seconds beyond the timestamp in milliseconds.
+    #[error("value:{value} of column:{column} overflows {target:?}")]
+    Overflow {
+        column: String,
+        value: String,
+        target: ValueDataType,
+    },
+
+    /// The struct column is nested deeper than `max_depth`.
+    #[error("nesting of column:{column} is deeper than {max_depth}")]
+    NestingTooDeep { column: String, max_depth: usize },
+}
+
+/// The error reported by the server.
+///
+/// More fields may be added, so it is made by [`ServerError::new`] outside of
+/// the crate, e.g. in the mocked clients.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ServerError {
     pub code: u32,
     pub msg: String,
+    /// How long to wait before retrying, if it is hinted by the server in the
+    /// `x-horaedb-retry-after-ms` metadata.
+    pub retry_after: Option<Duration>,
+    /// The id of the failed request.
+    pub request_id: Option<String>,
+}
+
+impl ServerError {
+    pub fn new(code: u32, msg: impl Into<String>) -> Self {
+        Self {
+            code,
+            msg: msg.into(),
+            retry_after: None,
+            request_id: None,
+        }
+    }
 }
 
 impl Display for ServerError {
@@ -135,6 +295,8 @@ impl Display for ServerError {
         f.debug_struct("ServerError")
             .field("code", &self.code)
             .field("msg", &self.msg)
+            .field("retry_after", &self.retry_after)
+            .field("request_id", &self.request_id)
             .finish()
     }
 }
@@ -182,4 +344,28 @@ mod test {
             r#"failed to connect, addr:"1.1.1.1:1111", err:Unknown("unknown error")"#
         );
     }
+
+    #[test]
+    fn test_grpc_code() {
+        let status = tonic::Status::with_details(
+            tonic::Code::InvalidArgument,
+            "bad table",
+            b"details".to_vec().into(),
+        );
+        let err = Error::Rpc(status);
+        assert_eq!(err.grpc_code(), Some(tonic::Code::InvalidArgument));
+        let Error::Rpc(status) = &err else {
+            unreachable!()
+        };
+        assert_eq!(status.message(), "bad table");
+        assert_eq!(status.details(), b"details");
+        assert!(format!("{err}").contains("bad table"));
+
+        let err = Error::Connect {
+            addr: "1.1.1.1:1111".to_string(),
+            source: "refused".into(),
+        };
+        assert_eq!(err.grpc_code(), Some(tonic::Code::Unavailable));
+        assert_eq!(Error::Client("x".to_string()).grpc_code(), None);
+    }
 }
@@ -76,24 +76,54 @@
 //! # }
 //! ```
 
+mod blocking;
 mod config;
 #[doc(hidden)]
 pub mod db_client;
 mod errors;
+#[cfg(feature = "metrics")]
+mod metrics;
 #[doc(hidden)]
 pub mod model;
 mod router;
 mod rpc_client;
+#[cfg(feature = "tracing")]
+mod trace;
 mod util;
 
+/// Used to cancel the queries by [`DbClient::sql_query_with_cancel`].
+pub use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "tls")]
+#[doc(inline)]
+pub use crate::config::TlsConfig;
+#[cfg(feature = "test-util")]
+#[doc(inline)]
+pub use crate::db_client::MockDbClient;
+#[cfg(feature = "metrics")]
+#[doc(inline)]
+pub use crate::metrics::ClientMetrics;
 #[doc(inline)]
 pub use crate::{
-    config::{Authorization, RpcConfig},
-    db_client::{Builder, DbClient, Mode},
-    errors::{Error, Result},
+    blocking::BlockingClient,
+    config::{
+        Authorization, Compression, LoadBalancing, RetryConfig, RpcConfig, WriteBufferConfig,
+    },
+    db_client::{
+        Builder, CachingClient, ConcurrencyLimitedClient, DbClient, FailoverClient, Mode,
+        RetryableClient, ShardedWriteClient, WriteBuffer,
+    },
+    errors::{ConvertError, Error, Result},
     model::{
-        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
-        write::{Request as WriteRequest, Response as WriteResponse},
+        sql_query::{
+            display::{DefaultValueFormatter, ValueFormatter},
+            ColumnValues, PayloadCompression, RawResponse as SqlQueryRawResponse,
+            Request as SqlQueryRequest, Response as SqlQueryResponse, RowStream as SqlQueryStream,
+        },
+        write::{
+            Request as WriteRequest, RequestBuilder as WriteRequestBuilder,
+            Response as WriteResponse,
+        },
     },
-    rpc_client::RpcContext,
+    rpc_client::{RpcContext, TokenProvider},
 };
@@ -0,0 +1,253 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Metrics of the client operations, enabled by the `metrics` feature.
+
+use std::{fmt, future::Future};
+
+use horaedbproto::storage::{
+    SqlQueryResponse as QueryResponsePb, WriteRequest as WriteRequestPb,
+    WriteResponse as WriteResponsePb,
+};
+use prometheus::{
+    exponential_buckets, Histogram, HistogramOpts, IntCounter, IntCounterVec, Opts, Registry,
+};
+use prost::Message;
+
+use crate::{Error, Result};
+
+const NAMESPACE: &str = "horaedb_client";
+
+/// Metrics recorded around the rpc calls of the client.
+///
+/// The metrics are registered in the provided [`Registry`] when created, so
+/// create it only once for a registry and share it among the clients.
+#[derive(Clone)]
+pub struct ClientMetrics {
+    query_duration: Histogram,
+    write_duration: Histogram,
+    query_total: IntCounter,
+    write_total: IntCounter,
+    error_total: IntCounterVec,
+    query_response_bytes: Histogram,
+    write_request_bytes: Histogram,
+}
+
+#[allow(clippy::result_large_err)]
+impl ClientMetrics {
+    /// Create the metrics and register them in the `registry`.
+    pub fn new(registry: &Registry) -> Result<Self> {
+        let duration_buckets = exponential_buckets(0.001, 2.0, 16).map_err(register_error)?;
+        let bytes_buckets = exponential_buckets(64.0, 4.0, 12).map_err(register_error)?;
+        let make_histogram = |name: &str, help: &str, buckets: &[f64]| {
+            Histogram::with_opts(
+                HistogramOpts::new(name, help)
+                    .namespace(NAMESPACE)
+                    .buckets(buckets.to_vec()),
+            )
+        };
+        let make_counter = |name: &str, help: &str| {
+            IntCounter::with_opts(Opts::new(name, help).namespace(NAMESPACE))
+        };
+
+        let metrics = Self {
+            query_duration: make_histogram(
+                "query_duration_seconds",
+                "Duration of the sql queries",
+                &duration_buckets,
+            )
+            .map_err(register_error)?,
+            write_duration: make_histogram(
+                "write_duration_seconds",
+                "Duration of the writes",
+                &duration_buckets,
+            )
+            .map_err(register_error)?,
+            query_total: make_counter("query_total", "Total number of the sql queries")
+                .map_err(register_error)?,
+            write_total: make_counter("write_total", "Total number of the writes")
+                .map_err(register_error)?,
+            error_total: IntCounterVec::new(
+                Opts::new("error_total", "Total number of the failed operations")
+                    .namespace(NAMESPACE),
+                &["operation", "code"],
+            )
+            .map_err(register_error)?,
+            query_response_bytes: make_histogram(
+                "query_response_bytes",
+                "Encoded size of the sql query responses",
+                &bytes_buckets,
+            )
+            .map_err(register_error)?,
+            write_request_bytes: make_histogram(
+                "write_request_bytes",
+                "Encoded size of the write requests",
+                &bytes_buckets,
+            )
+            .map_err(register_error)?,
+        };
+
+        registry
+            .register(Box::new(metrics.query_duration.clone()))
+            .map_err(register_error)?;
+        registry
+            .register(Box::new(metrics.write_duration.clone()))
+            .map_err(register_error)?;
+        registry
+            .register(Box::new(metrics.query_total.clone()))
+            .map_err(register_error)?;
+        registry
+            .register(Box::new(metrics.write_total.clone()))
+            .map_err(register_error)?;
+        registry
+            .register(Box::new(metrics.error_total.clone()))
+            .map_err(register_error)?;
+        registry
+            .register(Box::new(metrics.query_response_bytes.clone()))
+            .map_err(register_error)?;
+        registry
+            .register(Box::new(metrics.write_request_bytes.clone()))
+            .map_err(register_error)?;
+
+        Ok(metrics)
+    }
+
+    pub(crate) async fn observe_query<F>(&self, query: F) -> Result<QueryResponsePb>
+    where
+        F: Future<Output = Result<QueryResponsePb>>,
+    {
+        self.query_total.inc();
+        let timer = self.query_duration.start_timer();
+        let resp = query.await;
+        timer.observe_duration();
+
+        match &resp {
+            Ok(resp) => self.query_response_bytes.observe(resp.encoded_len() as f64),
+            Err(e) => self.observe_error("query", e),
+        }
+
+        resp
+    }
+
+    pub(crate) async fn observe_write<W, F>(
+        &self,
+        req: WriteRequestPb,
+        write: W,
+    ) -> Result<WriteResponsePb>
+    where
+        W: FnOnce(WriteRequestPb) -> F,
+        F: Future<Output = Result<WriteResponsePb>>,
+    {
+        self.write_total.inc();
+        self.write_request_bytes.observe(req.encoded_len() as f64);
+        let timer = self.write_duration.start_timer();
+        let resp = write(req).await;
+        timer.observe_duration();
+
+        if let Err(e) = &resp {
+            self.observe_error("write", e);
+        }
+
+        resp
+    }
+
+    fn observe_error(&self, operation: &str, e: &Error) {
+        let code = match e {
+            Error::Server(e) => e.code.to_string(),
+            _ => "unknown".to_string(),
+        };
+        self.error_total
+            .with_label_values(&[operation, code.as_str()])
+            .inc();
+    }
+}
+
+impl fmt::Debug for ClientMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientMetrics").finish_non_exhaustive()
+    }
+}
+
+#[inline]
+fn register_error(e: prometheus::Error) -> Error {
+    Error::Client(format!("Failed to register metrics, err:{e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use horaedbproto::storage::{WriteRequest as WriteRequestPb, WriteResponse as WriteResponsePb};
+    use prometheus::{proto::MetricType, Registry};
+
+    use super::ClientMetrics;
+    use crate::{errors::ServerError, Error};
+
+    fn get_value(registry: &Registry, name: &str) -> f64 {
+        let family = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == name)
+            .unwrap();
+        let metric = &family.get_metric()[0];
+        match family.get_field_type() {
+            MetricType::HISTOGRAM => metric.get_histogram().get_sample_count() as f64,
+            _ => metric.get_counter().get_value(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observe_write() {
+        let registry = Registry::new();
+        let metrics = ClientMetrics::new(&registry).unwrap();
+
+        metrics
+            .observe_write(WriteRequestPb::default(), |_| async {
+                Ok(WriteResponsePb::default())
+            })
+            .await
+            .unwrap();
+        metrics
+            .observe_write(WriteRequestPb::default(), |_| async {
+                Err(Error::Server(ServerError {
+                    code: 500,
+                    msg: "internal error".to_string(),
+                    retry_after: None,
+                    request_id: None,
+                }))
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(get_value(&registry, "horaedb_client_write_total"), 2.0);
+        assert_eq!(
+            get_value(&registry, "horaedb_client_write_duration_seconds"),
+            2.0
+        );
+        assert_eq!(get_value(&registry, "horaedb_client_error_total"), 1.0);
+        let family = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == "horaedb_client_error_total")
+            .unwrap();
+        let labels = family.get_metric()[0].get_label();
+        assert!(labels
+            .iter()
+            .any(|l| l.get_name() == "code" && l.get_value() == "500"));
+
+        // Registering the metrics again is rejected.
+        assert!(ClientMetrics::new(&registry).is_err());
+    }
+}
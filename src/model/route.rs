@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, net::Ipv6Addr, str::FromStr};
 
 use horaedbproto::storage::Endpoint as EndPointPb;
 
@@ -37,10 +37,26 @@ impl Endpoint {
     }
 }
 
+/// The schemes stripped from the endpoints, the one actually used is decided by
+/// the [`RpcConfig`](crate::RpcConfig).
+const ENDPOINT_SCHEMES: [&str; 2] = ["http://", "grpc://"];
+
+/// Parse the endpoint in the form of `host:port`, `[ipv6]:port`, optionally
+/// with the `http://` or `grpc://` scheme and a trailing `/`.
 impl FromStr for Endpoint {
     type Err = Box<dyn std::error::Error + Send + Sync>;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = ENDPOINT_SCHEMES
+            .iter()
+            .find_map(|scheme| s.strip_prefix(scheme))
+            .unwrap_or(s);
+        let s = s.strip_suffix('/').unwrap_or(s);
+        if s.contains("://") {
+            let err_msg = "Unsupported scheme in the source string".to_string();
+            return Err(Self::Err::from(err_msg));
+        }
+
         let (addr, raw_port) = match s.rsplit_once(':') {
             Some(v) => v,
             None => {
@@ -53,6 +69,25 @@ impl FromStr for Endpoint {
             let err_msg = "Empty addr in the source string".to_string();
             return Err(Self::Err::from(err_msg));
         }
+        match addr.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(ipv6) => {
+                ipv6.parse::<Ipv6Addr>().map_err(|e| {
+                    let err_msg = format!("Fail to parse ipv6 addr:{ipv6}, err:{e}");
+                    Self::Err::from(err_msg)
+                })?;
+            }
+            None if addr.contains(':') => {
+                let err_msg = "Ipv6 addr must be enclosed in brackets".to_string();
+                return Err(Self::Err::from(err_msg));
+            }
+            None if addr
+                .contains(|c: char| c == '/' || c == '[' || c == ']' || c.is_whitespace()) =>
+            {
+                let err_msg = format!("Invalid addr:{addr}");
+                return Err(Self::Err::from(err_msg));
+            }
+            None => (),
+        }
 
         let port = raw_port.parse().map_err(|e| {
             let err_msg = format!("Fail to parse port:{raw_port}, err:{e}");
@@ -89,6 +124,10 @@ mod tests {
             ("127.0.0.1:80", "127.0.0.1", 80),
             ("hello.world.com:1080", "hello.world.com", 1080),
             ("horaedb.io:8831", "horaedb.io", 8831),
+            ("http://127.0.0.1:8831", "127.0.0.1", 8831),
+            ("grpc://horaedb.io:8831/", "horaedb.io", 8831),
+            ("[::1]:8831", "[::1]", 8831),
+            ("http://[fe80::1]:8831", "[fe80::1]", 8831),
         ];
 
         for (raw_endpoint, addr, port) in normal_cases {
@@ -97,10 +136,26 @@ mod tests {
             assert_eq!(port, endpoint.port);
         }
 
-        let abnormal_cases = vec!["127.0.0.1", ":1080", "", "0:99999999"];
+        let abnormal_cases = vec![
+            "127.0.0.1",
+            ":1080",
+            "",
+            "0:99999999",
+            "https://127.0.0.1:8831",
+            "http://",
+            "::1:8831",
+            "[::1]",
+            "[not-ipv6]:8831",
+            "127.0.0.1:8831/path",
+            "horae db:8831",
+        ];
         for raw_endpoint in abnormal_cases {
             let parse_res = raw_endpoint.parse::<Endpoint>();
-            assert!(parse_res.is_err());
+            assert!(parse_res.is_err(), "{raw_endpoint}");
         }
+
+        // The normalized endpoint is displayed.
+        let endpoint: Endpoint = "http://[::1]:8831/".parse().unwrap();
+        assert_eq!(endpoint.to_string(), "[::1]:8831");
     }
 }
@@ -0,0 +1,242 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Conversion from the [`Response`] to the columnar layout.
+
+use crate::{
+    model::{
+        sql_query::Response,
+        value::{DataType, TimestampMs, Value},
+    },
+    Error, Result,
+};
+
+macro_rules! define_column_values {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        /// The values of a column in the query result, and the null ones are
+        /// `None`.
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum ColumnValues {
+            /// The column of [`DataType::Null`], and the number of its values.
+            Null(usize),
+            $($variant(Vec<Option<$ty>>),)*
+        }
+
+        #[allow(clippy::result_large_err)]
+        impl ColumnValues {
+            fn with_capacity(data_type: DataType, capacity: usize) -> Self {
+                match data_type {
+                    DataType::Null => ColumnValues::Null(0),
+                    $(DataType::$variant => ColumnValues::$variant(Vec::with_capacity(capacity)),)*
+                }
+            }
+
+            pub fn data_type(&self) -> DataType {
+                match self {
+                    ColumnValues::Null(_) => DataType::Null,
+                    $(ColumnValues::$variant(_) => DataType::$variant,)*
+                }
+            }
+
+            pub fn len(&self) -> usize {
+                match self {
+                    ColumnValues::Null(len) => *len,
+                    $(ColumnValues::$variant(values) => values.len(),)*
+                }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            fn push(&mut self, name: &str, value: Value) -> Result<()> {
+                match (self, value) {
+                    (ColumnValues::Null(len), Value::Null) => *len += 1,
+                    $(
+                        (ColumnValues::$variant(values), Value::Null) => values.push(None),
+                        (ColumnValues::$variant(values), Value::$variant(v)) => values.push(Some(v)),
+                    )*
+                    (values, value) => {
+                        return Err(Error::Client(format!(
+                            "inconsistent types in column:{name}, expected:{:?}, actual:{:?}",
+                            values.data_type(),
+                            value.data_type()
+                        )))
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    };
+}
+
+define_column_values!(
+    Timestamp(TimestampMs),
+    Double(f64),
+    Float(f32),
+    Varbinary(Vec<u8>),
+    String(String),
+    UInt64(u64),
+    UInt32(u32),
+    UInt16(u16),
+    UInt8(u8),
+    Int64(i64),
+    Int32(i32),
+    Int16(i16),
+    Int8(i8),
+    Boolean(bool),
+);
+
+#[allow(clippy::result_large_err)]
+impl Response {
+    /// Convert the rows into the `(name, values)` of the columns in order,
+    /// which avoids matching the [`Value`] of every cell in the vectorized
+    /// processing.
+    ///
+    /// The columns are typed by [`Response::column_types`], and error will be
+    /// returned if any row doesn't match them.
+    pub fn into_columns(self) -> Result<Vec<(String, ColumnValues)>> {
+        let row_count = self.rows.len();
+        let mut columns: Vec<_> = self
+            .column_names
+            .into_iter()
+            .zip(self.column_types)
+            .map(|(name, data_type)| (name, ColumnValues::with_capacity(data_type, row_count)))
+            .collect();
+
+        for row in self.rows {
+            let row_columns = row.into_columns();
+            if row_columns.len() != columns.len() {
+                return Err(Error::Client(format!(
+                    "row with {} columns mismatches the {} columns of the response",
+                    row_columns.len(),
+                    columns.len()
+                )));
+            }
+            for ((name, values), column) in columns.iter_mut().zip(row_columns) {
+                values.push(name, column.into_value())?;
+            }
+        }
+
+        Ok(columns)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColumnValues;
+    use crate::model::{
+        sql_query::{
+            row::{Column, Row},
+            Response,
+        },
+        value::{DataType, Value},
+    };
+
+    fn make_response(rows: Vec<Vec<Value>>) -> Response {
+        let names = ["ts", "host", "value", "count", "null"];
+        let rows = rows
+            .into_iter()
+            .map(|values| {
+                Row::new(
+                    names
+                        .iter()
+                        .zip(values)
+                        .map(|(name, value)| Column::new(name.to_string(), value))
+                        .collect(),
+                )
+            })
+            .collect();
+        Response {
+            affected_rows: 0,
+            rows,
+            column_names: names.iter().map(|name| name.to_string()).collect(),
+            column_types: vec![
+                DataType::Timestamp,
+                DataType::String,
+                DataType::Double,
+                DataType::Int32,
+                DataType::Null,
+            ],
+            column_nullables: vec![true; 5],
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_into_columns() {
+        let rows = vec![
+            vec![
+                Value::Timestamp(1),
+                Value::String("a".to_string()),
+                Value::Double(1.0),
+                Value::Int32(1),
+                Value::Null,
+            ],
+            vec![
+                Value::Timestamp(2),
+                Value::String("b".to_string()),
+                Value::Null,
+                Value::Int32(2),
+                Value::Null,
+            ],
+        ];
+        let columns = make_response(rows.clone()).into_columns().unwrap();
+        let expected = vec![
+            (
+                "ts".to_string(),
+                ColumnValues::Timestamp(vec![Some(1), Some(2)]),
+            ),
+            (
+                "host".to_string(),
+                ColumnValues::String(vec![Some("a".to_string()), Some("b".to_string())]),
+            ),
+            (
+                "value".to_string(),
+                ColumnValues::Double(vec![Some(1.0), None]),
+            ),
+            (
+                "count".to_string(),
+                ColumnValues::Int32(vec![Some(1), Some(2)]),
+            ),
+            ("null".to_string(), ColumnValues::Null(2)),
+        ];
+        assert_eq!(columns, expected);
+        for (col_idx, (_, values)) in columns.iter().enumerate() {
+            assert_eq!(values.len(), 2);
+            assert_eq!(values.data_type(), rows[0][col_idx].data_type());
+        }
+
+        assert!(Response::default().into_columns().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_into_columns_with_inconsistent_rows() {
+        let resp = make_response(vec![vec![
+            Value::Timestamp(1),
+            Value::Int64(1),
+            Value::Double(1.0),
+            Value::Int32(1),
+            Value::Null,
+        ]]);
+        assert!(resp.into_columns().is_err());
+
+        let resp = make_response(vec![vec![Value::Timestamp(1)]]);
+        assert!(resp.into_columns().is_err());
+    }
+}
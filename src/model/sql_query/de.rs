@@ -0,0 +1,293 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Deserialize the [`Row`]s into the user defined types by serde.
+//!
+//! The column names are used as the field names, so a struct like this can be
+//! deserialized from the rows of `SELECT name, value, t FROM demo`:
+//! ```rust
+//! #[derive(serde::Deserialize)]
+//! struct Demo {
+//!     name: String,
+//!     value: Option<f64>,
+//!     t: i64,
+//! }
+//! ```
+//! And [`Value::Null`] can only be deserialized into the `Option` fields.
+
+use std::fmt::Display;
+
+use serde::{
+    de::{
+        self,
+        value::{BorrowedStrDeserializer, SeqDeserializer},
+        DeserializeSeed, MapAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+
+use crate::{
+    model::{
+        sql_query::{
+            row::{Column, Row},
+            Response,
+        },
+        value::Value,
+    },
+    Error, Result,
+};
+
+/// Deserialize the `row` into `T`.
+#[allow(clippy::result_large_err)]
+pub fn from_row<'de, T: Deserialize<'de>>(row: &'de Row) -> Result<T> {
+    T::deserialize(RowDeserializer { row }).map_err(|e| Error::Deserialize(e.0))
+}
+
+#[allow(clippy::result_large_err)]
+impl Response {
+    /// Deserialize all the rows into `T`.
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<Vec<T>> {
+        self.rows.iter().map(from_row).collect()
+    }
+}
+
+#[derive(Debug)]
+struct DeError(String);
+
+impl Display for DeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DeError(msg.to_string())
+    }
+}
+
+struct RowDeserializer<'de> {
+    row: &'de Row,
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_map(ColumnsAccess {
+            columns: self.row.columns().iter(),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ColumnsAccess<'de> {
+    columns: std::slice::Iter<'de, Column>,
+    value: Option<&'de Value>,
+}
+
+impl<'de> MapAccess<'de> for ColumnsAccess<'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> std::result::Result<Option<K::Value>, DeError> {
+        match self.columns.next() {
+            Some(column) => {
+                self.value = Some(column.value());
+                seed.deserialize(BorrowedStrDeserializer::new(column.name()))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        // The value must be set by the `next_key_seed` called before.
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| DeError("value is polled before key".to_string()))?;
+        seed.deserialize(ValueDeserializer { value })
+    }
+}
+
+struct ValueDeserializer<'de> {
+    value: &'de Value,
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Timestamp(v) => visitor.visit_i64(*v),
+            Value::Double(v) => visitor.visit_f64(*v),
+            Value::Float(v) => visitor.visit_f32(*v),
+            Value::Varbinary(v) => visitor.visit_borrowed_bytes(v),
+            Value::String(v) => visitor.visit_borrowed_str(v),
+            Value::UInt64(v) => visitor.visit_u64(*v),
+            Value::UInt32(v) => visitor.visit_u32(*v),
+            Value::UInt16(v) => visitor.visit_u16(*v),
+            Value::UInt8(v) => visitor.visit_u8(*v),
+            Value::Int64(v) => visitor.visit_i64(*v),
+            Value::Int32(v) => visitor.visit_i32(*v),
+            Value::Int16(v) => visitor.visit_i16(*v),
+            Value::Int8(v) => visitor.visit_i8(*v),
+            Value::Boolean(v) => visitor.visit_bool(*v),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        // Make it possible to deserialize varbinary into `Vec<u8>`.
+        match self.value {
+            Value::Varbinary(v) => visitor.visit_seq(SeqDeserializer::new(v.iter().copied())),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use super::from_row;
+    use crate::{
+        model::{
+            sql_query::{
+                row::{Column, Row},
+                Response,
+            },
+            value::Value,
+        },
+        Error,
+    };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Demo<'a> {
+        name: &'a str,
+        value: Option<f64>,
+        bin: Vec<u8>,
+        t: i64,
+    }
+
+    fn make_row(name: &str, value: Value) -> Row {
+        Row::new(vec![
+            Column::new("name".to_string(), Value::String(name.to_string())),
+            Column::new("value".to_string(), value),
+            Column::new("bin".to_string(), Value::Varbinary(b"bin".to_vec())),
+            Column::new("t".to_string(), Value::Timestamp(1001)),
+            Column::new("ignored".to_string(), Value::Boolean(true)),
+        ])
+    }
+
+    #[test]
+    fn test_deserialize_response() {
+        let resp = Response {
+            affected_rows: 0,
+            column_names: vec![],
+            column_types: vec![],
+            column_nullables: vec![],
+            rows: vec![
+                make_row("a", Value::Double(0.42)),
+                make_row("b", Value::Null),
+            ],
+            truncated: false,
+        };
+
+        let demos: Vec<Demo> = resp.deserialize().unwrap();
+        let expected = vec![
+            Demo {
+                name: "a",
+                value: Some(0.42),
+                bin: b"bin".to_vec(),
+                t: 1001,
+            },
+            Demo {
+                name: "b",
+                value: None,
+                bin: b"bin".to_vec(),
+                t: 1001,
+            },
+        ];
+        assert_eq!(demos, expected);
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Missing {
+            absent: i64,
+        }
+        let row = make_row("a", Value::Double(0.42));
+        match from_row::<Missing>(&row) {
+            Err(Error::Deserialize(msg)) => assert!(msg.contains("absent"), "msg:{msg}"),
+            res => panic!("unexpected result:{res:?}"),
+        }
+
+        // Null can't be deserialized into non-optional field.
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Required {
+            value: f64,
+        }
+        let row = make_row("a", Value::Null);
+        assert!(matches!(
+            from_row::<Required>(&row),
+            Err(Error::Deserialize(_))
+        ));
+    }
+}
@@ -15,9 +15,54 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::fmt::Display;
+use std::{
+    fmt::{Display, Write},
+    io,
+};
 
-use crate::model::sql_query::response::Response;
+use crate::{
+    model::{sql_query::response::Response, value::Value},
+    Error, Result,
+};
+
+/// Render the [`Value`]s into the cells of [`TableFormatter`] and
+/// [`Response::to_csv_with`], e.g. for the timestamps in a specific timezone or
+/// the floats in a fixed precision.
+///
+/// Only the hooks to customize need to be overridden, and the others render
+/// the values in the same way as [`DefaultValueFormatter`].
+pub trait ValueFormatter {
+    /// Render any value, which is dispatched to the other hooks by default.
+    fn format(&self, value: &Value) -> String {
+        match value {
+            Value::Timestamp(v) => self.format_timestamp(*v),
+            Value::Double(v) => self.format_double(*v),
+            Value::Float(v) => self.format_float(*v),
+            _ => format_value(value),
+        }
+    }
+
+    /// Render the timestamp in milliseconds, which is the number itself by
+    /// default.
+    fn format_timestamp(&self, ts: i64) -> String {
+        ts.to_string()
+    }
+
+    fn format_double(&self, v: f64) -> String {
+        v.to_string()
+    }
+
+    fn format_float(&self, v: f32) -> String {
+        v.to_string()
+    }
+}
+
+/// The [`ValueFormatter`] used by default, which renders the nulls as `NULL`,
+/// the timestamps as the milliseconds, and the binaries in hex.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultValueFormatter;
+
+impl ValueFormatter for DefaultValueFormatter {}
 
 /// Display [`SqlQueryResponse`](Response) in csv format.
 pub struct CsvFormatter {
@@ -52,3 +97,431 @@ impl Display for CsvFormatter {
         Ok(())
     }
 }
+
+/// Display [`SqlQueryResponse`](Response) as an ascii table.
+///
+/// The column names are taken from the first row, and nothing is displayed if
+/// there is no row.
+pub struct TableFormatter<'a> {
+    resp: &'a Response,
+    compact: bool,
+    max_rows: Option<usize>,
+    value_formatter: &'a dyn ValueFormatter,
+}
+
+impl<'a> TableFormatter<'a> {
+    pub fn new(resp: &'a Response) -> Self {
+        Self {
+            resp,
+            compact: false,
+            max_rows: None,
+            value_formatter: &DefaultValueFormatter,
+        }
+    }
+
+    /// Render the cells by `value_formatter` instead of the
+    /// [`DefaultValueFormatter`].
+    pub fn value_formatter(mut self, value_formatter: &'a dyn ValueFormatter) -> Self {
+        self.value_formatter = value_formatter;
+        self
+    }
+
+    /// Omit the borders of the table.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Display at most `max_rows` rows, and the rest are summarized in a
+    /// footer.
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    fn write_separator(f: &mut std::fmt::Formatter<'_>, widths: &[usize]) -> std::fmt::Result {
+        for width in widths {
+            write!(f, "+{}", "-".repeat(width + 2))?;
+        }
+        f.write_str("+\n")
+    }
+
+    fn write_line(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        cells: &[String],
+        widths: &[usize],
+    ) -> std::fmt::Result {
+        let mut line = String::new();
+        for (idx, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            if self.compact {
+                if idx > 0 {
+                    line.push_str("  ");
+                }
+                write!(line, "{cell:width$}")?;
+            } else {
+                write!(line, "| {cell:width$} ")?;
+            }
+        }
+        if !self.compact {
+            line.push('|');
+        }
+
+        writeln!(f, "{}", line.trim_end())
+    }
+}
+
+impl Display for TableFormatter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = &self.resp.rows;
+        let Some(first_row) = rows.first() else {
+            return Ok(());
+        };
+
+        let shown_num = self.max_rows.unwrap_or(rows.len()).min(rows.len());
+        let header = first_row
+            .columns()
+            .iter()
+            .map(|col| col.name().to_string())
+            .collect::<Vec<_>>();
+        let body = rows[..shown_num]
+            .iter()
+            .map(|row| {
+                row.columns()
+                    .iter()
+                    .map(|col| self.value_formatter.format(col.value()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // The width of every column is the max width of its cells.
+        let mut widths = header.iter().map(|h| h.chars().count()).collect::<Vec<_>>();
+        for cells in &body {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        if !self.compact {
+            Self::write_separator(f, &widths)?;
+        }
+        self.write_line(f, &header, &widths)?;
+        if !self.compact {
+            Self::write_separator(f, &widths)?;
+        }
+        for cells in &body {
+            self.write_line(f, cells, &widths)?;
+        }
+        if !self.compact {
+            Self::write_separator(f, &widths)?;
+        }
+
+        let hidden_num = rows.len() - shown_num;
+        if hidden_num > 0 {
+            writeln!(f, "... {hidden_num} more rows")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl Response {
+    /// Render the rows as an ascii table, see [`TableFormatter`] for more
+    /// options.
+    pub fn to_table_string(&self) -> String {
+        TableFormatter::new(self).to_string()
+    }
+
+    /// Render the rows as an ascii table whose cells are rendered by
+    /// `value_formatter`.
+    pub fn to_table_string_with(&self, value_formatter: &dyn ValueFormatter) -> String {
+        TableFormatter::new(self)
+            .value_formatter(value_formatter)
+            .to_string()
+    }
+
+    /// Write the rows into `w` in csv, with a header line of the column names.
+    ///
+    /// The strings and the binaries in hex are always quoted, the nulls are
+    /// empty fields, and the timestamps are written as the milliseconds. The
+    /// column names are taken from the first row if the schema is unknown.
+    pub fn to_csv<W: io::Write>(&self, w: W) -> Result<()> {
+        self.to_csv_with(w, &DefaultValueFormatter)
+    }
+
+    /// Write the rows into `w` in csv like [`Response::to_csv`], but the fields
+    /// are rendered by `value_formatter`.
+    ///
+    /// The rendered fields are quoted if they have the special chars of csv.
+    pub fn to_csv_with<W: io::Write>(
+        &self,
+        w: W,
+        value_formatter: &dyn ValueFormatter,
+    ) -> Result<()> {
+        self.write_csv(io::BufWriter::new(w), value_formatter)
+            .map_err(|e| Error::Other { source: e.into() })
+    }
+
+    fn write_csv<W: io::Write>(
+        &self,
+        mut w: W,
+        value_formatter: &dyn ValueFormatter,
+    ) -> io::Result<()> {
+        let header: Vec<_> = if self.column_names.is_empty() {
+            self.rows
+                .first()
+                .map(|row| row.iter().map(|(name, _)| quote_csv(name)).collect())
+                .unwrap_or_default()
+        } else {
+            self.column_names
+                .iter()
+                .map(|name| quote_csv(name))
+                .collect()
+        };
+        // Nothing is written without any column, e.g. for the affected rows.
+        if header.is_empty() {
+            return Ok(());
+        }
+        writeln!(w, "{}", header.join(","))?;
+
+        for row in &self.rows {
+            let fields: Vec<_> = row
+                .iter()
+                .map(|(_, value)| format_csv(value, value_formatter))
+                .collect();
+            writeln!(w, "{}", fields.join(","))?;
+        }
+
+        w.flush()
+    }
+}
+
+fn format_csv(value: &Value, value_formatter: &dyn ValueFormatter) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(_) | Value::Varbinary(_) => {
+            let s = value_formatter.format(value);
+            format!("\"{}\"", s.replace('"', "\"\""))
+        }
+        _ => quote_csv(&value_formatter.format(value)),
+    }
+}
+
+/// Quote the name only if it has the special chars of csv.
+fn quote_csv(name: &str) -> String {
+    if name.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Timestamp(v) => v.to_string(),
+        Value::Double(v) => v.to_string(),
+        Value::Float(v) => v.to_string(),
+        Value::Varbinary(v) => {
+            let mut hex = String::with_capacity(2 + v.len() * 2);
+            hex.push_str("0x");
+            for byte in v {
+                let _ = write!(hex, "{byte:02x}");
+            }
+            hex
+        }
+        Value::String(v) => v.clone(),
+        Value::UInt64(v) => v.to_string(),
+        Value::UInt32(v) => v.to_string(),
+        Value::UInt16(v) => v.to_string(),
+        Value::UInt8(v) => v.to_string(),
+        Value::Int64(v) => v.to_string(),
+        Value::Int32(v) => v.to_string(),
+        Value::Int16(v) => v.to_string(),
+        Value::Int8(v) => v.to_string(),
+        Value::Boolean(v) => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, SecondsFormat};
+
+    use super::{TableFormatter, ValueFormatter};
+    use crate::model::{
+        sql_query::{
+            row::{Column, Row},
+            Response,
+        },
+        value::DataType,
+        value::Value,
+    };
+
+    fn make_response() -> Response {
+        let make_row = |ts, host: &str, value| {
+            Row::new(vec![
+                Column::new("ts".to_string(), Value::Timestamp(ts)),
+                Column::new("host".to_string(), Value::String(host.to_string())),
+                Column::new("value".to_string(), value),
+                Column::new("raw".to_string(), Value::Varbinary(vec![0xca, 0xfe])),
+            ])
+        };
+
+        Response {
+            affected_rows: 0,
+            column_names: vec![],
+            column_types: vec![],
+            column_nullables: vec![],
+            rows: vec![
+                make_row(1000, "host-a", Value::Double(0.5)),
+                make_row(2000, "b", Value::Null),
+                make_row(3000, "c", Value::Int64(-10)),
+            ],
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_to_table_string() {
+        let expected = "\
++------+--------+-------+--------+
+| ts   | host   | value | raw    |
++------+--------+-------+--------+
+| 1000 | host-a | 0.5   | 0xcafe |
+| 2000 | b      | NULL  | 0xcafe |
+| 3000 | c      | -10   | 0xcafe |
++------+--------+-------+--------+
+";
+        assert_eq!(make_response().to_table_string(), expected);
+        assert_eq!(Response::default().to_table_string(), "");
+    }
+
+    #[test]
+    fn test_compact_and_max_rows() {
+        let resp = make_response();
+        let expected = "\
+ts    host    value  raw
+1000  host-a  0.5    0xcafe
+2000  b       NULL   0xcafe
+... 1 more rows
+";
+        let formatter = TableFormatter::new(&resp).compact(true).max_rows(2);
+        assert_eq!(formatter.to_string(), expected);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let row = |values: Vec<Value>| {
+            let names = ["ts", "host", "value", "raw", "ok", "n"];
+            Row::new(
+                names
+                    .iter()
+                    .zip(values)
+                    .map(|(name, value)| Column::new(name.to_string(), value))
+                    .collect(),
+            )
+        };
+        let resp = Response {
+            column_names: ["ts", "host", "value", "raw", "ok", "n"]
+                .map(String::from)
+                .to_vec(),
+            column_types: vec![
+                DataType::Timestamp,
+                DataType::String,
+                DataType::Double,
+                DataType::Varbinary,
+                DataType::Boolean,
+                DataType::Int32,
+            ],
+            rows: vec![
+                row(vec![
+                    Value::Timestamp(1000),
+                    Value::String("a,b".to_string()),
+                    Value::Double(0.5),
+                    Value::Varbinary(vec![0xca, 0xfe]),
+                    Value::Boolean(true),
+                    Value::Int32(-1),
+                ]),
+                row(vec![
+                    Value::Timestamp(2000),
+                    Value::String("say \"hi\"".to_string()),
+                    Value::Null,
+                    Value::Null,
+                    Value::Boolean(false),
+                    Value::Null,
+                ]),
+            ],
+            ..Default::default()
+        };
+
+        let mut buf = Vec::new();
+        resp.to_csv(&mut buf).unwrap();
+        let expected = "\
+ts,host,value,raw,ok,n
+1000,\"a,b\",0.5,\"0xcafe\",true,-1
+2000,\"say \"\"hi\"\"\",,,false,
+";
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+        // The header is taken from the first row without the schema.
+        let mut buf = Vec::new();
+        make_response().to_csv(&mut buf).unwrap();
+        let csv = String::from_utf8(buf).unwrap();
+        assert!(csv.starts_with("ts,host,value,raw\n1000,\"host-a\",0.5,\"0xcafe\"\n"));
+
+        let mut buf = Vec::new();
+        Response::default().to_csv(&mut buf).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    /// Render the timestamps in RFC3339 and the doubles in two decimals.
+    struct Rfc3339Formatter;
+
+    impl ValueFormatter for Rfc3339Formatter {
+        fn format_timestamp(&self, ts: i64) -> String {
+            DateTime::from_timestamp_millis(ts)
+                .unwrap()
+                .to_rfc3339_opts(SecondsFormat::Millis, true)
+        }
+
+        fn format_double(&self, v: f64) -> String {
+            format!("{v:.2}")
+        }
+    }
+
+    #[test]
+    fn test_custom_value_formatter() {
+        let resp = make_response();
+        let expected = "\
++--------------------------+--------+-------+--------+
+| ts                       | host   | value | raw    |
++--------------------------+--------+-------+--------+
+| 1970-01-01T00:00:01.000Z | host-a | 0.50  | 0xcafe |
+| 1970-01-01T00:00:02.000Z | b      | NULL  | 0xcafe |
+| 1970-01-01T00:00:03.000Z | c      | -10   | 0xcafe |
++--------------------------+--------+-------+--------+
+";
+        assert_eq!(resp.to_table_string_with(&Rfc3339Formatter), expected);
+        let formatter = TableFormatter::new(&resp)
+            .compact(true)
+            .max_rows(1)
+            .value_formatter(&Rfc3339Formatter);
+        assert_eq!(
+            formatter.to_string(),
+            "ts                        host    value  raw\n\
+             1970-01-01T00:00:01.000Z  host-a  0.50   0xcafe\n\
+             ... 2 more rows\n"
+        );
+
+        let mut buf = Vec::new();
+        resp.to_csv_with(&mut buf, &Rfc3339Formatter).unwrap();
+        let expected = "\
+ts,host,value,raw
+1970-01-01T00:00:01.000Z,\"host-a\",0.50,\"0xcafe\"
+1970-01-01T00:00:02.000Z,\"b\",,\"0xcafe\"
+1970-01-01T00:00:03.000Z,\"c\",-10,\"0xcafe\"
+";
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+}
@@ -0,0 +1,142 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Convert the [`SqlQueryResponse`](Response) into json, enabled by the `json`
+//! feature.
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::model::{sql_query::Response, value::Value};
+
+impl Response {
+    /// Convert the rows into a json array of objects keyed by the column names.
+    ///
+    /// The timestamps are converted to the milliseconds, the binaries are
+    /// converted to the base64 strings, and the nulls and the non-finite floats
+    /// are converted to `null`.
+    pub fn to_json(&self) -> JsonValue {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let object = row
+                    .columns()
+                    .iter()
+                    .map(|col| (col.name().to_string(), value_to_json(col.value())))
+                    .collect::<Map<_, _>>();
+                JsonValue::Object(object)
+            })
+            .collect();
+
+        JsonValue::Array(rows)
+    }
+}
+
+fn value_to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::Null => JsonValue::Null,
+        Value::Timestamp(v) => JsonValue::from(*v),
+        Value::Double(v) => float_to_json(*v),
+        Value::Float(v) => float_to_json(*v as f64),
+        Value::Varbinary(v) => JsonValue::String(BASE64_STANDARD.encode(v)),
+        Value::String(v) => JsonValue::String(v.clone()),
+        Value::UInt64(v) => JsonValue::from(*v),
+        Value::UInt32(v) => JsonValue::from(*v),
+        Value::UInt16(v) => JsonValue::from(*v),
+        Value::UInt8(v) => JsonValue::from(*v),
+        Value::Int64(v) => JsonValue::from(*v),
+        Value::Int32(v) => JsonValue::from(*v),
+        Value::Int16(v) => JsonValue::from(*v),
+        Value::Int8(v) => JsonValue::from(*v),
+        Value::Boolean(v) => JsonValue::Bool(*v),
+    }
+}
+
+#[inline]
+fn float_to_json(v: f64) -> JsonValue {
+    Number::from_f64(v)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::model::{
+        sql_query::{
+            row::{Column, Row},
+            Response,
+        },
+        value::Value,
+    };
+
+    #[test]
+    fn test_to_json() {
+        let columns = vec![
+            ("null", Value::Null),
+            ("timestamp", Value::Timestamp(1700000000000)),
+            ("double", Value::Double(0.5)),
+            ("float", Value::Float(1.5)),
+            ("nan", Value::Double(f64::NAN)),
+            ("varbinary", Value::Varbinary(b"hello".to_vec())),
+            ("string", Value::String("a\"b".to_string())),
+            ("uint64", Value::UInt64(u64::MAX)),
+            ("uint32", Value::UInt32(32)),
+            ("uint16", Value::UInt16(16)),
+            ("uint8", Value::UInt8(8)),
+            ("int64", Value::Int64(i64::MIN)),
+            ("int32", Value::Int32(-32)),
+            ("int16", Value::Int16(-16)),
+            ("int8", Value::Int8(-8)),
+            ("boolean", Value::Boolean(true)),
+        ];
+        let row = Row::new(
+            columns
+                .into_iter()
+                .map(|(name, value)| Column::new(name.to_string(), value))
+                .collect(),
+        );
+        let resp = Response {
+            affected_rows: 0,
+            rows: vec![row],
+            ..Default::default()
+        };
+
+        let expected = json!([{
+            "null": null,
+            "timestamp": 1700000000000i64,
+            "double": 0.5,
+            "float": 1.5,
+            "nan": null,
+            "varbinary": "aGVsbG8=",
+            "string": "a\"b",
+            "uint64": u64::MAX,
+            "uint32": 32,
+            "uint16": 16,
+            "uint8": 8,
+            "int64": i64::MIN,
+            "int32": -32,
+            "int16": -16,
+            "int8": -8,
+            "boolean": true,
+        }]);
+        assert_eq!(resp.to_json(), expected);
+        assert_eq!(Response::default().to_json(), json!([]));
+    }
+}
@@ -15,10 +15,17 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod columns;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod display;
+#[cfg(feature = "json")]
+mod json;
+mod record_batch;
 pub(crate) mod request;
 pub(crate) mod response;
 pub mod row;
 
+pub use columns::ColumnValues;
 pub use request::Request;
-pub use response::Response;
+pub use response::{PayloadCompression, RawResponse, Response, RowStream};
@@ -0,0 +1,253 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Conversion from the [`Response`] to the arrow [`RecordBatch`].
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        ArrayRef, BinaryBuilder, BooleanBuilder, Float32Builder, Float64Builder, Int16Builder,
+        Int32Builder, Int64Builder, Int8Builder, NullArray, StringBuilder,
+        TimestampMillisecondBuilder, UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
+    },
+    datatypes::{Field, Schema},
+    record_batch::RecordBatch,
+};
+
+use crate::{
+    model::{
+        sql_query::{row::Row, Response},
+        value::{DataType, Value},
+    },
+    Error, Result,
+};
+
+macro_rules! build_array {
+    ($rows:expr, $col_idx:expr, $builder_type:ty, $variant:ident) => {
+        build_array!($rows, $col_idx, $builder_type, $variant, |v| *v)
+    };
+    ($rows:expr, $col_idx:expr, $builder_type:ty, $variant:ident, |$v:ident| $to_arrow:expr) => {{
+        let mut builder = <$builder_type>::new();
+        for row in $rows {
+            match column_value(row, $col_idx)? {
+                Value::Null => builder.append_null(),
+                Value::$variant($v) => builder.append_value($to_arrow),
+                v => {
+                    return Err(Error::Client(format!(
+                        "inconsistent types in column:{}, expected:{:?}, actual:{:?}",
+                        $col_idx,
+                        DataType::$variant,
+                        v.data_type()
+                    )))
+                }
+            }
+        }
+        Arc::new(builder.finish()) as ArrayRef
+    }};
+}
+
+#[allow(clippy::result_large_err)]
+impl TryFrom<&Response> for RecordBatch {
+    type Error = Error;
+
+    /// The arrow type of a column is decided by its non-null values, and the
+    /// column consisting of null values only is converted to the null array.
+    fn try_from(resp: &Response) -> std::result::Result<Self, Self::Error> {
+        let first_row = match resp.rows.first() {
+            Some(row) => row,
+            None => return Ok(RecordBatch::new_empty(Arc::new(Schema::empty()))),
+        };
+
+        let mut fields = Vec::with_capacity(first_row.columns().len());
+        let mut arrays = Vec::with_capacity(first_row.columns().len());
+        for (col_idx, column) in first_row.columns().iter().enumerate() {
+            let data_type = resp
+                .rows
+                .iter()
+                .map(|row| column_value(row, col_idx))
+                .find(|v| !matches!(v, Ok(Value::Null)))
+                .transpose()?
+                .map(|v| v.data_type())
+                .unwrap_or(DataType::Null);
+            let array = build_column(&resp.rows, col_idx, data_type)?;
+            fields.push(Field::new(
+                column.name(),
+                array.data_type().clone(),
+                array.null_count() > 0 || data_type == DataType::Null,
+            ));
+            arrays.push(array);
+        }
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+            .map_err(|e| Error::Client(format!("failed to build record batch, err:{e}")))
+    }
+}
+
+impl TryFrom<Response> for RecordBatch {
+    type Error = Error;
+
+    fn try_from(resp: Response) -> std::result::Result<Self, Self::Error> {
+        RecordBatch::try_from(&resp)
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn column_value(row: &Row, col_idx: usize) -> Result<&Value> {
+    row.get(col_idx).ok_or_else(|| {
+        Error::Client(format!(
+            "column:{col_idx} is missing in row with {} columns",
+            row.columns().len()
+        ))
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn build_column(rows: &[Row], col_idx: usize, data_type: DataType) -> Result<ArrayRef> {
+    let array = match data_type {
+        DataType::Null => Arc::new(NullArray::new(rows.len())) as ArrayRef,
+        DataType::Timestamp => {
+            build_array!(rows, col_idx, TimestampMillisecondBuilder, Timestamp)
+        }
+        DataType::Double => build_array!(rows, col_idx, Float64Builder, Double),
+        DataType::Float => build_array!(rows, col_idx, Float32Builder, Float),
+        DataType::Varbinary => {
+            build_array!(rows, col_idx, BinaryBuilder, Varbinary, |v| v)
+        }
+        DataType::String => {
+            build_array!(rows, col_idx, StringBuilder, String, |v| v)
+        }
+        DataType::UInt64 => build_array!(rows, col_idx, UInt64Builder, UInt64),
+        DataType::UInt32 => build_array!(rows, col_idx, UInt32Builder, UInt32),
+        DataType::UInt16 => build_array!(rows, col_idx, UInt16Builder, UInt16),
+        DataType::UInt8 => build_array!(rows, col_idx, UInt8Builder, UInt8),
+        DataType::Int64 => build_array!(rows, col_idx, Int64Builder, Int64),
+        DataType::Int32 => build_array!(rows, col_idx, Int32Builder, Int32),
+        DataType::Int16 => build_array!(rows, col_idx, Int16Builder, Int16),
+        DataType::Int8 => build_array!(rows, col_idx, Int8Builder, Int8),
+        DataType::Boolean => build_array!(rows, col_idx, BooleanBuilder, Boolean),
+    };
+
+    Ok(array)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{
+            BinaryArray, Float64Array, Int32Array, NullArray, StringArray,
+            TimestampMillisecondArray,
+        },
+        datatypes::{DataType, Field, Schema, TimeUnit},
+        record_batch::RecordBatch,
+    };
+
+    use crate::model::{
+        sql_query::{
+            row::{Column, Row},
+            Response,
+        },
+        value::Value,
+    };
+
+    fn make_row(values: Vec<Value>) -> Row {
+        let names = ["int", "double", "string", "varbinary", "timestamp", "null"];
+        let columns = names
+            .into_iter()
+            .zip(values)
+            .map(|(name, value)| Column::new(name.to_string(), value))
+            .collect();
+        Row::new(columns)
+    }
+
+    #[test]
+    fn test_convert_to_record_batch() {
+        let resp = Response {
+            affected_rows: 0,
+            column_names: vec![],
+            column_types: vec![],
+            column_nullables: vec![],
+            rows: vec![
+                make_row(vec![
+                    Value::Int32(1),
+                    Value::Null,
+                    Value::String("a".to_string()),
+                    Value::Varbinary(b"a".to_vec()),
+                    Value::Timestamp(1001),
+                    Value::Null,
+                ]),
+                make_row(vec![
+                    Value::Int32(2),
+                    Value::Double(0.2),
+                    Value::Null,
+                    Value::Varbinary(b"b".to_vec()),
+                    Value::Timestamp(1002),
+                    Value::Null,
+                ]),
+            ],
+            truncated: false,
+        };
+
+        let schema = Schema::new(vec![
+            Field::new("int", DataType::Int32, false),
+            Field::new("double", DataType::Float64, true),
+            Field::new("string", DataType::Utf8, true),
+            Field::new("varbinary", DataType::Binary, false),
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Millisecond, None),
+                false,
+            ),
+            Field::new("null", DataType::Null, true),
+        ]);
+        let expected = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(Float64Array::from(vec![None, Some(0.2)])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+                Arc::new(BinaryArray::from(vec![b"a".as_slice(), b"b".as_slice()])),
+                Arc::new(TimestampMillisecondArray::from(vec![1001, 1002])),
+                Arc::new(NullArray::new(2)),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(RecordBatch::try_from(resp).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_convert_inconsistent_types() {
+        let resp = Response {
+            affected_rows: 0,
+            column_names: vec![],
+            column_types: vec![],
+            column_nullables: vec![],
+            rows: vec![
+                Row::new(vec![Column::new("v".to_string(), Value::Int32(1))]),
+                Row::new(vec![Column::new("v".to_string(), Value::Int64(2))]),
+            ],
+            truncated: false,
+        };
+        assert!(RecordBatch::try_from(resp).is_err());
+
+        let empty = RecordBatch::try_from(Response::default()).unwrap();
+        assert_eq!(empty.num_rows(), 0);
+    }
+}
@@ -15,6 +15,8 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::{model::value::Value, Error, Result};
+
 #[derive(Debug, Clone)]
 pub struct Request {
     /// The tables involved in the sql.
@@ -25,3 +27,258 @@ pub struct Request {
     /// The sql for query.
     pub sql: String,
 }
+
+#[allow(clippy::result_large_err)]
+impl Request {
+    /// Check the request locally, so that the obviously invalid ones fail fast
+    /// without the round trip to the server.
+    ///
+    /// The sql must not be blank, and the table names in the hint must not be
+    /// empty. Note that the empty `tables` is still valid here, though it is
+    /// rejected in the `Direct` mode for no table to route by.
+    pub fn validate(&self) -> Result<()> {
+        if self.sql.trim().is_empty() {
+            return Err(Error::Client("Sql in query request is empty".to_string()));
+        }
+        if self.tables.iter().any(|table| table.is_empty()) {
+            return Err(Error::Client(format!(
+                "Empty table name in query request, tables:{:?}",
+                self.tables
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Make the request by replacing the `?` placeholders in the `sql` with the
+    /// `params` in order.
+    ///
+    /// The params are rendered as sql literals, e.g. the strings are quoted and
+    /// the quotes and the backslashes in them are escaped, and the timestamps
+    /// are rendered as the milliseconds. The `?` in the quoted strings or
+    /// identifiers (by `'`, `"` or `` ` ``) or in the comments (by `--` or
+    /// `/* */`) of the `sql` is not a placeholder.
+    ///
+    /// Error will be returned if the number of the placeholders doesn't match
+    /// the params, or a param can't be represented in sql, e.g. the NaN.
+    pub fn with_params(tables: Vec<String>, sql: &str, params: &[Value]) -> Result<Self> {
+        let mut rendered = String::with_capacity(sql.len());
+        let mut params_iter = params.iter();
+        let mut chars = sql.chars().peekable();
+        let mut state = ScanState::Plain;
+        while let Some(c) = chars.next() {
+            match (state, c) {
+                (ScanState::Plain, '\'' | '"' | '`') => state = ScanState::Quoted(c),
+                (ScanState::Plain, '-') if chars.peek() == Some(&'-') => {
+                    state = ScanState::LineComment
+                }
+                // The opening `*` is consumed here, so that `/*/` doesn't close the comment.
+                (ScanState::Plain, '/') if chars.peek() == Some(&'*') => {
+                    rendered.push(c);
+                    rendered.extend(chars.next());
+                    state = ScanState::BlockComment;
+                    continue;
+                }
+                (ScanState::Plain, '?') => {
+                    let param = params_iter.next().ok_or_else(|| {
+                        Error::Client(format!(
+                            "Too few params for sql, params:{}, sql:{sql}",
+                            params.len()
+                        ))
+                    })?;
+                    render_param(param, &mut rendered)?;
+                    continue;
+                }
+                (ScanState::Quoted(q), _) if q == c => state = ScanState::Plain,
+                // The escaped char never closes the quoted string.
+                (ScanState::Quoted(q), '\\') if q != '`' => {
+                    rendered.push(c);
+                    rendered.extend(chars.next());
+                    continue;
+                }
+                (ScanState::LineComment, '\n') => state = ScanState::Plain,
+                (ScanState::BlockComment, '*') if chars.peek() == Some(&'/') => {
+                    rendered.push(c);
+                    rendered.extend(chars.next());
+                    state = ScanState::Plain;
+                    continue;
+                }
+                _ => {}
+            }
+            rendered.push(c);
+        }
+
+        if params_iter.next().is_some() {
+            return Err(Error::Client(format!(
+                "Too many params for sql, params:{}, sql:{sql}",
+                params.len()
+            )));
+        }
+
+        Ok(Self {
+            tables,
+            sql: rendered,
+        })
+    }
+}
+
+/// Where the scanner of [`Request::with_params`] is in the sql.
+#[derive(Clone, Copy)]
+enum ScanState {
+    Plain,
+    /// In the string or identifier quoted by the char.
+    Quoted(char),
+    LineComment,
+    BlockComment,
+}
+
+#[allow(clippy::result_large_err)]
+fn render_param(param: &Value, rendered: &mut String) -> Result<()> {
+    match param {
+        Value::Null => rendered.push_str("NULL"),
+        Value::Timestamp(v) => rendered.push_str(&v.to_string()),
+        Value::Double(v) => render_float(*v, rendered)?,
+        Value::Float(v) => render_float(*v as f64, rendered)?,
+        Value::Varbinary(v) => {
+            rendered.push_str("X'");
+            for byte in v {
+                rendered.push_str(&format!("{byte:02x}"));
+            }
+            rendered.push('\'');
+        }
+        Value::String(v) => {
+            if v.contains('\0') {
+                return Err(Error::Client(format!(
+                    "String param with nul can't be represented in sql, param:{v:?}"
+                )));
+            }
+            rendered.push('\'');
+            rendered.push_str(&v.replace('\\', "\\\\").replace('\'', "''"));
+            rendered.push('\'');
+        }
+        Value::UInt64(v) => rendered.push_str(&v.to_string()),
+        Value::UInt32(v) => rendered.push_str(&v.to_string()),
+        Value::UInt16(v) => rendered.push_str(&v.to_string()),
+        Value::UInt8(v) => rendered.push_str(&v.to_string()),
+        Value::Int64(v) => rendered.push_str(&v.to_string()),
+        Value::Int32(v) => rendered.push_str(&v.to_string()),
+        Value::Int16(v) => rendered.push_str(&v.to_string()),
+        Value::Int8(v) => rendered.push_str(&v.to_string()),
+        Value::Boolean(v) => rendered.push_str(if *v { "TRUE" } else { "FALSE" }),
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::result_large_err)]
+fn render_float(v: f64, rendered: &mut String) -> Result<()> {
+    if !v.is_finite() {
+        return Err(Error::Client(format!(
+            "Float param can't be represented in sql, param:{v}"
+        )));
+    }
+    rendered.push_str(&v.to_string());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Request;
+    use crate::{model::value::Value, Error};
+
+    #[test]
+    fn test_validate() {
+        let make_req = |tables: &[&str], sql: &str| Request {
+            tables: tables.iter().map(|table| table.to_string()).collect(),
+            sql: sql.to_string(),
+        };
+
+        assert!(make_req(&["cpu"], "SELECT * FROM cpu").validate().is_ok());
+        assert!(make_req(&[], "SELECT 1").validate().is_ok());
+        for req in [
+            make_req(&["cpu"], ""),
+            make_req(&["cpu"], " \n\t"),
+            make_req(&["cpu", ""], "SELECT * FROM cpu"),
+        ] {
+            assert!(matches!(req.validate(), Err(Error::Client(_))));
+        }
+    }
+
+    #[test]
+    fn test_with_params() {
+        let req = Request::with_params(
+            vec!["cpu".to_string()],
+            "SELECT * FROM cpu WHERE host = ? AND t >= ? AND note = '?' AND \"x?\" > ?",
+            &[
+                Value::String("a' OR '1'='1".to_string()),
+                Value::Timestamp(1700000000000),
+                Value::Double(0.5),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            req.sql,
+            "SELECT * FROM cpu WHERE host = 'a'' OR ''1''=''1' AND t >= 1700000000000 AND note = '?' AND \"x?\" > 0.5"
+        );
+        assert_eq!(req.tables, vec!["cpu".to_string()]);
+
+        let req = Request::with_params(
+            vec![],
+            "SELECT ?, ?, ?, ?",
+            &[
+                Value::Null,
+                Value::Boolean(true),
+                Value::Varbinary(vec![0xca, 0xfe]),
+                Value::Int8(-1),
+            ],
+        )
+        .unwrap();
+        assert_eq!(req.sql, "SELECT NULL, TRUE, X'cafe', -1");
+
+        // The backslash in the param can't escape the closing quote.
+        let req = Request::with_params(
+            vec![],
+            "SELECT * FROM t WHERE a = ? AND b = ?",
+            &[
+                Value::String("x\\".to_string()),
+                Value::String(" OR 1=1 --".to_string()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            req.sql,
+            "SELECT * FROM t WHERE a = 'x\\\\' AND b = ' OR 1=1 --'"
+        );
+    }
+
+    #[test]
+    fn test_with_params_skip_quotes_and_comments() {
+        let sql = "SELECT `a?`, 'b\\'?', ? -- c?\n/* d? */ FROM t /*/ e? */ WHERE f = ?";
+        let req = Request::with_params(vec![], sql, &[Value::Int64(1), Value::Int64(2)]).unwrap();
+        assert_eq!(
+            req.sql,
+            "SELECT `a?`, 'b\\'?', 1 -- c?\n/* d? */ FROM t /*/ e? */ WHERE f = 2"
+        );
+
+        // The placeholder in the comment is not counted.
+        assert!(Request::with_params(vec![], "SELECT ? -- ?", &[Value::Int64(1)]).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let cases: Vec<(&str, Vec<Value>)> = vec![
+            ("SELECT ?, ?", vec![Value::Int64(1)]),
+            ("SELECT ?", vec![Value::Int64(1), Value::Int64(2)]),
+            ("SELECT ?", vec![Value::Double(f64::NAN)]),
+            ("SELECT ?", vec![Value::Float(f32::INFINITY)]),
+            ("SELECT ?", vec![Value::String("a\0b".to_string())]),
+        ];
+        for (sql, params) in cases {
+            assert!(
+                Request::with_params(vec![], sql, &params).is_err(),
+                "sql:{sql}, params:{params:?}"
+            );
+        }
+    }
+}
@@ -17,48 +17,270 @@
 
 use std::io::Cursor;
 
-use arrow::{ipc::reader::StreamReader, record_batch::RecordBatch};
+use arrow::{datatypes::SchemaRef, ipc::reader::StreamReader, record_batch::RecordBatch};
+use futures::stream::BoxStream;
 use horaedbproto::storage::{
     arrow_payload::Compression, sql_query_response::Output as OutputPb, ArrowPayload,
     SqlQueryResponse,
 };
 
 use crate::{
-    errors::{Error, Result},
-    model::sql_query::row::{Row, RowBuilder},
+    errors::{ConvertError, Error, Result},
+    model::{
+        sql_query::row::{self, Column, Row, RowBuilder},
+        value::{DataType, Value},
+    },
 };
 
 /// The response for [`SqlQueryRequest`](crate::model::sql_query::Request).
-#[derive(Debug, Default)]
+///
+/// More fields may be added, so it is made from the default one outside of
+/// the crate.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct Response {
     /// The affected rows by the query sql.
     pub affected_rows: u32,
     /// The rows of the sql result.
     pub rows: Vec<Row>,
+    /// The column names of the sql result.
+    ///
+    /// They are known even if no rows are returned, but empty if the sql
+    /// returns the affected rows instead, e.g. `INSERT` and `CREATE TABLE`.
+    pub column_names: Vec<String>,
+    /// The data types of the columns in the same order as `column_names`,
+    /// that is to say, the types of the non-null values in them.
+    pub column_types: Vec<DataType>,
+    /// Whether the columns may contain nulls in the same order as
+    /// `column_names`, as declared by the schema of the result set.
+    pub column_nullables: Vec<bool>,
+    /// Whether the rows are only the ones arrived before the deadline, see
+    /// [`DbClient::sql_query_best_effort`](crate::DbClient::sql_query_best_effort).
+    pub truncated: bool,
 }
 
+#[allow(clippy::result_large_err)]
+impl Response {
+    /// Make the response from the streamed rows, and the columns are taken from
+    /// the first row, typed by their first non-null values.
+    pub(crate) fn with_rows(rows: Vec<Row>) -> Self {
+        let column_names: Vec<_> = rows
+            .first()
+            .map(|row| row.iter().map(|(name, _)| name.to_string()).collect())
+            .unwrap_or_default();
+        let column_types = (0..column_names.len())
+            .map(|idx| {
+                rows.iter()
+                    .filter_map(|row| row.get(idx))
+                    .find(|value| !value.is_null())
+                    .map(|value| value.data_type())
+                    .unwrap_or(DataType::Null)
+            })
+            .collect();
+        // Unknown without the schema.
+        let column_nullables = vec![true; column_names.len()];
+
+        Self {
+            rows,
+            column_names,
+            column_types,
+            column_nullables,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the sql returns a result set, which may contain no rows at all,
+    /// e.g. a `SELECT` matching nothing.
+    pub fn has_schema(&self) -> bool {
+        !self.column_names.is_empty()
+    }
+
+    /// Whether the sql returns the affected rows instead of a result set, e.g.
+    /// `INSERT` and `CREATE TABLE`, the opposite of
+    /// [`has_schema`](Response::has_schema).
+    pub fn is_mutation(&self) -> bool {
+        !self.has_schema()
+    }
+
+    /// The position of the column of the `name`, or `None` if not found.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.column_names.iter().position(|column| column == name)
+    }
+
+    /// The data type of the column of the `name`, or `None` if not found.
+    pub fn data_type(&self, name: &str) -> Option<DataType> {
+        self.column_index(name)
+            .and_then(|idx| self.column_types.get(idx).copied())
+    }
+
+    /// Whether the column of the `name` may contain nulls, or `None` if not
+    /// found.
+    pub fn is_nullable(&self, name: &str) -> Option<bool> {
+        self.column_index(name)
+            .and_then(|idx| self.column_nullables.get(idx).copied())
+    }
+
+    /// Merge the `other` into the response, that is to say, the affected rows
+    /// are added up and the rows are concatenated, and it is truncated if
+    /// either is. A column is nullable if it is in either.
+    ///
+    /// Error will be returned if the columns of them are different.
+    pub fn merge(&mut self, other: Response) -> Result<()> {
+        if self.column_names != other.column_names || self.column_types != other.column_types {
+            return Err(Error::Client(format!(
+                "Failed to merge responses of different columns, left:{:?}, right:{:?}",
+                self.column_names, other.column_names
+            )));
+        }
+
+        self.affected_rows += other.affected_rows;
+        self.rows.extend(other.rows);
+        if self.column_nullables.len() == other.column_nullables.len() {
+            for (nullable, other_nullable) in
+                self.column_nullables.iter_mut().zip(other.column_nullables)
+            {
+                *nullable |= other_nullable;
+            }
+        }
+        self.truncated |= other.truncated;
+        Ok(())
+    }
+
+    /// Iterate the rows by reference, which provide the named and typed access
+    /// to the columns.
+    pub fn iter_rows(&self) -> impl Iterator<Item = &Row> {
+        self.rows.iter()
+    }
+}
+
+/// Iterate the rows only, and the columns can be read from the response before
+/// it is consumed.
+impl IntoIterator for Response {
+    type IntoIter = std::vec::IntoIter<Row>;
+    type Item = Row;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Response {
+    type IntoIter = std::slice::Iter<'a, Row>;
+    type Item = &'a Row;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
+/// The undecoded response for [`SqlQueryRequest`](crate::model::sql_query::Request),
+/// for the users decoding the arrow payload with their own tooling.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawResponse {
+    /// The affected rows by the query sql.
+    pub affected_rows: u32,
+    /// The record batches in the arrow ipc stream format as sent by the
+    /// server, and every one of them may contain multiple record batches.
+    pub record_batches: Vec<Vec<u8>>,
+    /// The compression of every one in the `record_batches`.
+    pub compression: PayloadCompression,
+}
+
+/// Compression of the arrow payload in [`RawResponse`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PayloadCompression {
+    #[default]
+    None,
+    Zstd,
+}
+
+impl TryFrom<SqlQueryResponse> for RawResponse {
+    type Error = Error;
+
+    fn try_from(sql_resp_pb: SqlQueryResponse) -> std::result::Result<Self, Self::Error> {
+        let output_pb = sql_resp_pb
+            .output
+            .ok_or_else(|| Error::Unknown("output is empty in sql query response".to_string()))?;
+
+        let resp = match output_pb {
+            OutputPb::AffectedRows(affected) => RawResponse {
+                affected_rows: affected,
+                ..Default::default()
+            },
+            OutputPb::Arrow(arrow_payload) => {
+                let compression = match arrow_payload.compression() {
+                    Compression::None => PayloadCompression::None,
+                    Compression::Zstd => PayloadCompression::Zstd,
+                };
+                RawResponse {
+                    record_batches: arrow_payload.record_batches,
+                    compression,
+                    ..Default::default()
+                }
+            }
+        };
+
+        Ok(resp)
+    }
+}
+
+/// The stream of rows returned by the streaming sql query.
+///
+/// The rows are decoded lazily, that is to say, one response from the server
+/// is decoded only when its first row is polled.
+pub type RowStream = BoxStream<'static, Result<Row>>;
+
 #[derive(Debug)]
 enum Output {
     AffectedRows(u32),
-    Rows(Vec<Row>),
+    Rows {
+        column_names: Vec<String>,
+        column_types: Vec<DataType>,
+        column_nullables: Vec<bool>,
+        rows: Vec<Row>,
+    },
 }
 
 impl TryFrom<SqlQueryResponse> for Response {
     type Error = Error;
 
     fn try_from(sql_resp_pb: SqlQueryResponse) -> std::result::Result<Self, Self::Error> {
+        Response::from_pb(sql_resp_pb, None, false)
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl Response {
+    /// Decode the response, and fail if it contains more rows than `max_rows`,
+    /// see [`RpcConfig::max_response_rows`](crate::RpcConfig::max_response_rows).
+    ///
+    /// The batches of the different columns are tolerated if `permissive`,
+    /// see [`RpcConfig::permissive_decode`](crate::RpcConfig::permissive_decode).
+    pub(crate) fn from_pb(
+        sql_resp_pb: SqlQueryResponse,
+        max_rows: Option<usize>,
+        permissive: bool,
+    ) -> Result<Self> {
         let output_pb = sql_resp_pb
             .output
             .ok_or_else(|| Error::Unknown("output is empty in sql query response".to_string()))?;
-        let output = Output::try_from(output_pb)?;
+        let output = Output::from_pb(output_pb, max_rows, permissive)?;
 
         let resp = match output {
             Output::AffectedRows(affected) => Response {
                 affected_rows: affected,
                 ..Default::default()
             },
-            Output::Rows(rows) => Response {
+            Output::Rows {
+                column_names,
+                column_types,
+                column_nullables,
                 rows,
+            } => Response {
+                rows,
+                column_names,
+                column_types,
+                column_nullables,
                 ..Default::default()
             },
         };
@@ -67,14 +289,38 @@ impl TryFrom<SqlQueryResponse> for Response {
     }
 }
 
-impl TryFrom<OutputPb> for Output {
-    type Error = Error;
-
-    fn try_from(output_pb: OutputPb) -> std::result::Result<Self, Self::Error> {
+#[allow(clippy::result_large_err)]
+impl Output {
+    fn from_pb(output_pb: OutputPb, max_rows: Option<usize>, permissive: bool) -> Result<Self> {
         let output = match output_pb {
             OutputPb::AffectedRows(affected) => Output::AffectedRows(affected),
             OutputPb::Arrow(arrow_payload) => {
-                let arrow_record_batches = decode_arrow_payload(arrow_payload)?;
+                let (schema, arrow_record_batches) =
+                    decode_arrow_payload(arrow_payload, permissive)?;
+                // Check before building the rows, which take much more memory than
+                // the record batches.
+                if let Some(max_rows) = max_rows {
+                    let num_rows: usize = arrow_record_batches
+                        .iter()
+                        .map(|batch| batch.num_rows())
+                        .sum();
+                    if num_rows > max_rows {
+                        return Err(Error::Client("row limit exceeded".to_string()));
+                    }
+                }
+                // The schema is still sent by the server if no rows are returned.
+                let fields = schema
+                    .map(|schema| schema.fields().clone())
+                    .unwrap_or_default();
+                let columns = row::flatten_fields(&fields)?;
+                let mut column_names = Vec::with_capacity(columns.len());
+                let mut column_types = Vec::with_capacity(columns.len());
+                let mut column_nullables = Vec::with_capacity(columns.len());
+                for (name, data_type, nullable) in columns {
+                    column_names.push(name);
+                    column_types.push(data_type);
+                    column_nullables.push(nullable);
+                }
                 let rows_group = arrow_record_batches
                     .into_iter()
                     .map(|record_batch| {
@@ -85,9 +331,20 @@ impl TryFrom<OutputPb> for Output {
                         Ok(row_builder.build())
                     })
                     .collect::<Result<Vec<_>>>()?;
-                let rows = rows_group.into_iter().flatten().collect::<Vec<_>>();
+                let mut rows = rows_group.into_iter().flatten().collect::<Vec<_>>();
+                if permissive {
+                    rows = rows
+                        .into_iter()
+                        .map(|row| fit_row(row, &column_names))
+                        .collect();
+                }
 
-                Output::Rows(rows)
+                Output::Rows {
+                    column_names,
+                    column_types,
+                    column_nullables,
+                    rows,
+                }
             }
         };
 
@@ -95,37 +352,98 @@ impl TryFrom<OutputPb> for Output {
     }
 }
 
-pub fn decode_arrow_payload(arrow_payload: ArrowPayload) -> Result<Vec<RecordBatch>> {
+/// Pad the row with the nulls of the missing trailing columns, or truncate
+/// the extra trailing columns of it, to fit the `column_names`.
+fn fit_row(row: Row, column_names: &[String]) -> Row {
+    if row.columns().len() == column_names.len() {
+        return row;
+    }
+
+    let mut columns = row.into_columns();
+    columns.truncate(column_names.len());
+    let present = columns.len();
+    for name in &column_names[present..] {
+        columns.push(Column::new(name.clone(), Value::Null));
+    }
+    Row::new(columns)
+}
+
+/// Decode the payload, and the schema of the first byte batch is returned
+/// too, which is `None` only if the payload contains no byte batches.
+///
+/// The byte batches of the different column counts from the first one fail
+/// the decode, unless `permissive` for which they are only warned.
+#[allow(clippy::result_large_err)]
+pub fn decode_arrow_payload(
+    arrow_payload: ArrowPayload,
+    permissive: bool,
+) -> Result<(Option<SchemaRef>, Vec<RecordBatch>)> {
     let compression = arrow_payload.compression();
     let byte_batches = arrow_payload.record_batches;
 
     // Maybe unzip payload bytes firstly.
     let unzip_byte_batches = byte_batches
         .into_iter()
-        .map(|bytes_batch| match compression {
+        .enumerate()
+        .map(|(batch_index, bytes_batch)| match compression {
             Compression::None => Ok(bytes_batch),
-            Compression::Zstd => zstd::stream::decode_all(Cursor::new(bytes_batch))
-                .map_err(|e| Error::DecodeArrowPayload(Box::new(e))),
+            Compression::Zstd => {
+                zstd::stream::decode_all(Cursor::new(bytes_batch)).map_err(|source| {
+                    Error::Convert(ConvertError::Decompress {
+                        batch_index,
+                        source,
+                    })
+                })
+            }
         })
         .collect::<Result<Vec<Vec<u8>>>>()?;
 
     // Decode the byte batches to record batches, multiple record batches may be
     // included in one byte batch.
+    let mut schema = None;
     let record_batches_group = unzip_byte_batches
         .into_iter()
-        .map(|byte_batch| {
+        .enumerate()
+        .map(|(batch_index, byte_batch)| {
             // Decode bytes to `RecordBatch`.
-            let stream_reader = match StreamReader::try_new(Cursor::new(byte_batch), None)
-                .map_err(|e| Error::DecodeArrowPayload(Box::new(e)))
-            {
-                Ok(reader) => reader,
-                Err(e) => return Err(e),
-            };
+            let stream_reader =
+                match StreamReader::try_new(Cursor::new(byte_batch), None).map_err(|source| {
+                    Error::Convert(ConvertError::SchemaParse {
+                        batch_index,
+                        source,
+                    })
+                }) {
+                    Ok(reader) => reader,
+                    Err(e) => return Err(e),
+                };
+            match &schema {
+                None => schema = Some(stream_reader.schema()),
+                Some(schema) => {
+                    let (expected, actual) =
+                        (schema.fields().len(), stream_reader.schema().fields().len());
+                    if expected != actual && permissive {
+                        log::warn!(
+                            "Decode batch:{batch_index} permissively, expected columns:{expected}, actual:{actual}"
+                        );
+                    } else if expected != actual {
+                        return Err(Error::Convert(ConvertError::ColumnCountMismatch {
+                            batch_index,
+                            expected,
+                            actual,
+                        }));
+                    }
+                }
+            }
 
             stream_reader
                 .into_iter()
                 .map(|decode_result| {
-                    decode_result.map_err(|e| Error::DecodeArrowPayload(Box::new(e)))
+                    decode_result.map_err(|source| {
+                        Error::Convert(ConvertError::RowParse {
+                            batch_index,
+                            source,
+                        })
+                    })
                 })
                 .collect::<Result<Vec<_>>>()
         })
@@ -136,5 +454,438 @@ pub fn decode_arrow_payload(arrow_payload: ArrowPayload) -> Result<Vec<RecordBat
         .flatten()
         .collect::<Vec<_>>();
 
-    Ok(record_batches)
+    Ok((schema, record_batches))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{Int64Array, StringArray},
+        datatypes::{DataType, Field, Schema},
+        ipc::writer::StreamWriter,
+        record_batch::RecordBatch,
+    };
+    use horaedbproto::storage::{
+        arrow_payload::Compression, sql_query_response::Output as OutputPb, ArrowPayload,
+        SqlQueryResponse,
+    };
+
+    use super::Response;
+    use crate::{
+        errors::{ConvertError, Error},
+        model::{
+            sql_query::row::{Column, Row},
+            value::{DataType as ValueDataType, Value},
+        },
+    };
+
+    #[test]
+    fn test_iter_rows() {
+        let resp = Response {
+            affected_rows: 0,
+            column_names: vec!["host".to_string(), "value".to_string()],
+            column_types: vec![ValueDataType::String, ValueDataType::Int64],
+            column_nullables: vec![false, false],
+            rows: vec![
+                Row::new(vec![
+                    Column::new("host".to_string(), Value::String("a".to_string())),
+                    Column::new("value".to_string(), Value::Int64(1)),
+                ]),
+                Row::new(vec![
+                    Column::new("host".to_string(), Value::String("b".to_string())),
+                    Column::new("value".to_string(), Value::Int64(2)),
+                ]),
+            ],
+            truncated: false,
+        };
+
+        let hosts: Vec<_> = resp
+            .iter_rows()
+            .map(|row| row.get_string("host").unwrap())
+            .collect();
+        assert_eq!(hosts, vec!["a", "b"]);
+        let pairs: Vec<_> = resp.iter_rows().next().unwrap().iter().collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("host", &Value::String("a".to_string())),
+                ("value", &Value::Int64(1))
+            ]
+        );
+
+        assert_eq!(Response::default().iter_rows().count(), 0);
+        let resp = Response {
+            affected_rows: 3,
+            ..Default::default()
+        };
+        assert_eq!(resp.iter_rows().count(), 0);
+    }
+
+    fn make_arrow_response(batches: &[RecordBatch], schema: &Schema) -> SqlQueryResponse {
+        let mut buf = Vec::new();
+        let mut writer = StreamWriter::try_new(&mut buf, schema).unwrap();
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+
+        SqlQueryResponse {
+            header: None,
+            output: Some(OutputPb::Arrow(ArrowPayload {
+                record_batches: vec![buf],
+                compression: 0,
+            })),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let make_resp = |names: &[&str], values: &[i64]| Response {
+            affected_rows: 0,
+            column_names: names.iter().map(|name| name.to_string()).collect(),
+            column_types: vec![ValueDataType::Int64; names.len()],
+            column_nullables: vec![false; names.len()],
+            rows: values
+                .iter()
+                .map(|v| {
+                    Row::new(
+                        names
+                            .iter()
+                            .map(|name| Column::new(name.to_string(), Value::Int64(*v)))
+                            .collect(),
+                    )
+                })
+                .collect(),
+            truncated: false,
+        };
+
+        let mut resp = make_resp(&["a", "b"], &[1]);
+        resp.merge(make_resp(&["a", "b"], &[])).unwrap();
+        resp.merge(make_resp(&["a", "b"], &[2, 3])).unwrap();
+        let values: Vec<_> = resp.iter_rows().map(|row| row.get_i64("b")).collect();
+        assert_eq!(values, vec![Some(1), Some(2), Some(3)]);
+
+        assert!(resp.merge(make_resp(&["a", "c"], &[4])).is_err());
+        assert!(resp.merge(make_resp(&["b", "a"], &[4])).is_err());
+        assert_eq!(resp.rows.len(), 3);
+
+        let mut resp = Response {
+            affected_rows: 1,
+            ..Default::default()
+        };
+        resp.merge(Response {
+            affected_rows: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(resp.affected_rows, 3);
+    }
+
+    #[test]
+    fn test_convert_error_of_corrupted_batch() {
+        let schema = Schema::new(vec![Field::new("value", DataType::Int64, false)]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(Int64Array::from(vec![1, 2]))],
+        )
+        .unwrap();
+        let make_resp = |mutate: fn(&mut Vec<u8>)| {
+            let mut resp = make_arrow_response(std::slice::from_ref(&batch), &schema);
+            let Some(OutputPb::Arrow(payload)) = resp.output.as_mut() else {
+                unreachable!()
+            };
+            let mut corrupted = payload.record_batches[0].clone();
+            mutate(&mut corrupted);
+            payload.record_batches.push(corrupted);
+            resp
+        };
+
+        // The schema is at the head of the batch, and the rows follow it.
+        let resp = make_resp(|bytes| bytes.truncate(4));
+        match Response::try_from(resp) {
+            Err(Error::Convert(ConvertError::SchemaParse { batch_index, .. })) => {
+                assert_eq!(batch_index, 1)
+            }
+            v => panic!("unexpected result:{v:?}"),
+        }
+        let resp = make_resp(|bytes| {
+            let len = bytes.len();
+            bytes.truncate(len - 16)
+        });
+        match Response::try_from(resp) {
+            Err(Error::Convert(ConvertError::RowParse { batch_index, .. })) => {
+                assert_eq!(batch_index, 1)
+            }
+            v => panic!("unexpected result:{v:?}"),
+        }
+    }
+
+    #[test]
+    fn test_max_rows() {
+        let schema = Schema::new(vec![Field::new("value", DataType::Int64, false)]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(Int64Array::from((0..100).collect::<Vec<_>>()))],
+        )
+        .unwrap();
+        let resp = make_arrow_response(&[batch.clone(), batch], &schema);
+
+        match Response::from_pb(resp.clone(), Some(199), false) {
+            Err(Error::Client(msg)) => assert_eq!(msg, "row limit exceeded"),
+            v => panic!("unexpected result:{v:?}"),
+        }
+        assert_eq!(
+            Response::from_pb(resp.clone(), Some(200), false)
+                .unwrap()
+                .rows
+                .len(),
+            200
+        );
+        assert_eq!(
+            Response::from_pb(resp, None, false).unwrap().rows.len(),
+            200
+        );
+    }
+
+    #[test]
+    fn test_column_count_mismatch() {
+        let make_payload = |names: &[&str]| {
+            let schema = Schema::new(
+                names
+                    .iter()
+                    .map(|name| Field::new(*name, DataType::Int64, false))
+                    .collect::<Vec<_>>(),
+            );
+            let columns = names
+                .iter()
+                .map(|_| Arc::new(Int64Array::from(vec![1])) as _)
+                .collect();
+            let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns).unwrap();
+            make_arrow_response(&[batch], &schema)
+        };
+
+        let mut resp = make_payload(&["a", "b"]);
+        let Some(OutputPb::Arrow(short)) = make_payload(&["a"]).output else {
+            unreachable!()
+        };
+        if let Some(OutputPb::Arrow(payload)) = resp.output.as_mut() {
+            payload.record_batches.extend(short.record_batches);
+        }
+        match Response::try_from(resp) {
+            Err(Error::Convert(ConvertError::ColumnCountMismatch {
+                batch_index,
+                expected,
+                actual,
+            })) => assert_eq!((batch_index, expected, actual), (1, 2, 1)),
+            v => panic!("unexpected result:{v:?}"),
+        }
+    }
+
+    #[test]
+    fn test_permissive_decode() {
+        let make_payload = |names: &[&str], value: i64| {
+            let schema = Schema::new(
+                names
+                    .iter()
+                    .map(|name| Field::new(*name, DataType::Int64, false))
+                    .collect::<Vec<_>>(),
+            );
+            let columns = names
+                .iter()
+                .map(|_| Arc::new(Int64Array::from(vec![value])) as _)
+                .collect();
+            let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns).unwrap();
+            make_arrow_response(&[batch], &schema)
+        };
+        let mut resp = make_payload(&["a", "b"], 1);
+        for other in [make_payload(&["a"], 2), make_payload(&["a", "b", "c"], 3)] {
+            let Some(OutputPb::Arrow(other)) = other.output else {
+                unreachable!()
+            };
+            if let Some(OutputPb::Arrow(payload)) = resp.output.as_mut() {
+                payload.record_batches.extend(other.record_batches);
+            }
+        }
+
+        assert!(matches!(
+            Response::from_pb(resp.clone(), None, false),
+            Err(Error::Convert(ConvertError::ColumnCountMismatch { .. }))
+        ));
+
+        let resp = Response::from_pb(resp, None, true).unwrap();
+        assert_eq!(resp.column_names, vec!["a", "b"]);
+        let rows: Vec<Vec<_>> = resp
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect()
+            })
+            .collect();
+        let column = |name: &str, value| (name.to_string(), value);
+        assert_eq!(
+            rows,
+            vec![
+                vec![column("a", Value::Int64(1)), column("b", Value::Int64(1))],
+                // The missing field is padded with null.
+                vec![column("a", Value::Int64(2)), column("b", Value::Null)],
+                // The extra field is ignored.
+                vec![column("a", Value::Int64(3)), column("b", Value::Int64(3))],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zstd_compressed_payload() {
+        let schema = Schema::new(vec![Field::new("value", DataType::Int64, false)]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(Int64Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap();
+        let mut resp = make_arrow_response(std::slice::from_ref(&batch), &schema);
+        let Some(OutputPb::Arrow(payload)) = resp.output.as_mut() else {
+            unreachable!()
+        };
+        let compressed = payload
+            .record_batches
+            .iter()
+            .map(|bytes| zstd::stream::encode_all(&bytes[..], 0).unwrap())
+            .collect();
+        payload.record_batches = compressed;
+        payload.set_compression(Compression::Zstd);
+
+        let resp = Response::try_from(resp.clone()).unwrap();
+        let values: Vec<_> = resp.iter_rows().map(|row| row.get_i64("value")).collect();
+        assert_eq!(values, vec![Some(1), Some(2), Some(3)]);
+
+        // The uncompressed bytes are rejected if compression is indicated.
+        let mut resp = make_arrow_response(&[batch], &schema);
+        if let Some(OutputPb::Arrow(payload)) = resp.output.as_mut() {
+            payload.set_compression(Compression::Zstd);
+        }
+        assert!(matches!(
+            Response::try_from(resp),
+            Err(Error::Convert(ConvertError::Decompress {
+                batch_index: 0,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_schema_but_no_rows() {
+        let schema = Schema::new(vec![
+            Field::new("host", DataType::Utf8, false),
+            Field::new("value", DataType::Int64, false),
+        ]);
+        let empty_batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(StringArray::from(Vec::<&str>::new())),
+                Arc::new(Int64Array::from(Vec::<i64>::new())),
+            ],
+        )
+        .unwrap();
+        let expected_names = vec!["host".to_string(), "value".to_string()];
+
+        // Both the empty record batch and the schema without any record batch.
+        for batches in [vec![empty_batch], vec![]] {
+            let resp = Response::try_from(make_arrow_response(&batches, &schema)).unwrap();
+            assert!(resp.has_schema());
+            assert!(resp.rows.is_empty());
+            assert_eq!(resp.column_names, expected_names);
+            assert_eq!(resp.data_type("host"), Some(ValueDataType::String));
+            assert_eq!(resp.data_type("value"), Some(ValueDataType::Int64));
+            assert_eq!(resp.column_index("value"), Some(1));
+            assert_eq!(resp.data_type("missing"), None);
+        }
+
+        let resp = Response::try_from(SqlQueryResponse {
+            header: None,
+            output: Some(OutputPb::AffectedRows(1)),
+        })
+        .unwrap();
+        assert!(!resp.has_schema());
+        assert_eq!(resp.affected_rows, 1);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let schema = Schema::new(vec![Field::new("value", DataType::Int64, false)]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(Int64Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap();
+        let resp = Response::try_from(make_arrow_response(&[batch], &schema)).unwrap();
+
+        let mut values = Vec::new();
+        for row in &resp {
+            values.push(row.get_i64("value").unwrap());
+        }
+        assert_eq!(values, vec![1, 2, 3]);
+        assert_eq!(resp.column_names, vec!["value".to_string()]);
+
+        let rows: Vec<Row> = resp.into_iter().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2].get_i64(0), Some(3));
+    }
+
+    #[test]
+    fn test_is_mutation() {
+        // Even if no rows are affected.
+        for affected_rows in [0, 3] {
+            let resp = Response::try_from(SqlQueryResponse {
+                header: None,
+                output: Some(OutputPb::AffectedRows(affected_rows)),
+            })
+            .unwrap();
+            assert!(resp.is_mutation());
+            assert_eq!(resp.affected_rows, affected_rows);
+        }
+
+        // The result set is not, even if it is empty.
+        let schema = Schema::new(vec![Field::new("value", DataType::Int64, false)]);
+        let resp = Response::try_from(make_arrow_response(&[], &schema)).unwrap();
+        assert!(!resp.is_mutation());
+        assert!(resp.rows.is_empty());
+    }
+
+    #[test]
+    fn test_column_nullables() {
+        let schema = Schema::new(vec![
+            Field::new("host", DataType::Utf8, false),
+            Field::new("value", DataType::Int64, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b"])),
+                Arc::new(Int64Array::from(vec![Some(1), None])),
+            ],
+        )
+        .unwrap();
+        let mut resp = Response::try_from(make_arrow_response(&[batch], &schema)).unwrap();
+        assert_eq!(resp.column_nullables, vec![false, true]);
+        assert_eq!(resp.is_nullable("host"), Some(false));
+        assert_eq!(resp.is_nullable("value"), Some(true));
+        assert_eq!(resp.is_nullable("missing"), None);
+        assert_eq!(resp.rows[0].get("value"), Some(&Value::Int64(1)));
+        assert_eq!(resp.rows[1].get("value"), Some(&Value::Null));
+
+        // The column nullable in either response is nullable after merging.
+        let schema = Schema::new(vec![
+            Field::new("host", DataType::Utf8, true),
+            Field::new("value", DataType::Int64, false),
+        ]);
+        let other = Response::try_from(make_arrow_response(&[], &schema)).unwrap();
+        assert_eq!(other.column_nullables, vec![true, false]);
+        resp.merge(other).unwrap();
+        assert_eq!(resp.column_nullables, vec![true, true]);
+    }
 }
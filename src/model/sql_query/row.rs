@@ -15,21 +15,72 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::fmt::Display;
+
 use arrow::{
     array::{
-        ArrayAccessor, ArrayRef, AsArray, BinaryArray, BooleanArray, Float32Array, Float64Array,
-        Int16Array, Int32Array, Int64Array, Int8Array, StringArray, Time32MillisecondArray,
-        TimestampMillisecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+        ArrayAccessor, ArrayRef, AsArray, BinaryArray, BooleanArray, Date32Array, Date64Array,
+        Decimal128Array, FixedSizeBinaryArray, Float32Array, Float64Array, Int16Array, Int32Array,
+        Int64Array, Int8Array, StringArray, Time32MillisecondArray, TimestampMicrosecondArray,
+        TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray, UInt16Array,
+        UInt32Array, UInt64Array, UInt8Array,
     },
-    datatypes::{DataType, Int32Type, TimeUnit},
+    datatypes::{DataType, Fields, Int32Type, TimeUnit},
     record_batch::RecordBatch,
 };
 use paste::paste;
 
-use crate::{model::value::Value, Error, Result};
+use crate::{
+    errors::ConvertError,
+    model::value::{DataType as ValueDataType, TimestampMs, TimestampResolution, Value},
+    Error, Result,
+};
+
+macro_rules! impl_typed_getter {
+    ($ty:ty, $variant:ident, $suffix:ident $(, $widened:ident)*) => {
+        paste! {
+            #[doc = concat!("Get the `", stringify!($ty), "` value of the column by its position or name.")]
+            ///
+            /// `None` will be returned if the column is not found, null or of
+            /// another type which can't be widened losslessly.
+            pub fn [<get_ $suffix>](&self, idx: impl ColumnIndex) -> Option<$ty> {
+                self.[<try_get_ $suffix>](idx).ok().flatten()
+            }
+
+            #[doc = concat!("Get the `", stringify!($ty), "` value of the column by its position or name.")]
+            ///
+            #[doc = concat!("Different from [`get_", stringify!($suffix), "`](Row::get_", stringify!($suffix), "),")]
+            /// [`TypeError`] will be returned if the column is of another type
+            /// which can't be widened losslessly.
+            pub fn [<try_get_ $suffix>](&self, idx: impl ColumnIndex) -> std::result::Result<Option<$ty>, TypeError> {
+                match self.get(idx) {
+                    None | Some(Value::Null) => Ok(None),
+                    Some(Value::$variant(v)) => Ok(Some(*v)),
+                    $(Some(Value::$widened(v)) => Ok(Some(<$ty>::from(*v))),)*
+                    Some(v) => Err(TypeError {
+                        expected: ValueDataType::$variant,
+                        actual: v.data_type(),
+                    }),
+                }
+            }
+        }
+    };
+}
 
 /// A row in the
 /// [`SqlQueryResponse`](crate::model::sql_query::Response).
+///
+/// The typed getters widen the narrower values losslessly, and reject the
+/// others including the narrowing ones:
+///
+/// | getter      | accepted values                                                |
+/// |-------------|----------------------------------------------------------------|
+/// | `i64`       | `Int64`, `Int32`, `Int16`, `Int8`, `UInt32`, `UInt16`, `UInt8` |
+/// | `i32`       | `Int32`, `Int16`, `Int8`, `UInt16`, `UInt8`                    |
+/// | `f64`       | `Double`, `Float`                                              |
+/// | `f32`       | `Float`                                                        |
+/// | `timestamp` | `Timestamp`                                                    |
+/// | `bool`      | `Boolean`                                                      |
 #[derive(Clone, Debug, PartialEq)]
 pub struct Row {
     // It is better to iterate in a fixed order, also can save memory.
@@ -37,6 +88,12 @@ pub struct Row {
 }
 
 impl Row {
+    /// Make the row from the columns, e.g. for the canned query responses in
+    /// tests.
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self { columns }
+    }
+
     /// Find the [`Column`] by the column name.
     pub fn column(&self, name: &str) -> Option<&Column> {
         self.columns.iter().find(|column| column.name == name)
@@ -46,8 +103,110 @@ impl Row {
     pub fn columns(&self) -> &[Column] {
         &self.columns
     }
+
+    pub(crate) fn into_columns(self) -> Vec<Column> {
+        self.columns
+    }
+
+    /// Iterate the `(name, value)` pairs of the columns in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.columns
+            .iter()
+            .map(|column| (column.name.as_str(), &column.value))
+    }
+
+    /// Get the [`Value`] of the column by its position or name.
+    ///
+    /// `None` will be returned if the column is not found.
+    pub fn get(&self, idx: impl ColumnIndex) -> Option<&Value> {
+        idx.position(self).map(|pos| &self.columns[pos].value)
+    }
+
+    impl_typed_getter!(i64, Int64, i64, Int32, Int16, Int8, UInt32, UInt16, UInt8);
+    impl_typed_getter!(i32, Int32, i32, Int16, Int8, UInt16, UInt8);
+    impl_typed_getter!(f64, Double, f64, Float);
+    impl_typed_getter!(f32, Float, f32);
+    impl_typed_getter!(TimestampMs, Timestamp, timestamp);
+    impl_typed_getter!(bool, Boolean, bool);
+
+    /// Get the string value of the column by its position or name.
+    ///
+    /// `None` will be returned if the column is not found, null or not a
+    /// string.
+    pub fn get_string(&self, idx: impl ColumnIndex) -> Option<&str> {
+        self.try_get_string(idx).ok().flatten()
+    }
+
+    /// Get the string value of the column by its position or name.
+    ///
+    /// Different from [`get_string`](Row::get_string), [`TypeError`] will be
+    /// returned if the column is not a string.
+    pub fn try_get_string(
+        &self,
+        idx: impl ColumnIndex,
+    ) -> std::result::Result<Option<&str>, TypeError> {
+        match self.get(idx) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(v)) => Ok(Some(v.as_str())),
+            Some(v) => Err(TypeError {
+                expected: ValueDataType::String,
+                actual: v.data_type(),
+            }),
+        }
+    }
+}
+
+/// The index to find a column in the [`Row`], either its position or its
+/// name.
+pub trait ColumnIndex {
+    /// Return the position of the column in the `row`, or `None` if not found.
+    fn position(&self, row: &Row) -> Option<usize>;
+}
+
+impl ColumnIndex for usize {
+    fn position(&self, row: &Row) -> Option<usize> {
+        (*self < row.columns.len()).then_some(*self)
+    }
+}
+
+impl ColumnIndex for str {
+    fn position(&self, row: &Row) -> Option<usize> {
+        row.columns.iter().position(|column| column.name == self)
+    }
+}
+
+impl ColumnIndex for String {
+    fn position(&self, row: &Row) -> Option<usize> {
+        self.as_str().position(row)
+    }
+}
+
+impl<T: ColumnIndex + ?Sized> ColumnIndex for &T {
+    fn position(&self, row: &Row) -> Option<usize> {
+        (**self).position(row)
+    }
+}
+
+/// Error returned by the typed getters of [`Row`] when the column is not of
+/// the expected type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeError {
+    pub expected: ValueDataType,
+    pub actual: ValueDataType,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "mismatched column type, expected:{:?}, actual:{:?}",
+            self.expected, self.actual
+        )
+    }
 }
 
+impl std::error::Error for TypeError {}
+
 /// A column in the [`Row`].
 #[derive(Clone, Debug, PartialEq)]
 pub struct Column {
@@ -56,7 +215,7 @@ pub struct Column {
 }
 
 impl Column {
-    pub(crate) fn new(name: String, value: Value) -> Self {
+    pub fn new(name: String, value: Value) -> Self {
         Self { name, value }
     }
 
@@ -69,8 +228,18 @@ impl Column {
     pub fn value(&self) -> &Value {
         &self.value
     }
+
+    pub(crate) fn into_value(self) -> Value {
+        self.value
+    }
 }
 
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// The max depth of the nested struct columns, and the deeper ones are
+/// rejected.
+pub(crate) const MAX_NESTED_DEPTH: usize = 8;
+
 macro_rules! fill_column {
     ($arrow_column:expr, $arrow_array_type:ty, $value_type:ty, $rows:expr, $col_idx:expr) => {
         paste! {
@@ -79,6 +248,9 @@ macro_rules! fill_column {
                 .as_any()
                 .downcast_ref::<$arrow_array_type>().unwrap();
             for row_idx in 0..row_count {
+                if $arrow_column.is_null(row_idx) {
+                    continue;
+                }
                 let value = cast_arrow_column.value(row_idx).to_owned();
                 let row = $rows.get_mut(row_idx).unwrap();
                 let col = row.get_mut($col_idx).unwrap();
@@ -88,12 +260,139 @@ macro_rules! fill_column {
     };
 }
 
+/// The type of the [`Value`]s decoded from the arrow column of `arrow_type`.
+#[allow(clippy::result_large_err)]
+pub(crate) fn value_data_type(arrow_type: &DataType) -> Result<ValueDataType> {
+    let data_type = match arrow_type {
+        DataType::Null => ValueDataType::Null,
+        DataType::Boolean => ValueDataType::Boolean,
+        DataType::Int8 => ValueDataType::Int8,
+        DataType::Int16 => ValueDataType::Int16,
+        DataType::Int32 => ValueDataType::Int32,
+        DataType::Int64 => ValueDataType::Int64,
+        DataType::UInt8 => ValueDataType::UInt8,
+        DataType::UInt16 => ValueDataType::UInt16,
+        DataType::UInt32 => ValueDataType::UInt32,
+        DataType::UInt64 => ValueDataType::UInt64,
+        DataType::Float32 => ValueDataType::Float,
+        DataType::Float64 => ValueDataType::Double,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Decimal128(_, _) => ValueDataType::String,
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => {
+            ValueDataType::Varbinary
+        }
+        DataType::Timestamp(_, _)
+        | DataType::Time32(TimeUnit::Millisecond)
+        | DataType::Date32
+        | DataType::Date64 => ValueDataType::Timestamp,
+        DataType::Dictionary(index_type, encode_type)
+            if index_type.as_ref() == &DataType::Int32
+                && encode_type.as_ref() == &DataType::Utf8 =>
+        {
+            ValueDataType::String
+        }
+        _ => {
+            return Err(Error::Convert(ConvertError::UnsupportedType(
+                arrow_type.clone(),
+            )));
+        }
+    };
+
+    Ok(data_type)
+}
+
+/// Flatten the struct fields into their leaf fields named by the dotted paths,
+/// e.g. `a.b`, with the types of the [`Value`]s decoded from them.
+///
+/// The leaf field is nullable if it or any struct field containing it is.
+#[allow(clippy::result_large_err)]
+pub(crate) fn flatten_fields(fields: &Fields) -> Result<Vec<(String, ValueDataType, bool)>> {
+    fn flatten(
+        prefix: Option<&str>,
+        fields: &Fields,
+        nullable: bool,
+        depth: usize,
+        flattened: &mut Vec<(String, ValueDataType, bool)>,
+    ) -> Result<()> {
+        for field in fields.iter() {
+            let name = match prefix {
+                Some(prefix) => format!("{prefix}.{}", field.name()),
+                None => field.name().clone(),
+            };
+            let nullable = nullable || field.is_nullable();
+            match field.data_type() {
+                DataType::Struct(children) => {
+                    check_nested_depth(&name, depth)?;
+                    flatten(Some(&name), children, nullable, depth + 1, flattened)?;
+                }
+                data_type => flattened.push((name, value_data_type(data_type)?, nullable)),
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut flattened = Vec::with_capacity(fields.len());
+    flatten(None, fields, false, 0, &mut flattened)?;
+    Ok(flattened)
+}
+
+/// Flatten the struct columns of the `record_batch` in the same way as
+/// [`flatten_fields`].
+#[allow(clippy::result_large_err)]
+fn flatten_columns(record_batch: &RecordBatch) -> Result<Vec<(String, ArrayRef)>> {
+    fn flatten(
+        name: String,
+        column: &ArrayRef,
+        depth: usize,
+        flattened: &mut Vec<(String, ArrayRef)>,
+    ) -> Result<()> {
+        if !matches!(column.data_type(), DataType::Struct(_)) {
+            flattened.push((name, column.clone()));
+            return Ok(());
+        }
+
+        check_nested_depth(&name, depth)?;
+        let struct_column = column.as_struct();
+        for (field, child) in struct_column.fields().iter().zip(struct_column.columns()) {
+            flatten(
+                format!("{name}.{}", field.name()),
+                child,
+                depth + 1,
+                flattened,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    let schema = record_batch.schema();
+    let mut flattened = Vec::with_capacity(record_batch.num_columns());
+    for (field, column) in schema.fields().iter().zip(record_batch.columns()) {
+        flatten(field.name().clone(), column, 0, &mut flattened)?;
+    }
+    Ok(flattened)
+}
+
+#[inline]
+#[allow(clippy::result_large_err)]
+fn check_nested_depth(column: &str, depth: usize) -> Result<()> {
+    if depth >= MAX_NESTED_DEPTH {
+        return Err(Error::Convert(ConvertError::NestingTooDeep {
+            column: column.to_string(),
+            max_depth: MAX_NESTED_DEPTH,
+        }));
+    }
+
+    Ok(())
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RowBuilder {
     pub col_idx_to_name: Vec<String>,
     pub row_values: Vec<Vec<Value>>,
 }
 
+#[allow(clippy::result_large_err)]
 impl RowBuilder {
     pub fn build(self) -> Vec<Row> {
         self.row_values
@@ -110,32 +409,30 @@ impl RowBuilder {
                     })
                     .collect::<Vec<Column>>();
 
-                Row { columns }
+                Row::new(columns)
             })
             .collect::<Vec<_>>()
     }
 
+    /// The struct columns are flattened into the columns of their fields, see
+    /// [`flatten_fields`].
     pub fn with_arrow_record_batch(record_batch: RecordBatch) -> Result<Self> {
-        // Build `col_idx_to_name`.
-        let col_idx_to_name = record_batch
-            .schema()
-            .fields()
-            .iter()
-            .map(|field| field.name().clone())
-            .collect::<Vec<_>>();
+        let columns = flatten_columns(&record_batch)?;
 
         // Build `rows`.
-        let col_count = record_batch.num_columns();
+        let col_count = columns.len();
         let row_count = record_batch.num_rows();
 
         let mut rows = vec![vec![Value::Null; col_count]; row_count];
 
         // Fill row row batch column by column.
-        for col_idx in 0..col_count {
-            let arrow_column = record_batch.column(col_idx);
-            Self::fill_column_in_row_batch(&mut rows, col_idx, arrow_column)?;
+        for (col_idx, (col_name, arrow_column)) in columns.iter().enumerate() {
+            Self::fill_column_in_row_batch(&mut rows, col_idx, col_name, arrow_column)?;
         }
 
+        // Build `col_idx_to_name`.
+        let col_idx_to_name = columns.into_iter().map(|(name, _)| name).collect();
+
         Ok(RowBuilder {
             col_idx_to_name,
             row_values: rows,
@@ -145,6 +442,7 @@ impl RowBuilder {
     fn fill_column_in_row_batch(
         rows: &mut [Vec<Value>],
         col_idx: usize,
+        col_name: &str,
         arrow_column: &ArrayRef,
     ) -> Result<()> {
         let row_count = rows.len();
@@ -152,7 +450,8 @@ impl RowBuilder {
         // TODO: may we can make it simpler with macro.
         match arrow_type {
             // Because `rows` will be initialized with `Value::Null`, just do nothing while
-            // encounter `DataType::Null`.
+            // encounter `DataType::Null`, and the null cells of the other types are skipped
+            // in the same way.
             DataType::Null => {}
             DataType::Boolean => {
                 fill_column!(arrow_column, BooleanArray, Value::Boolean, rows, col_idx);
@@ -193,6 +492,32 @@ impl RowBuilder {
             DataType::Binary | DataType::LargeBinary => {
                 fill_column!(arrow_column, BinaryArray, Value::Varbinary, rows, col_idx);
             }
+            DataType::FixedSizeBinary(_) => {
+                fill_column!(
+                    arrow_column,
+                    FixedSizeBinaryArray,
+                    Value::Varbinary,
+                    rows,
+                    col_idx
+                );
+            }
+            // There is no decimal value, so the decimals are kept exactly as the
+            // strings.
+            DataType::Decimal128(_, _) => {
+                let cast_arrow_column = arrow_column
+                    .as_any()
+                    .downcast_ref::<Decimal128Array>()
+                    .unwrap();
+                for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
+                    let value = cast_arrow_column.value_as_string(row_idx);
+                    let row = rows.get_mut(row_idx).unwrap();
+                    let col = row.get_mut(col_idx).unwrap();
+                    *col = Value::String(value)
+                }
+            }
             DataType::Timestamp(TimeUnit::Millisecond, _) => {
                 fill_column!(
                     arrow_column,
@@ -202,18 +527,95 @@ impl RowBuilder {
                     col_idx
                 );
             }
+            // The timestamps of other units are converted to milliseconds, and the
+            // seconds out of the range fail instead of saturating.
+            DataType::Timestamp(TimeUnit::Second, _) => {
+                let cast_arrow_column = arrow_column
+                    .as_any()
+                    .downcast_ref::<TimestampSecondArray>()
+                    .unwrap();
+                for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
+                    let seconds = cast_arrow_column.value(row_idx);
+                    let value = seconds.checked_mul(1000).ok_or_else(|| {
+                        Error::Convert(ConvertError::Overflow {
+                            column: col_name.to_string(),
+                            value: seconds.to_string(),
+                            target: ValueDataType::Timestamp,
+                        })
+                    })?;
+                    let row = rows.get_mut(row_idx).unwrap();
+                    let col = row.get_mut(col_idx).unwrap();
+                    *col = Value::Timestamp(value)
+                }
+            }
+            DataType::Timestamp(TimeUnit::Microsecond, _) => {
+                let cast_arrow_column = arrow_column
+                    .as_any()
+                    .downcast_ref::<TimestampMicrosecondArray>()
+                    .unwrap();
+                for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
+                    let value =
+                        TimestampResolution::Micros.to_millis(cast_arrow_column.value(row_idx));
+                    let row = rows.get_mut(row_idx).unwrap();
+                    let col = row.get_mut(col_idx).unwrap();
+                    *col = Value::Timestamp(value)
+                }
+            }
+            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+                let cast_arrow_column = arrow_column
+                    .as_any()
+                    .downcast_ref::<TimestampNanosecondArray>()
+                    .unwrap();
+                for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
+                    let value =
+                        TimestampResolution::Nanos.to_millis(cast_arrow_column.value(row_idx));
+                    let row = rows.get_mut(row_idx).unwrap();
+                    let col = row.get_mut(col_idx).unwrap();
+                    *col = Value::Timestamp(value)
+                }
+            }
             DataType::Time32(TimeUnit::Millisecond) => {
                 let cast_arrow_column = arrow_column
                     .as_any()
                     .downcast_ref::<Time32MillisecondArray>()
                     .unwrap();
                 for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
                     let value = cast_arrow_column.value(row_idx);
                     let row = rows.get_mut(row_idx).unwrap();
                     let col = row.get_mut(col_idx).unwrap();
                     *col = Value::Timestamp(value as i64)
                 }
             }
+            // The dates are converted to the timestamps of their midnights in utc,
+            // which can't overflow for any i32 days.
+            DataType::Date32 => {
+                let cast_arrow_column =
+                    arrow_column.as_any().downcast_ref::<Date32Array>().unwrap();
+                for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
+                    let days = cast_arrow_column.value(row_idx) as i64;
+                    let row = rows.get_mut(row_idx).unwrap();
+                    let col = row.get_mut(col_idx).unwrap();
+                    *col = Value::Timestamp(days * MILLIS_PER_DAY)
+                }
+            }
+            DataType::Date64 => {
+                fill_column!(arrow_column, Date64Array, Value::Timestamp, rows, col_idx);
+            }
             DataType::Dictionary(index_type, encode_type)
                 if index_type.as_ref() == &DataType::Int32
                     && encode_type.as_ref() == &DataType::Utf8 =>
@@ -224,6 +626,9 @@ impl RowBuilder {
                     .downcast_dict::<StringArray>()
                     .unwrap();
                 for row_idx in 0..row_count {
+                    if arrow_column.is_null(row_idx) {
+                        continue;
+                    }
                     let value = cast_arrow_column.value(row_idx).to_owned();
                     let row = rows.get_mut(row_idx).unwrap();
                     let col = row.get_mut(col_idx).unwrap();
@@ -232,8 +637,8 @@ impl RowBuilder {
             }
             // Encounter unsupported type.
             _ => {
-                return Err(Error::BuildRows(format!(
-                    "Unsupported arrow type:{arrow_type}",
+                return Err(Error::Convert(ConvertError::UnsupportedType(
+                    arrow_type.clone(),
                 )));
             }
         }
@@ -247,15 +652,24 @@ mod test {
 
     use arrow::{
         array::{
-            BinaryArray, DictionaryArray, Int32Array, StringArray, Time32MillisecondArray,
-            TimestampMillisecondArray,
+            Array, BinaryArray, Date32Array, Date64Array, Decimal128Array, DictionaryArray,
+            FixedSizeBinaryArray, Int32Array, Int64Array, ListArray, StringArray, StructArray,
+            Time32MillisecondArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+            TimestampNanosecondArray, TimestampSecondArray, UInt64Array,
         },
-        datatypes::{DataType, Field, Int32Type, Schema},
+        datatypes::{DataType, Field, Fields, Int32Type, Schema, TimeUnit},
         record_batch::RecordBatch,
     };
 
-    use super::{Row, RowBuilder};
-    use crate::model::{sql_query::row::Column, value::Value};
+    use super::{flatten_fields, value_data_type, Row, RowBuilder, TypeError, MAX_NESTED_DEPTH};
+    use crate::{
+        errors::ConvertError,
+        model::{
+            sql_query::row::Column,
+            value::{DataType as ValueDataType, Value},
+        },
+        Error,
+    };
 
     #[test]
     fn test_build_row() {
@@ -374,4 +788,491 @@ mod test {
 
         assert_eq!(built_rows, expected_rows);
     }
+
+    #[test]
+    fn test_build_row_with_uint64() {
+        let uint64_values = vec![0, i64::MAX as u64 + 1, u64::MAX];
+        let schema = Schema::new(vec![Field::new("uint64", DataType::UInt64, false)]);
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(UInt64Array::from(uint64_values.clone()))],
+        )
+        .unwrap();
+
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+
+        let expected_rows = uint64_values
+            .into_iter()
+            .map(|v| Row {
+                columns: vec![Column::new("uint64".to_string(), Value::UInt64(v))],
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(built_rows, expected_rows);
+    }
+
+    #[test]
+    fn test_build_row_with_nulls() {
+        let dict_type = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+        let schema = Schema::new(vec![
+            Field::new("string", DataType::Utf8, true),
+            Field::new("seconds", DataType::Timestamp(TimeUnit::Second, None), true),
+            Field::new("dict", dict_type, true),
+        ]);
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+                Arc::new(TimestampSecondArray::from(vec![None, Some(1)])),
+                Arc::new(
+                    vec![None, Some("d")]
+                        .into_iter()
+                        .collect::<DictionaryArray<Int32Type>>(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+
+        let expected_rows = vec![
+            Row {
+                columns: vec![
+                    Column::new("string".to_string(), Value::String("a".to_string())),
+                    Column::new("seconds".to_string(), Value::Null),
+                    Column::new("dict".to_string(), Value::Null),
+                ],
+            },
+            Row {
+                columns: vec![
+                    Column::new("string".to_string(), Value::Null),
+                    Column::new("seconds".to_string(), Value::Timestamp(1000)),
+                    Column::new("dict".to_string(), Value::String("d".to_string())),
+                ],
+            },
+        ];
+        assert_eq!(built_rows, expected_rows);
+    }
+
+    #[test]
+    fn test_build_row_with_date() {
+        let schema = Schema::new(vec![
+            Field::new("date32", DataType::Date32, false),
+            Field::new("date64", DataType::Date64, false),
+        ]);
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Date32Array::from(vec![0, 19675, -1])),
+                Arc::new(Date64Array::from(vec![0, 1699920000000, -86400000])),
+            ],
+        )
+        .unwrap();
+
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+
+        let expected_rows = [0, 1699920000000, -86400000]
+            .into_iter()
+            .map(|ts| Row {
+                columns: vec![
+                    Column::new("date32".to_string(), Value::Timestamp(ts)),
+                    Column::new("date64".to_string(), Value::Timestamp(ts)),
+                ],
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(built_rows, expected_rows);
+    }
+
+    #[test]
+    fn test_build_row_with_timestamp_units() {
+        let schema = Schema::new(vec![
+            Field::new("s", DataType::Timestamp(TimeUnit::Second, None), false),
+            Field::new(
+                "us",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new("ns", DataType::Timestamp(TimeUnit::Nanosecond, None), false),
+        ]);
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(TimestampSecondArray::from(vec![1_700_000_000, -1])),
+                Arc::new(TimestampMicrosecondArray::from(vec![
+                    1_700_000_000_000_999,
+                    -1_000_001,
+                ])),
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    1_700_000_000_000_999_999,
+                    -1_000_000_001,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+
+        let expected_rows = [
+            (1_700_000_000_000, 1_700_000_000_000, 1_700_000_000_000),
+            (-1000, -1001, -1001),
+        ]
+        .into_iter()
+        .map(|(s, us, ns)| Row {
+            columns: vec![
+                Column::new("s".to_string(), Value::Timestamp(s)),
+                Column::new("us".to_string(), Value::Timestamp(us)),
+                Column::new("ns".to_string(), Value::Timestamp(ns)),
+            ],
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(built_rows, expected_rows);
+    }
+
+    #[test]
+    fn test_build_row_with_boundary_values() {
+        let schema = Schema::new(vec![
+            Field::new("s", DataType::Timestamp(TimeUnit::Second, None), false),
+            Field::new("d", DataType::Date32, false),
+        ]);
+        let max_seconds = i64::MAX / 1000;
+        let min_seconds = i64::MIN / 1000;
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(TimestampSecondArray::from(vec![max_seconds, min_seconds])),
+                Arc::new(Date32Array::from(vec![i32::MAX, i32::MIN])),
+            ],
+        )
+        .unwrap();
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+        assert_eq!(
+            built_rows[0].get("s"),
+            Some(&Value::Timestamp(max_seconds * 1000))
+        );
+        assert_eq!(
+            built_rows[1].get("s"),
+            Some(&Value::Timestamp(min_seconds * 1000))
+        );
+        assert_eq!(
+            built_rows[0].get("d"),
+            Some(&Value::Timestamp(i32::MAX as i64 * 86_400_000))
+        );
+        assert_eq!(
+            built_rows[1].get("d"),
+            Some(&Value::Timestamp(i32::MIN as i64 * 86_400_000))
+        );
+
+        // Just beyond the range instead of saturating.
+        for seconds in [max_seconds + 1, min_seconds - 1, i64::MAX, i64::MIN] {
+            let arrow_batch = RecordBatch::try_new(
+                Arc::new(schema.clone()),
+                vec![
+                    Arc::new(TimestampSecondArray::from(vec![0, seconds])),
+                    Arc::new(Date32Array::from(vec![0, 0])),
+                ],
+            )
+            .unwrap();
+            match RowBuilder::with_arrow_record_batch(arrow_batch) {
+                Err(Error::Convert(ConvertError::Overflow {
+                    column,
+                    value,
+                    target,
+                })) => {
+                    assert_eq!(column, "s");
+                    assert_eq!(value, seconds.to_string());
+                    assert_eq!(target, ValueDataType::Timestamp);
+                }
+                v => panic!("unexpected result:{v:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_row_with_decimal_and_fixed_binary() {
+        let decimals = Decimal128Array::from(vec![12345, -5, 0])
+            .with_precision_and_scale(10, 2)
+            .unwrap();
+        let fixed_binaries =
+            FixedSizeBinaryArray::try_from_iter(vec![b"ab", b"cd", b"ef"].into_iter()).unwrap();
+        let schema = Schema::new(vec![
+            Field::new("decimal", DataType::Decimal128(10, 2), false),
+            Field::new("fixed", DataType::FixedSizeBinary(2), false),
+        ]);
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(decimals), Arc::new(fixed_binaries)],
+        )
+        .unwrap();
+
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+
+        let expected_rows = [("123.45", b"ab"), ("-0.05", b"cd"), ("0.00", b"ef")]
+            .into_iter()
+            .map(|(decimal, fixed)| Row {
+                columns: vec![
+                    Column::new("decimal".to_string(), Value::String(decimal.to_string())),
+                    Column::new("fixed".to_string(), Value::Varbinary(fixed.to_vec())),
+                ],
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(built_rows, expected_rows);
+    }
+
+    #[test]
+    fn test_value_data_type() {
+        let cases = [
+            (DataType::Boolean, ValueDataType::Boolean),
+            (DataType::UInt8, ValueDataType::UInt8),
+            (DataType::Float32, ValueDataType::Float),
+            (DataType::Float64, ValueDataType::Double),
+            (DataType::Decimal128(10, 2), ValueDataType::String),
+            (DataType::FixedSizeBinary(4), ValueDataType::Varbinary),
+            (
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                ValueDataType::Timestamp,
+            ),
+            (DataType::Date32, ValueDataType::Timestamp),
+            (
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                ValueDataType::String,
+            ),
+        ];
+        for (arrow_type, expected) in cases {
+            assert_eq!(value_data_type(&arrow_type).unwrap(), expected);
+        }
+        assert!(value_data_type(&DataType::Float16).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_list_column() {
+        // HoraeDB has no list or map columns, so they are rejected as a whole
+        // instead of being decoded partially.
+        let list_array = ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            None,
+        ]);
+        let list_type = list_array.data_type().clone();
+        let schema = Schema::new(vec![Field::new("list", list_type.clone(), true)]);
+        let record_batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(list_array)]).unwrap();
+
+        match RowBuilder::with_arrow_record_batch(record_batch) {
+            Err(Error::Convert(ConvertError::UnsupportedType(data_type))) => {
+                assert_eq!(data_type, list_type)
+            }
+            v => panic!("unexpected result:{v:?}"),
+        }
+        assert!(value_data_type(&list_type).is_err());
+    }
+
+    #[test]
+    fn test_typed_getters() {
+        let row = Row {
+            columns: vec![
+                Column::new("int64".to_string(), Value::Int64(42)),
+                Column::new("double".to_string(), Value::Double(0.42)),
+                Column::new("string".to_string(), Value::String("test".to_string())),
+                Column::new("timestamp".to_string(), Value::Timestamp(1001)),
+                Column::new("bool".to_string(), Value::Boolean(true)),
+                Column::new("null".to_string(), Value::Null),
+            ],
+        };
+
+        assert_eq!(row.get_i64(0), Some(42));
+        assert_eq!(row.get_f64(1), Some(0.42));
+        assert_eq!(row.get_string(2), Some("test"));
+        assert_eq!(row.get_timestamp(3), Some(1001));
+        assert_eq!(row.get_bool(4), Some(true));
+
+        // Null, mismatched type and out of range.
+        assert_eq!(row.get_i64(5), None);
+        assert_eq!(row.get_i64(2), None);
+        assert_eq!(row.get_string(0), None);
+        assert_eq!(row.get_i64(6), None);
+
+        // Null and mismatched type can be distinguished.
+        assert_eq!(row.try_get_i64(5), Ok(None));
+        assert_eq!(
+            row.try_get_i64(2),
+            Err(TypeError {
+                expected: ValueDataType::Int64,
+                actual: ValueDataType::String,
+            })
+        );
+    }
+
+    #[test]
+    fn test_getters_on_decoded_null() {
+        let schema = Schema::new(vec![
+            Field::new("int64", DataType::Int64, true),
+            Field::new("string", DataType::Utf8, true),
+        ]);
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(vec![None])),
+                Arc::new(StringArray::from(vec![None::<&str>])),
+            ],
+        )
+        .unwrap();
+        let rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+
+        assert_eq!(rows[0].get_i64("int64"), None);
+        assert_eq!(rows[0].try_get_i64("int64"), Ok(None));
+        assert_eq!(rows[0].get_string("string"), None);
+        assert_eq!(rows[0].try_get_string("string"), Ok(None));
+    }
+
+    #[test]
+    fn test_widening_getters() {
+        let row = Row {
+            columns: vec![
+                Column::new("int32".to_string(), Value::Int32(-42)),
+                Column::new("uint32".to_string(), Value::UInt32(u32::MAX)),
+                Column::new("int8".to_string(), Value::Int8(-8)),
+                Column::new("float".to_string(), Value::Float(0.5)),
+                Column::new("double".to_string(), Value::Double(0.1)),
+                Column::new("int64".to_string(), Value::Int64(i64::MAX)),
+                Column::new("uint64".to_string(), Value::UInt64(1)),
+            ],
+        };
+
+        assert_eq!(row.get_i64("int32"), Some(-42));
+        assert_eq!(row.get_i64("uint32"), Some(u32::MAX as i64));
+        assert_eq!(row.get_i32("int8"), Some(-8));
+        assert_eq!(row.get_f64("float"), Some(0.5));
+        assert_eq!(row.get_f32("float"), Some(0.5));
+
+        // The narrowing and the lossy ones are rejected.
+        assert_eq!(
+            row.try_get_f32("double"),
+            Err(TypeError {
+                expected: ValueDataType::Float,
+                actual: ValueDataType::Double,
+            })
+        );
+        assert_eq!(
+            row.try_get_i32("int64"),
+            Err(TypeError {
+                expected: ValueDataType::Int32,
+                actual: ValueDataType::Int64,
+            })
+        );
+        assert!(row.try_get_i32("uint32").is_err());
+        assert!(row.try_get_i64("uint64").is_err());
+        assert!(row.try_get_f64("int32").is_err());
+        assert!(row.try_get_i64("float").is_err());
+    }
+
+    #[test]
+    fn test_get_by_name() {
+        let row = Row {
+            columns: vec![
+                Column::new("ts".to_string(), Value::Timestamp(1001)),
+                Column::new("TS".to_string(), Value::Timestamp(1002)),
+                Column::new("ts_1".to_string(), Value::Int64(42)),
+                Column::new("ts ".to_string(), Value::String("test".to_string())),
+            ],
+        };
+
+        assert_eq!(row.get("ts"), Some(&Value::Timestamp(1001)));
+        assert_eq!(row.get_timestamp("TS"), Some(1002));
+        assert_eq!(row.get_i64("ts_1"), Some(42));
+        assert_eq!(row.get_string("ts ".to_string()), Some("test"));
+        assert_eq!(row.get_i64(2), row.get_i64("ts_1"));
+
+        // Missing columns.
+        assert_eq!(row.get("Ts"), None);
+        assert_eq!(row.get(""), None);
+        assert_eq!(row.try_get_i64("missing"), Ok(None));
+    }
+
+    #[test]
+    fn test_build_row_with_nested_struct() {
+        let host = Field::new("host", DataType::Utf8, false);
+        let cores = Field::new("cores", DataType::Int32, true);
+        let struct_array = StructArray::from(vec![
+            (
+                host.clone(),
+                Arc::new(StringArray::from(vec!["a", "b"])) as _,
+            ),
+            (cores.clone(), Arc::new(Int32Array::from(vec![4, 8])) as _),
+        ]);
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::UInt64, false),
+            Field::new(
+                "machine",
+                DataType::Struct(Fields::from(vec![host, cores])),
+                false,
+            ),
+        ]);
+        assert_eq!(
+            flatten_fields(schema.fields()).unwrap(),
+            vec![
+                ("id".to_string(), ValueDataType::UInt64, false),
+                ("machine.host".to_string(), ValueDataType::String, false),
+                ("machine.cores".to_string(), ValueDataType::Int32, true),
+            ]
+        );
+
+        let arrow_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(UInt64Array::from(vec![1, 2])),
+                Arc::new(struct_array),
+            ],
+        )
+        .unwrap();
+        let built_rows = RowBuilder::with_arrow_record_batch(arrow_batch)
+            .unwrap()
+            .build();
+        assert_eq!(built_rows.len(), 2);
+        for (row, (id, host, cores)) in built_rows.iter().zip([(1, "a", 4), (2, "b", 8)]) {
+            let names = row.columns().iter().map(|c| c.name()).collect::<Vec<_>>();
+            assert_eq!(names, vec!["id", "machine.host", "machine.cores"]);
+            assert_eq!(row.get("id"), Some(&Value::UInt64(id)));
+            assert_eq!(
+                row.get("machine.host"),
+                Some(&Value::String(host.to_string()))
+            );
+            assert_eq!(row.get("machine.cores"), Some(&Value::Int32(cores)));
+        }
+    }
+
+    #[test]
+    fn test_build_row_with_too_deep_struct() {
+        let mut column: Arc<dyn Array> = Arc::new(Int32Array::from(vec![1]));
+        let mut field = Field::new("leaf", DataType::Int32, false);
+        for _ in 0..=MAX_NESTED_DEPTH {
+            let array = StructArray::from(vec![(field.clone(), column)]);
+            field = Field::new("s", array.data_type().clone(), false);
+            column = Arc::new(array);
+        }
+        let schema = Schema::new(vec![field]);
+        assert!(matches!(
+            flatten_fields(schema.fields()),
+            Err(Error::Convert(ConvertError::NestingTooDeep { max_depth, .. }))
+                if max_depth == MAX_NESTED_DEPTH
+        ));
+
+        let arrow_batch = RecordBatch::try_new(Arc::new(schema), vec![column]).unwrap();
+        assert!(matches!(
+            RowBuilder::with_arrow_record_batch(arrow_batch),
+            Err(Error::Convert(ConvertError::NestingTooDeep { max_depth, .. }))
+                if max_depth == MAX_NESTED_DEPTH
+        ));
+    }
 }
@@ -15,15 +15,21 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::any::Any;
+use std::{any::Any, cmp::Ordering, fmt};
 
+use base64::{prelude::BASE64_STANDARD, Engine};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, TimeZone, Utc};
 use horaedbproto::storage::{value, Value as ValuePb};
 
+use crate::{Error, Result};
+
 pub type TimestampMs = i64;
 
 /// The value enum to express the data in HoraeDB.
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
 pub enum Value {
+    #[default]
     Null,
     Timestamp(TimestampMs),
     Double(f64),
@@ -41,6 +47,7 @@ pub enum Value {
     Boolean(bool),
 }
 
+#[allow(clippy::result_large_err)]
 impl Value {
     pub fn data_type(&self) -> DataType {
         match self {
@@ -209,6 +216,28 @@ impl Value {
         }
     }
 
+    /// Borrow the bytes of the varbinary without copying.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Varbinary(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Encode the varbinary in the standard base64 with padding, the same as
+    /// the json of the rows.
+    pub fn to_base64(&self) -> Option<String> {
+        self.as_bytes().map(|v| BASE64_STANDARD.encode(v))
+    }
+
+    /// Make the varbinary from the standard base64 with padding.
+    pub fn from_base64(s: &str) -> Result<Self> {
+        BASE64_STANDARD
+            .decode(s)
+            .map(Value::Varbinary)
+            .map_err(|e| Error::Client(format!("Invalid base64:{s}, err:{e}")))
+    }
+
     /// Cast datum to &str.
     pub fn as_str(&self) -> Option<String> {
         match self {
@@ -217,6 +246,78 @@ impl Value {
         }
     }
 
+    /// Cast the timestamp to the [`DateTime`] in utc.
+    ///
+    /// `None` will be returned if it is not a timestamp or out of the range of
+    /// [`DateTime`].
+    #[cfg(feature = "chrono")]
+    pub fn as_datetime(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Value::Timestamp(v) => DateTime::from_timestamp_millis(*v),
+            _ => None,
+        }
+    }
+
+    /// Make the timestamp from the `datetime`, whose precision below
+    /// milliseconds is truncated.
+    #[cfg(feature = "chrono")]
+    pub fn from_datetime<Tz: TimeZone>(datetime: DateTime<Tz>) -> Self {
+        Value::Timestamp(datetime.timestamp_millis())
+    }
+
+    /// Compare the values by what they mean instead of their variants, e.g.
+    /// for sorting the rows on the client side.
+    ///
+    /// The numbers are compared across the numeric types, e.g. `Int32(1)`
+    /// equals `Double(1.0)`, and the integers are compared exactly while the
+    /// others are compared as `f64`. `Null` sorts before all the others and
+    /// equals itself. `None` is returned for the incompatible types, e.g. a
+    /// string and a number, and for `NaN`.
+    ///
+    /// Different from it, the derived [`PartialEq`] and [`PartialOrd`] compare
+    /// the variants first.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            (Value::Null, _) => Some(Ordering::Less),
+            (_, Value::Null) => Some(Ordering::Greater),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+            (Value::Varbinary(a), Value::Varbinary(b)) => Some(a.cmp(b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+            _ => match (self.as_i128(), other.as_i128()) {
+                (Some(a), Some(b)) => Some(a.cmp(&b)),
+                _ if self.is_numeric() && other.is_numeric() => {
+                    self.as_f64()?.partial_cmp(&other.as_f64()?)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Whether the values are equal by [`compare`](Value::compare).
+    pub fn loosely_eq(&self, other: &Value) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Value::Double(_) | Value::Float(_)) || self.as_i128().is_some()
+    }
+
+    fn as_i128(&self) -> Option<i128> {
+        match self {
+            Value::UInt64(v) => Some(*v as i128),
+            Value::UInt32(v) => Some(*v as i128),
+            Value::UInt16(v) => Some(*v as i128),
+            Value::UInt8(v) => Some(*v as i128),
+            Value::Int64(v) => Some(*v as i128),
+            Value::Int32(v) => Some(*v as i128),
+            Value::Int16(v) => Some(*v as i128),
+            Value::Int8(v) => Some(*v as i128),
+            _ => None,
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         match self {
             Value::Null => b"".to_vec(),
@@ -238,12 +339,78 @@ impl Value {
     }
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Self::Null
+/// The max number of the bytes of the varbinary shown by [`Display`](fmt::Display).
+const MAX_DISPLAYED_BYTES: usize = 16;
+
+/// Format the value for humans, e.g. in the logs, and [`Debug`] is still used
+/// for the exact representation.
+///
+/// The timestamps are formatted in ISO-8601 in utc, the strings are quoted,
+/// and the varbinary is formatted in hex and truncated with `...` if it is
+/// longer than 16 bytes.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Timestamp(v) => format_timestamp(*v, f),
+            Value::Double(v) => write!(f, "{v}"),
+            Value::Float(v) => write!(f, "{v}"),
+            Value::Varbinary(v) => {
+                write!(f, "0x")?;
+                for byte in v.iter().take(MAX_DISPLAYED_BYTES) {
+                    write!(f, "{byte:02x}")?;
+                }
+                if v.len() > MAX_DISPLAYED_BYTES {
+                    write!(f, "...")?;
+                }
+                Ok(())
+            }
+            Value::String(v) => write!(f, "{v:?}"),
+            Value::UInt64(v) => write!(f, "{v}"),
+            Value::UInt32(v) => write!(f, "{v}"),
+            Value::UInt16(v) => write!(f, "{v}"),
+            Value::UInt8(v) => write!(f, "{v}"),
+            Value::Int64(v) => write!(f, "{v}"),
+            Value::Int32(v) => write!(f, "{v}"),
+            Value::Int16(v) => write!(f, "{v}"),
+            Value::Int8(v) => write!(f, "{v}"),
+            Value::Boolean(v) => write!(f, "{v}"),
+        }
     }
 }
 
+/// Format the timestamp like `2023-11-14T22:13:20.000Z`.
+fn format_timestamp(timestamp: TimestampMs, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+    let days = timestamp.div_euclid(MILLIS_PER_DAY);
+    let millis_of_day = timestamp.rem_euclid(MILLIS_PER_DAY);
+    let (hour, minute, second, millis) = (
+        millis_of_day / 3_600_000,
+        millis_of_day / 60_000 % 60,
+        millis_of_day / 1000 % 60,
+        millis_of_day % 1000,
+    );
+
+    // The civil date from the days since epoch, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    write!(
+        f,
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{millis:03}Z"
+    )
+}
+
 impl From<Value> for ValuePb {
     fn from(val: Value) -> Self {
         let value = match val {
@@ -294,6 +461,34 @@ impl From<ValuePb> for Value {
     }
 }
 
+/// The resolution of the timestamps provided by the users.
+///
+/// The timestamps are stored in milliseconds by HoraeDB, so the ones of the
+/// higher resolutions are truncated to milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampResolution {
+    Seconds,
+    #[default]
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimestampResolution {
+    /// Convert the `timestamp` of the resolution into milliseconds.
+    ///
+    /// The timestamp is floored, so that the pre-epoch timestamps stay in the
+    /// right millisecond, and it saturates on overflow.
+    pub fn to_millis(self, timestamp: i64) -> TimestampMs {
+        match self {
+            TimestampResolution::Seconds => timestamp.saturating_mul(1000),
+            TimestampResolution::Millis => timestamp,
+            TimestampResolution::Micros => timestamp.div_euclid(1000),
+            TimestampResolution::Nanos => timestamp.div_euclid(1_000_000),
+        }
+    }
+}
+
 /// The data type supported by HoraeDB.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DataType {
@@ -313,3 +508,225 @@ pub enum DataType {
     Int8,
     Boolean,
 }
+
+impl DataType {
+    /// The data type of the column holding the `value`, e.g. for inferring the
+    /// schema from the points to write.
+    ///
+    /// `None` is returned for [`Value::Null`], which may be in the column of
+    /// any type, unlike [`Value::data_type`].
+    pub fn from_value(value: &Value) -> Option<DataType> {
+        match value {
+            Value::Null => None,
+            value => Some(value.data_type()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use horaedbproto::storage::Value as ValuePb;
+
+    use super::{DataType, TimestampResolution, Value};
+    use crate::Error;
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_datetime_round_trip() {
+        use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+
+        for millis in [0, 1_700_000_000_123, -1, -86_400_001] {
+            let datetime = Value::Timestamp(millis).as_datetime().unwrap();
+            assert_eq!(datetime.timestamp_millis(), millis);
+            assert_eq!(Value::from_datetime(datetime), Value::Timestamp(millis));
+        }
+
+        let datetime = Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(Value::from_datetime(datetime), Value::Timestamp(-1000));
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let datetime = offset.with_ymd_and_hms(1970, 1, 1, 8, 0, 0).unwrap();
+        assert_eq!(Value::from_datetime(datetime), Value::Timestamp(0));
+        let datetime = DateTime::from_timestamp(1, 999_999).unwrap();
+        assert_eq!(Value::from_datetime(datetime), Value::Timestamp(1000));
+
+        assert_eq!(Value::Timestamp(i64::MAX).as_datetime(), None);
+        assert_eq!(Value::Int64(0).as_datetime(), None);
+    }
+
+    #[test]
+    fn test_base64() {
+        for bytes in [vec![], vec![0], b"horaedb".to_vec(), vec![0xff; 17]] {
+            let value = Value::Varbinary(bytes.clone());
+            assert_eq!(value.as_bytes(), Some(bytes.as_slice()));
+            let encoded = value.to_base64().unwrap();
+            assert_eq!(Value::from_base64(&encoded).unwrap(), value);
+        }
+        assert_eq!(
+            Value::Varbinary(b"horaedb".to_vec()).to_base64().as_deref(),
+            Some("aG9yYWVkYg==")
+        );
+
+        let string = Value::String("aG9yYWVkYg==".to_string());
+        assert_eq!(string.as_bytes(), None);
+        assert_eq!(string.to_base64(), None);
+
+        for invalid in ["aG9yYWVkYg", "not base64!", "aG9y=YWVk"] {
+            assert!(matches!(Value::from_base64(invalid), Err(Error::Client(_))));
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let equal_cases = [
+            (Value::Int32(1), Value::Int64(1)),
+            (Value::Int32(1), Value::Double(1.0)),
+            (Value::Float(0.5), Value::Double(0.5)),
+            (Value::UInt64(u64::MAX), Value::UInt64(u64::MAX)),
+            (Value::Null, Value::Null),
+        ];
+        for (a, b) in equal_cases {
+            assert!(a.loosely_eq(&b), "{a:?} {b:?}");
+            assert!(b.loosely_eq(&a), "{a:?} {b:?}");
+        }
+
+        let less_cases = [
+            (Value::Int8(-1), Value::UInt64(0)),
+            (Value::Int64(i64::MAX), Value::UInt64(i64::MAX as u64 + 1)),
+            (Value::Int32(1), Value::Float(1.5)),
+            (Value::Double(-0.5), Value::UInt8(0)),
+            (Value::Null, Value::Int64(i64::MIN)),
+            (Value::Null, Value::String("".to_string())),
+            (
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ),
+            (Value::Timestamp(1), Value::Timestamp(2)),
+        ];
+        for (a, b) in less_cases {
+            assert_eq!(a.compare(&b), Some(Ordering::Less), "{a:?} {b:?}");
+            assert_eq!(b.compare(&a), Some(Ordering::Greater), "{a:?} {b:?}");
+        }
+
+        // The incompatible types.
+        let incompatible_cases = [
+            (Value::Int64(1), Value::String("1".to_string())),
+            (Value::Timestamp(1), Value::Int64(1)),
+            (Value::Boolean(true), Value::Int8(1)),
+            (Value::Double(f64::NAN), Value::Double(f64::NAN)),
+        ];
+        for (a, b) in incompatible_cases {
+            assert_eq!(a.compare(&b), None, "{a:?} {b:?}");
+            assert!(!a.loosely_eq(&b));
+        }
+
+        // The derived ones are kept.
+        assert_ne!(Value::Int32(1), Value::Int64(1));
+    }
+
+    #[test]
+    fn test_data_type_from_value() {
+        let cases = [
+            (Value::Timestamp(1), DataType::Timestamp),
+            (Value::Double(1.0), DataType::Double),
+            (Value::Float(1.0), DataType::Float),
+            (Value::Varbinary(vec![1]), DataType::Varbinary),
+            (Value::String("a".to_string()), DataType::String),
+            (Value::UInt64(1), DataType::UInt64),
+            (Value::UInt32(1), DataType::UInt32),
+            (Value::UInt16(1), DataType::UInt16),
+            (Value::UInt8(1), DataType::UInt8),
+            (Value::Int64(1), DataType::Int64),
+            (Value::Int32(1), DataType::Int32),
+            (Value::Int16(1), DataType::Int16),
+            (Value::Int8(1), DataType::Int8),
+            (Value::Boolean(true), DataType::Boolean),
+        ];
+        for (value, data_type) in cases {
+            assert_eq!(DataType::from_value(&value), Some(data_type));
+        }
+        assert_eq!(DataType::from_value(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let cases = [
+            (Value::Null, "NULL"),
+            (Value::Timestamp(0), "1970-01-01T00:00:00.000Z"),
+            (
+                Value::Timestamp(1_700_000_000_123),
+                "2023-11-14T22:13:20.123Z",
+            ),
+            (
+                Value::Timestamp(951_782_400_000),
+                "2000-02-29T00:00:00.000Z",
+            ),
+            (Value::Timestamp(-1), "1969-12-31T23:59:59.999Z"),
+            (Value::Double(0.5), "0.5"),
+            (Value::Float(-1.5), "-1.5"),
+            (Value::Varbinary(vec![]), "0x"),
+            (Value::Varbinary(vec![0xca, 0xfe]), "0xcafe"),
+            (
+                Value::Varbinary(vec![0xab; 16]),
+                "0xabababababababababababababababab",
+            ),
+            (
+                Value::Varbinary(vec![0xab; 17]),
+                "0xabababababababababababababababab...",
+            ),
+            (Value::String("a \"b\"".to_string()), "\"a \\\"b\\\"\""),
+            (Value::UInt64(u64::MAX), "18446744073709551615"),
+            (Value::UInt32(1), "1"),
+            (Value::UInt16(2), "2"),
+            (Value::UInt8(3), "3"),
+            (Value::Int64(i64::MIN), "-9223372036854775808"),
+            (Value::Int32(-1), "-1"),
+            (Value::Int16(-2), "-2"),
+            (Value::Int8(-3), "-3"),
+            (Value::Boolean(true), "true"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_resolution() {
+        let cases = [
+            (
+                TimestampResolution::Seconds,
+                1_700_000_000,
+                1_700_000_000_000,
+            ),
+            (
+                TimestampResolution::Millis,
+                1_700_000_000_123,
+                1_700_000_000_123,
+            ),
+            (
+                TimestampResolution::Micros,
+                1_700_000_000_123_456,
+                1_700_000_000_123,
+            ),
+            (
+                TimestampResolution::Nanos,
+                1_700_000_000_123_456_789,
+                1_700_000_000_123,
+            ),
+            (TimestampResolution::Micros, -1, -1),
+            (TimestampResolution::Seconds, i64::MAX, i64::MAX),
+        ];
+        for (resolution, timestamp, expected) in cases {
+            assert_eq!(resolution.to_millis(timestamp), expected);
+        }
+    }
+
+    #[test]
+    fn test_uint64_pb_round_trip() {
+        for v in [0, i64::MAX as u64 + 1, u64::MAX] {
+            let value_pb = ValuePb::from(Value::UInt64(v));
+            assert_eq!(Value::from(value_pb), Value::UInt64(v));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Parse the [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/)
+//! into the [`Request`].
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::model::{
+    value::{TimestampMs, Value},
+    write::{point::PointBuilder, Request},
+};
+
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// Parse the lines in the line protocol into the [`Request`].
+///
+/// The measurement is used as the table, and the nanosecond timestamp is
+/// truncated to millisecond. The lines without a timestamp are set to the
+/// current time.
+pub fn parse_line_protocol(input: &str) -> Result<Request, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Invalid system time, err:{e}"))?
+        .as_millis() as TimestampMs;
+
+    parse_line_protocol_with_default_timestamp(input, now)
+}
+
+/// Same as [`parse_line_protocol`], but the lines without a timestamp are set
+/// to `default_timestamp`.
+pub fn parse_line_protocol_with_default_timestamp(
+    input: &str,
+    default_timestamp: TimestampMs,
+) -> Result<Request, String> {
+    let mut request = Request::default();
+    for (line_idx, line) in input.lines().enumerate() {
+        let line = line.trim();
+        // Skip the empty lines and comments.
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let point_builder = parse_line(line, default_timestamp)
+            .and_then(|builder| builder.build())
+            .map_err(|e| format!("Failed to parse line:{}, err:{e}", line_idx + 1))?;
+        request.add_point(point_builder);
+    }
+
+    Ok(request)
+}
+
+fn parse_line(line: &str, default_timestamp: TimestampMs) -> Result<PointBuilder, String> {
+    let sections = split_unescaped(line, ' ');
+    let (series, fields, timestamp) = match sections.as_slice() {
+        [series, fields] => (*series, *fields, None),
+        [series, fields, timestamp] => (*series, *fields, Some(*timestamp)),
+        _ => return Err("Expect measurement, fields and optional timestamp".to_string()),
+    };
+
+    // Parse measurement and tags.
+    let series = split_unescaped(series, ',');
+    let measurement = unescape(series[0]);
+    if measurement.is_empty() {
+        return Err("Measurement should not be empty".to_string());
+    }
+    let mut builder = PointBuilder::new(measurement);
+    for tag in &series[1..] {
+        let (name, value) = split_key_value(tag)?;
+        builder = builder.tag(name, Value::String(unescape(value)));
+    }
+
+    // Parse fields.
+    for field in split_unescaped(fields, ',') {
+        let (name, value) = split_key_value(field)?;
+        builder = builder.field(name, parse_field_value(value)?);
+    }
+
+    // Parse timestamp.
+    let timestamp = match timestamp {
+        Some(raw) => raw
+            .parse::<i64>()
+            .map_err(|e| format!("Invalid timestamp:{raw}, err:{e}"))?
+            .div_euclid(NANOS_PER_MILLI),
+        None => default_timestamp,
+    };
+
+    Ok(builder.timestamp(timestamp))
+}
+
+fn split_key_value(s: &str) -> Result<(String, &str), String> {
+    let pos = find_unescaped(s, '=').ok_or_else(|| format!("Missing '=' in:{s}"))?;
+    let key = unescape(&s[..pos]);
+    if key.is_empty() {
+        return Err(format!("Empty key in:{s}"));
+    }
+
+    Ok((key, &s[pos + 1..]))
+}
+
+fn parse_field_value(raw: &str) -> Result<Value, String> {
+    if raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+        return Ok(Value::String(unescape(&raw[1..raw.len() - 1])));
+    }
+
+    let value = match raw {
+        "t" | "T" | "true" | "True" | "TRUE" => Value::Boolean(true),
+        "f" | "F" | "false" | "False" | "FALSE" => Value::Boolean(false),
+        _ if raw.ends_with('i') => Value::Int64(
+            raw[..raw.len() - 1]
+                .parse()
+                .map_err(|e| format!("Invalid integer:{raw}, err:{e}"))?,
+        ),
+        _ if raw.ends_with('u') => Value::UInt64(
+            raw[..raw.len() - 1]
+                .parse()
+                .map_err(|e| format!("Invalid unsigned integer:{raw}, err:{e}"))?,
+        ),
+        _ => Value::Double(
+            raw.parse()
+                .map_err(|e| format!("Invalid float:{raw}, err:{e}"))?,
+        ),
+    };
+
+    Ok(value)
+}
+
+/// Split `s` by the `sep` which is neither escaped nor quoted.
+fn split_unescaped(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quoted = false;
+    for (idx, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            _ if c == sep && !quoted => {
+                parts.push(&s[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+
+    parts
+}
+
+fn find_unescaped(s: &str, target: char) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == target => return Some(idx),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(next @ (',' | '=' | ' ' | '"' | '\\'))) => {
+                unescaped.push(*next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_line_protocol, parse_line_protocol_with_default_timestamp};
+    use crate::model::{value::Value, write::point::Point};
+
+    fn get_point<'a>(points: &'a [Point], tag: &str, value: &str) -> &'a Point {
+        points
+            .iter()
+            .find(|point| point.tags.get(tag) == Some(&Value::String(value.to_string())))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let input = r#"
+# comment
+cpu,host=a,region=us\ west usage=0.5,count=10i,ok=true 1700000000123456789
+cpu,host=b usage=1,total=7u,note="say \"hi\", bye" 1700000000999000000
+my\,mem,host\=x=y used=2048i 1700000000000000000
+"#;
+        let req = parse_line_protocol_with_default_timestamp(input, 0).unwrap();
+        assert_eq!(req.point_groups.len(), 2);
+
+        let cpu_points = &req.point_groups["cpu"];
+        let point = get_point(cpu_points, "host", "a");
+        assert_eq!(point.timestamp, 1700000000123);
+        assert_eq!(point.tags["region"], Value::String("us west".to_string()));
+        assert_eq!(point.fields["usage"], Value::Double(0.5));
+        assert_eq!(point.fields["count"], Value::Int64(10));
+        assert_eq!(point.fields["ok"], Value::Boolean(true));
+
+        let point = get_point(cpu_points, "host", "b");
+        assert_eq!(point.timestamp, 1700000000999);
+        assert_eq!(point.fields["usage"], Value::Double(1.0));
+        assert_eq!(point.fields["total"], Value::UInt64(7));
+        assert_eq!(
+            point.fields["note"],
+            Value::String(r#"say "hi", bye"#.to_string())
+        );
+
+        let mem_points = &req.point_groups["my,mem"];
+        let point = get_point(mem_points, "host=x", "y");
+        assert_eq!(point.fields["used"], Value::Int64(2048));
+    }
+
+    #[test]
+    fn test_parse_missing_timestamp() {
+        let req = parse_line_protocol_with_default_timestamp("cpu usage=0.5", 42).unwrap();
+        assert_eq!(req.point_groups["cpu"][0].timestamp, 42);
+
+        let req = parse_line_protocol("cpu usage=0.5").unwrap();
+        assert!(req.point_groups["cpu"][0].timestamp > 0);
+    }
+
+    #[test]
+    fn test_parse_invalid_lines() {
+        let cases = [
+            "cpu",
+            "cpu,host=a",
+            "cpu usage",
+            "cpu usage=abc",
+            "cpu usage=1i2",
+            "cpu usage=1 abc",
+            ",host=a usage=1",
+        ];
+        for input in cases {
+            assert!(parse_line_protocol(input).is_err(), "input:{input}");
+        }
+    }
+}
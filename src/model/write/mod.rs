@@ -15,9 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod line_protocol;
 pub mod point;
 mod request;
 mod response;
 
-pub use request::{pb_builder::WriteTableRequestPbsBuilder, Request};
+#[cfg(test)]
+pub(crate) use request::estimate_point_size;
+pub use request::{pb_builder::WriteTableRequestPbsBuilder, Request, RequestBuilder};
 pub use response::Response;
@@ -17,7 +17,7 @@
 
 use std::collections::BTreeMap;
 
-use crate::model::value::Value;
+use crate::model::value::{TimestampResolution, Value};
 
 const TSID: &str = "tsid";
 const TIMESTAMP: &str = "timestamp";
@@ -45,6 +45,8 @@ pub struct PointBuilder {
     tags: BTreeMap<String, Value>,
     fields: BTreeMap<String, Value>,
     contains_reserved_column_name: bool,
+    /// The first field set more than once.
+    duplicate_field: Option<String>,
 }
 
 impl PointBuilder {
@@ -55,6 +57,7 @@ impl PointBuilder {
             tags: BTreeMap::new(),
             fields: BTreeMap::new(),
             contains_reserved_column_name: false,
+            duplicate_field: None,
         }
     }
 
@@ -70,6 +73,22 @@ impl PointBuilder {
         self
     }
 
+    /// Whether the timestamp is set for the point.
+    pub(crate) fn has_timestamp(&self) -> bool {
+        self.timestamp.is_some()
+    }
+
+    /// Set the timestamp of the `resolution` for the point, which is truncated
+    /// to milliseconds.
+    pub fn timestamp_with_resolution(
+        mut self,
+        timestamp: i64,
+        resolution: TimestampResolution,
+    ) -> Self {
+        self.timestamp = Some(resolution.to_millis(timestamp));
+        self
+    }
+
     /// Set tag name and value of the write entry.
     ///
     /// You cannot set tag with name like 'timestamp' or 'tsid',
@@ -85,16 +104,51 @@ impl PointBuilder {
     }
 
     /// Set the name and value of a field specified by its `name`.
+    ///
+    /// The field set to [`Value::Null`] is written as null explicitly, while
+    /// the field not set is absent from the written point and left to the
+    /// server, e.g. filled by the default value of the column.
+    ///
+    /// Every field can only be set once, and the field of the same name as a
+    /// tag is not allowed either, which fail the [`build`](PointBuilder::build).
     pub fn field(mut self, name: impl Into<String>, value: Value) -> Self {
         let name = name.into();
         if is_reserved_column_name(&name) {
             self.contains_reserved_column_name = true;
         }
 
+        if self.fields.contains_key(&name) {
+            self.duplicate_field.get_or_insert_with(|| name.clone());
+        }
+
         let _ = self.fields.insert(name, value);
         self
     }
 
+    /// Set the field of `f64`, the same as [`field`](PointBuilder::field) with
+    /// [`Value::Double`].
+    pub fn field_f64(self, name: impl Into<String>, value: f64) -> Self {
+        self.field(name, Value::Double(value))
+    }
+
+    /// Set the field of `i64`, the same as [`field`](PointBuilder::field) with
+    /// [`Value::Int64`].
+    pub fn field_i64(self, name: impl Into<String>, value: i64) -> Self {
+        self.field(name, Value::Int64(value))
+    }
+
+    /// Set the field of string, the same as [`field`](PointBuilder::field) with
+    /// [`Value::String`].
+    pub fn field_str(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.field(name, Value::String(value.into()))
+    }
+
+    /// Set the field specified by its `name` to null explicitly, the same as
+    /// [`field`](PointBuilder::field) with [`Value::Null`].
+    pub fn field_null(self, name: impl Into<String>) -> Self {
+        self.field(name, Value::Null)
+    }
+
     /// Build the final point.
     pub fn build(self) -> Result<Point, String> {
         if self.contains_reserved_column_name {
@@ -105,6 +159,18 @@ impl PointBuilder {
             return Err("Fields should not be empty".to_string());
         }
 
+        if let Some(name) = self.duplicate_field {
+            return Err(format!("Field:{name} is set more than once"));
+        }
+
+        if let Some(name) = self
+            .fields
+            .keys()
+            .find(|name| self.tags.contains_key(*name))
+        {
+            return Err(format!("Column:{name} is set as both a tag and a field"));
+        }
+
         let timestamp = self
             .timestamp
             .ok_or_else(|| "Timestamp must be set".to_string())?;
@@ -117,3 +183,76 @@ impl PointBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::PointBuilder;
+    use crate::model::value::Value;
+
+    #[test]
+    fn test_typed_fields() {
+        let point = PointBuilder::new("cpu")
+            .timestamp(1)
+            .tag("host", Value::String("a".to_string()))
+            .field_f64("usage", 0.5)
+            .field_i64("cores", 8)
+            .field_str("state", "idle")
+            .build()
+            .unwrap();
+        let fields: Vec<_> = point
+            .fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("cores", Value::Int64(8)),
+                ("state", Value::String("idle".to_string())),
+                ("usage", Value::Double(0.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_field() {
+        let res = PointBuilder::new("cpu")
+            .timestamp(1)
+            .field_f64("usage", 0.5)
+            .field_i64("cores", 8)
+            .field_f64("usage", 0.6)
+            .build();
+        assert_eq!(res.unwrap_err(), "Field:usage is set more than once");
+
+        // Null is not an exception.
+        let res = PointBuilder::new("cpu")
+            .timestamp(1)
+            .field_null("usage")
+            .field_f64("usage", 0.6)
+            .build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_tag_field_collision() {
+        let res = PointBuilder::new("cpu")
+            .timestamp(1)
+            .field_str("host", "a")
+            .tag("host", Value::String("a".to_string()))
+            .field_f64("usage", 0.5)
+            .build();
+        assert_eq!(
+            res.unwrap_err(),
+            "Column:host is set as both a tag and a field"
+        );
+
+        // The same value set as the tags is fine.
+        let res = PointBuilder::new("cpu")
+            .timestamp(1)
+            .tag("host", Value::String("a".to_string()))
+            .tag("region", Value::String("a".to_string()))
+            .field_str("state", "a")
+            .build();
+        assert!(res.is_ok());
+    }
+}
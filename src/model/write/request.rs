@@ -15,9 +15,15 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crate::model::write::point::Point;
+use crate::model::{
+    value::{DataType, TimestampMs},
+    write::point::{Point, PointBuilder},
+};
 
 /// Write request.
 #[derive(Clone, Debug, Default)]
@@ -43,6 +49,186 @@ impl Request {
 
         self
     }
+
+    /// Split the request into the ones whose estimated encoded sizes are not
+    /// larger than `max_bytes`, e.g. to keep under the max message length of
+    /// the server.
+    ///
+    /// The size is estimated from the names and the values of the points, and
+    /// the point larger than `max_bytes` is put into a request by itself.
+    pub fn split_by_size(self, max_bytes: usize) -> Vec<Request> {
+        let mut requests = Vec::new();
+        let mut current = Request::default();
+        let mut current_bytes = 0;
+        for point in self.point_groups.into_values().flatten() {
+            let point_bytes = estimate_point_size(&point);
+            if current_bytes > 0 && current_bytes + point_bytes > max_bytes {
+                requests.push(std::mem::take(&mut current));
+                current_bytes = 0;
+            }
+            current_bytes += point_bytes;
+            current.add_point(point);
+        }
+        if !current.point_groups.is_empty() {
+            requests.push(current);
+        }
+
+        requests
+    }
+
+    /// Merge the points of `other` into the request.
+    pub fn merge(&mut self, other: Request) -> &mut Self {
+        for (table, points) in other.point_groups {
+            self.point_groups.entry(table).or_default().extend(points);
+        }
+
+        self
+    }
+}
+
+/// The estimated bytes of the point once encoded, including the table name
+/// and the timestamp, and a few bytes of the overhead for every column.
+pub(crate) fn estimate_point_size(point: &Point) -> usize {
+    const COLUMN_OVERHEAD: usize = 4;
+
+    let columns_bytes: usize = point
+        .tags
+        .iter()
+        .chain(point.fields.iter())
+        .map(|(name, value)| name.len() + value.to_bytes().len() + COLUMN_OVERHEAD)
+        .sum();
+    point.table.len() + std::mem::size_of::<i64>() + columns_bytes
+}
+
+/// Builder for building the [`Request`] from points.
+///
+/// Different from [`Request::add_point`], the types of the tags and fields with
+/// the same name in the same table are ensured to be consistent, and the null
+/// values are compatible with any type.
+#[derive(Debug, Default)]
+pub struct RequestBuilder {
+    point_builders: Vec<PointBuilder>,
+    dedup: bool,
+    backfill_timestamp: Option<TimestampBackfill>,
+}
+
+/// How the points without a timestamp are backfilled.
+#[derive(Clone, Copy, Debug)]
+enum TimestampBackfill {
+    WallClock,
+    Fixed(TimestampMs),
+}
+
+impl RequestBuilder {
+    /// Add one point to the request.
+    pub fn point(mut self, point_builder: PointBuilder) -> Self {
+        self.point_builders.push(point_builder);
+        self
+    }
+
+    /// Collapse the points of the same table, timestamp and tags into the last
+    /// one of them, which keeps the place of the first one.
+    ///
+    /// It is disabled by default.
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Set the timestamp of the points without it to the current time, which is
+    /// read once in [`build`](RequestBuilder::build) and shared by all of them.
+    ///
+    /// It is disabled by default, and the points without a timestamp fail the
+    /// build then.
+    pub fn backfill_timestamp(mut self, backfill: bool) -> Self {
+        self.backfill_timestamp = backfill.then_some(TimestampBackfill::WallClock);
+        self
+    }
+
+    /// Same as [`backfill_timestamp`](RequestBuilder::backfill_timestamp), but
+    /// the points without a timestamp are set to `now`, e.g. for determinism.
+    pub fn backfill_timestamp_with(mut self, now: TimestampMs) -> Self {
+        self.backfill_timestamp = Some(TimestampBackfill::Fixed(now));
+        self
+    }
+
+    /// Build the final request.
+    pub fn build(self) -> Result<Request, String> {
+        let backfill_timestamp = match self.backfill_timestamp {
+            Some(TimestampBackfill::WallClock) => Some(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|e| format!("Invalid system time, err:{e}"))?
+                    .as_millis() as TimestampMs,
+            ),
+            Some(TimestampBackfill::Fixed(now)) => Some(now),
+            None => None,
+        };
+
+        let mut request = Request::default();
+        // (table, column) -> data type
+        let mut column_types: HashMap<(String, String), DataType> = HashMap::new();
+        for point_builder in self.point_builders {
+            let point_builder = match backfill_timestamp {
+                Some(now) if !point_builder.has_timestamp() => point_builder.timestamp(now),
+                _ => point_builder,
+            };
+            let point = point_builder.build()?;
+            for (name, value) in point.tags.iter().chain(point.fields.iter()) {
+                if value.is_null() {
+                    continue;
+                }
+
+                let data_type = value.data_type();
+                let expected = column_types
+                    .entry((point.table.clone(), name.clone()))
+                    .or_insert(data_type);
+                if *expected != data_type {
+                    return Err(format!(
+                        "Inconsistent types of column:{name} in table:{}, expected:{expected:?}, actual:{data_type:?}",
+                        point.table
+                    ));
+                }
+            }
+
+            request.add_point(point);
+        }
+
+        if self.dedup {
+            for points in request.point_groups.values_mut() {
+                dedup_points(points);
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// Collapse the points of the same timestamp and tags in one table, and the
+/// last one wins.
+fn dedup_points(points: &mut Vec<Point>) {
+    // The same tag is of the same type in one table, so the bytes with the null
+    // flag identify its value.
+    type SeriesKey = (i64, Vec<(String, bool, Vec<u8>)>);
+
+    let mut positions: HashMap<SeriesKey, usize> = HashMap::with_capacity(points.len());
+    let mut deduped: Vec<Point> = Vec::with_capacity(points.len());
+    for point in points.drain(..) {
+        let tags = point
+            .tags
+            .iter()
+            .map(|(name, value)| (name.clone(), value.is_null(), value.to_bytes()))
+            .collect();
+        match positions.entry((point.timestamp, tags)) {
+            Entry::Occupied(entry) => deduped[*entry.get()] = point,
+            Entry::Vacant(entry) => {
+                entry.insert(deduped.len());
+                deduped.push(point);
+            }
+        }
+    }
+
+    *points = deduped;
 }
 
 pub mod pb_builder {
@@ -253,10 +439,77 @@ mod test {
         write::{
             point::{Point, PointBuilder},
             request::pb_builder::WriteTableRequestPbsBuilder,
-            Request,
+            Request, RequestBuilder,
         },
     };
 
+    #[test]
+    fn test_split_by_size() {
+        let mut req = Request::default();
+        for ts in 0..10 {
+            let point = PointBuilder::new("t")
+                .timestamp(ts)
+                .tag("host", Value::String("a".to_string()))
+                .field("value", Value::Int64(ts))
+                .build()
+                .unwrap();
+            req.add_point(point);
+        }
+        let point_bytes = super::estimate_point_size(&req.point_groups["t"][0]);
+
+        let requests = req.clone().split_by_size(point_bytes * 3);
+        let sizes: Vec<_> = requests
+            .iter()
+            .map(|req| req.point_groups["t"].len())
+            .collect();
+        assert_eq!(sizes, vec![3, 3, 3, 1]);
+        let mut timestamps: Vec<_> = requests
+            .iter()
+            .flat_map(|req| req.point_groups["t"].iter().map(|point| point.timestamp))
+            .collect();
+        timestamps.sort();
+        assert_eq!(timestamps, (0..10).collect::<Vec<_>>());
+
+        // Every point is too large.
+        assert_eq!(req.clone().split_by_size(1).len(), 10);
+        assert_eq!(req.split_by_size(usize::MAX).len(), 1);
+        assert!(Request::default().split_by_size(100).is_empty());
+    }
+
+    #[test]
+    fn test_null_field_and_absent_field() {
+        let make_fields = |point_builder: PointBuilder| {
+            let mut req = Request::default();
+            req.add_point(point_builder.timestamp(1).build().unwrap());
+            let table_request = WriteTableRequestPbsBuilder(req).build().pop().unwrap();
+            let field_group = &table_request.entries[0].field_groups[0];
+            field_group
+                .fields
+                .iter()
+                .map(|field| {
+                    let name = table_request.field_names[field.name_index as usize].clone();
+                    (name, field.value.clone())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let with_null = make_fields(
+            PointBuilder::new("t")
+                .field("a", Value::Int64(1))
+                .field_null("b"),
+        );
+        assert_eq!(with_null.len(), 2);
+        assert_eq!(with_null[1].0, "b");
+        // The null value is sent as a value without any content.
+        let null_pb = with_null[1].1.as_ref().unwrap();
+        assert!(null_pb.value.is_none());
+        assert_eq!(Value::from(null_pb.clone()), Value::Null);
+
+        let absent = make_fields(PointBuilder::new("t").field("a", Value::Int64(1)));
+        assert_eq!(absent.len(), 1);
+        assert_eq!(absent[0].0, "a");
+    }
+
     #[test]
     fn test_build_write_table() {
         let ts1 = Local::now().timestamp_millis();
@@ -397,6 +650,170 @@ mod test {
         assert_eq!(points, expected_points);
     }
 
+    #[test]
+    fn test_request_builder() {
+        let ts = Local::now().timestamp_millis();
+        let req = RequestBuilder::default()
+            .point(
+                PointBuilder::new("cpu")
+                    .timestamp(ts)
+                    .tag("host", Value::String("a".to_string()))
+                    .field("usage", Value::Double(0.5)),
+            )
+            .point(
+                PointBuilder::new("cpu")
+                    .timestamp(ts + 1)
+                    .tag("host", Value::String("b".to_string()))
+                    .field("usage", Value::Null),
+            )
+            .point(
+                PointBuilder::new("mem")
+                    .timestamp(ts)
+                    .tag("host", Value::String("a".to_string()))
+                    .field("usage", Value::Int64(1024)),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(req.point_groups["cpu"].len(), 2);
+        assert_eq!(req.point_groups["mem"].len(), 1);
+
+        // Inconsistent field types in the same table.
+        let res = RequestBuilder::default()
+            .point(
+                PointBuilder::new("cpu")
+                    .timestamp(ts)
+                    .field("usage", Value::Double(0.5)),
+            )
+            .point(
+                PointBuilder::new("cpu")
+                    .timestamp(ts + 1)
+                    .field("usage", Value::Int64(1)),
+            )
+            .build();
+        assert!(res.is_err());
+
+        // Missing timestamp.
+        let res = RequestBuilder::default()
+            .point(PointBuilder::new("cpu").field("usage", Value::Double(0.5)))
+            .build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_request_builder_dedup() {
+        let make_point = |ts: i64, host: &str, usage: f64| {
+            PointBuilder::new("cpu")
+                .timestamp(ts)
+                .tag("host", Value::String(host.to_string()))
+                .field("usage", Value::Double(usage))
+        };
+        let make_builder = || {
+            RequestBuilder::default()
+                .point(make_point(1, "a", 0.1))
+                .point(make_point(1, "b", 0.2))
+                .point(make_point(2, "a", 0.3))
+                .point(make_point(1, "a", 0.4))
+                .point(make_point(1, "b", 0.5))
+                .point(
+                    PointBuilder::new("mem")
+                        .timestamp(1)
+                        .tag("host", Value::String("a".to_string()))
+                        .field("usage", Value::Int64(1024)),
+                )
+        };
+        let values = |req: &Request, table: &str| -> Vec<(i64, Value, Value)> {
+            req.point_groups[table]
+                .iter()
+                .map(|point| {
+                    (
+                        point.timestamp,
+                        point.tags["host"].clone(),
+                        point.fields["usage"].clone(),
+                    )
+                })
+                .collect()
+        };
+
+        // The duplicates are kept by default.
+        let req = make_builder().build().unwrap();
+        assert_eq!(req.point_groups["cpu"].len(), 5);
+
+        let req = make_builder().dedup(true).build().unwrap();
+        assert_eq!(
+            values(&req, "cpu"),
+            vec![
+                (1, Value::String("a".to_string()), Value::Double(0.4)),
+                (1, Value::String("b".to_string()), Value::Double(0.5)),
+                (2, Value::String("a".to_string()), Value::Double(0.3)),
+            ]
+        );
+        // The points of different tables are not collapsed.
+        assert_eq!(req.point_groups["mem"].len(), 1);
+
+        // The null tag differs from the empty string.
+        let req = RequestBuilder::default()
+            .point(
+                PointBuilder::new("cpu")
+                    .timestamp(1)
+                    .tag("host", Value::Null)
+                    .field("usage", Value::Double(0.1)),
+            )
+            .point(make_point(1, "", 0.2))
+            .dedup(true)
+            .build()
+            .unwrap();
+        assert_eq!(req.point_groups["cpu"].len(), 2);
+    }
+
+    #[test]
+    fn test_request_builder_backfill_timestamp() {
+        let make_builder = || {
+            RequestBuilder::default()
+                .point(
+                    PointBuilder::new("cpu")
+                        .timestamp(1)
+                        .tag("host", Value::String("a".to_string()))
+                        .field("usage", Value::Double(0.1)),
+                )
+                .point(
+                    PointBuilder::new("cpu")
+                        .tag("host", Value::String("b".to_string()))
+                        .field("usage", Value::Double(0.2)),
+                )
+                .point(PointBuilder::new("mem").field("usage", Value::Int64(1024)))
+        };
+        let timestamps = |req: &Request| -> Vec<(Value, i64)> {
+            ["cpu", "mem"]
+                .iter()
+                .flat_map(|table| &req.point_groups[*table])
+                .map(|point| (point.fields["usage"].clone(), point.timestamp))
+                .collect()
+        };
+
+        // The points without a timestamp fail the build by default.
+        assert!(make_builder().build().is_err());
+        assert!(make_builder().backfill_timestamp(false).build().is_err());
+
+        let req = make_builder().backfill_timestamp_with(100).build().unwrap();
+        assert_eq!(
+            timestamps(&req),
+            vec![
+                (Value::Double(0.1), 1),
+                (Value::Double(0.2), 100),
+                (Value::Int64(1024), 100),
+            ]
+        );
+
+        let begin = Local::now().timestamp_millis();
+        let req = make_builder().backfill_timestamp(true).build().unwrap();
+        let end = Local::now().timestamp_millis();
+        let timestamps = timestamps(&req);
+        assert_eq!(timestamps[0], (Value::Double(0.1), 1));
+        // All the backfilled points share one timestamp.
+        assert_eq!(timestamps[1].1, timestamps[2].1);
+        assert!((begin..=end).contains(&timestamps[1].1));
+    }
+
     fn make_cmp_key(point: &Point) -> (Vec<u8>, i64) {
         let mut series_key = point.table.as_bytes().to_vec();
         let tagks_key = make_tags_key(&point.tags);
@@ -419,4 +836,33 @@ mod test {
             cmp_key1.cmp(&cmp_key2)
         });
     }
+
+    #[test]
+    fn test_merge() {
+        let make_point = |table: &str, ts| {
+            PointBuilder::new(table.to_string())
+                .timestamp(ts)
+                .tag("host".to_string(), Value::String("a".to_string()))
+                .field("value".to_string(), Value::Int64(ts))
+                .build()
+                .unwrap()
+        };
+
+        let mut req = Request::default();
+        req.add_points(vec![make_point("t1", 1), make_point("t2", 2)]);
+        let mut other = Request::default();
+        other.add_points(vec![make_point("t1", 3), make_point("t3", 4)]);
+        req.merge(other);
+
+        let mut tables: Vec<_> = req.point_groups.keys().cloned().collect();
+        tables.sort();
+        assert_eq!(tables, vec!["t1", "t2", "t3"]);
+        let timestamps: Vec<_> = req.point_groups["t1"]
+            .iter()
+            .map(|point| point.timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![1, 3]);
+        assert_eq!(req.point_groups["t2"].len(), 1);
+        assert_eq!(req.point_groups["t3"].len(), 1);
+    }
 }
@@ -30,6 +30,25 @@ impl Response {
     pub fn new(success: u32, failed: u32) -> Self {
         Self { success, failed }
     }
+
+    /// Whether all the rows are written successfully.
+    pub fn is_fully_successful(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Whether some rows are written successfully but the others fail.
+    pub fn partial_failure(&self) -> bool {
+        self.success > 0 && self.failed > 0
+    }
+
+    /// Merge the counts of `other` into the response, used to combine the
+    /// responses of the sub-requests split from one write.
+    pub fn merge(&mut self, other: Response) -> &mut Self {
+        self.success += other.success;
+        self.failed += other.failed;
+
+        self
+    }
 }
 
 impl From<WriteResponsePb> for Response {
@@ -40,3 +59,39 @@ impl From<WriteResponsePb> for Response {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use horaedbproto::storage::WriteResponse as WriteResponsePb;
+
+    use super::Response;
+
+    #[test]
+    fn test_partial_failure() {
+        let resp: Response = WriteResponsePb {
+            header: None,
+            success: 8,
+            failed: 2,
+        }
+        .into();
+        assert_eq!(resp.success, 8);
+        assert_eq!(resp.failed, 2);
+        assert!(!resp.is_fully_successful());
+
+        assert!(resp.partial_failure());
+
+        assert!(Response::new(10, 0).is_fully_successful());
+        assert!(Response::new(0, 0).is_fully_successful());
+        assert!(!Response::new(10, 0).partial_failure());
+        assert!(!Response::new(0, 10).partial_failure());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut resp = Response::new(0, 0);
+        resp.merge(Response::new(8, 0)).merge(Response::new(3, 2));
+        assert_eq!(resp.success, 11);
+        assert_eq!(resp.failed, 2);
+        assert!(!resp.is_fully_successful());
+    }
+}
@@ -15,11 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use dashmap::DashMap;
 use horaedbproto::storage::{self, RouteRequest};
+use tokio::time::Instant;
 
 use crate::{
     errors::Result,
@@ -41,23 +42,43 @@ pub trait Router: Send + Sync {
 /// There is cache in [`RouterImpl`], it will return endpoints in cache first.
 /// If returned endpoints is outdated, you should call [`evict`] to remove them.
 /// And [`RouterImpl`] will fetch new endpoints when you call ['route'] again.
+/// The cached endpoints will also be refetched once they are older than
+/// `cache_ttl` if it is set.
 ///
 /// [`route`]: RouterImpl::route
 /// [`evict`]: RouterImpl::evict
 pub struct RouterImpl {
     default_endpoint: Endpoint,
-    cache: DashMap<String, Endpoint>,
+    cache: DashMap<String, CachedEndpoint>,
+    cache_ttl: Option<Duration>,
     rpc_client: Arc<dyn RpcClient>,
 }
 
+struct CachedEndpoint {
+    endpoint: Endpoint,
+    cached_at: Instant,
+}
+
 impl RouterImpl {
-    pub fn new(default_endpoint: Endpoint, rpc_client: Arc<dyn RpcClient>) -> Self {
+    pub fn new(
+        default_endpoint: Endpoint,
+        rpc_client: Arc<dyn RpcClient>,
+        cache_ttl: Option<Duration>,
+    ) -> Self {
         Self {
             default_endpoint,
             cache: DashMap::new(),
+            cache_ttl,
             rpc_client,
         }
     }
+
+    #[inline]
+    fn is_expired(&self, cached: &CachedEndpoint) -> bool {
+        self.cache_ttl
+            .map(|ttl| cached.cached_at.elapsed() >= ttl)
+            .unwrap_or(false)
+    }
 }
 
 #[async_trait]
@@ -72,11 +93,11 @@ impl Router for RouterImpl {
             let mut misses = HashMap::new();
             for (idx, table) in tables.iter().enumerate() {
                 match self.cache.get(table) {
-                    Some(pair) => {
-                        target_endpoints[idx] = Some(pair.value().clone());
+                    Some(pair) if !self.is_expired(pair.value()) => {
+                        target_endpoints[idx] = Some(pair.value().endpoint.clone());
                     }
 
-                    None => {
+                    _ => {
                         misses.insert(table.clone(), idx);
                     }
                 }
@@ -107,7 +128,11 @@ impl Router for RouterImpl {
                 Error::Unknown(format!("Unknown table:{} in response", route.table))
             })?;
             let endpoint: Endpoint = route.endpoint.unwrap().into();
-            self.cache.insert(route.table, endpoint.clone());
+            let cached = CachedEndpoint {
+                endpoint: endpoint.clone(),
+                cached_at: Instant::now(),
+            };
+            self.cache.insert(route.table, cached);
             target_endpoints[*idx] = Some(endpoint);
         }
 
@@ -123,7 +148,7 @@ impl Router for RouterImpl {
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::{sync::Arc, time::Duration};
 
     use dashmap::DashMap;
 
@@ -148,9 +173,7 @@ mod test {
 
         // Init mock client with route1 and route2
         let route_table = Arc::new(DashMap::default());
-        let mock_rpc_client = MockRpcClient {
-            route_table: route_table.clone(),
-        };
+        let mock_rpc_client = MockRpcClient::default().with_route_table(route_table.clone());
         mock_rpc_client
             .route_table
             .insert(table1.clone(), endpoint1.clone());
@@ -162,10 +185,11 @@ mod test {
         // route --> change route_table --> route again.
         let ctx = RpcContext {
             database: Some("db".to_string()),
-            timeout: None,
+            ..Default::default()
         };
         let tables = vec![table1.clone(), table2.clone()];
-        let route_client = RouterImpl::new(default_endpoint.clone(), Arc::new(mock_rpc_client));
+        let route_client =
+            RouterImpl::new(default_endpoint.clone(), Arc::new(mock_rpc_client), None);
         let route_res1 = route_client.route(&tables, &ctx).await.unwrap();
         assert_eq!(&endpoint1, route_res1.first().unwrap().as_ref().unwrap());
         assert_eq!(&endpoint2, route_res1.get(1).unwrap().as_ref().unwrap());
@@ -193,4 +217,37 @@ mod test {
             route_res4.get(1).unwrap().as_ref().unwrap()
         );
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_ttl() {
+        let table = "table".to_string();
+        let endpoint1 = Endpoint::new("192.168.0.1".to_string(), 11);
+        let endpoint2 = Endpoint::new("192.168.0.2".to_string(), 12);
+        let default_endpoint = Endpoint::new("192.168.0.5".to_string(), 15);
+
+        let route_table = Arc::new(DashMap::default());
+        route_table.insert(table.clone(), endpoint1.clone());
+        let mock_rpc_client = MockRpcClient::default().with_route_table(route_table.clone());
+
+        let ctx = RpcContext {
+            database: Some("db".to_string()),
+            ..Default::default()
+        };
+        let tables = vec![table.clone()];
+        let ttl = Duration::from_millis(50);
+        let route_client = RouterImpl::new(default_endpoint, Arc::new(mock_rpc_client), Some(ttl));
+        let route_res1 = route_client.route(&tables, &ctx).await.unwrap();
+        assert_eq!(&endpoint1, route_res1[0].as_ref().unwrap());
+
+        // Cached before expired.
+        route_table.insert(table.clone(), endpoint2.clone());
+        tokio::time::advance(ttl - Duration::from_millis(1)).await;
+        let route_res2 = route_client.route(&tables, &ctx).await.unwrap();
+        assert_eq!(&endpoint1, route_res2[0].as_ref().unwrap());
+
+        // Refetched after expired.
+        tokio::time::advance(Duration::from_millis(1)).await;
+        let route_res3 = route_client.route(&tables, &ctx).await.unwrap();
+        assert_eq!(&endpoint2, route_res3[0].as_ref().unwrap());
+    }
 }
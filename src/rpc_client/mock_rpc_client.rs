@@ -15,36 +15,145 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use dashmap::DashMap;
+use futures::stream::{self, BoxStream, StreamExt};
 use horaedbproto::storage::{
     Endpoint as EndpointPb, Route as RoutePb, RouteRequest as RouteRequestPb,
     RouteResponse as RouteResponsePb, SqlQueryRequest as QueryRequestPb,
     SqlQueryResponse as QueryResponsePb, WriteRequest as WriteRequestPb,
     WriteResponse as WriteResponsePb,
 };
+use tokio::sync::Semaphore;
 
 use crate::{
     model::route::Endpoint,
-    rpc_client::{RpcClient, RpcContext},
+    rpc_client::{RpcClient, RpcClientFactory, RpcContext},
     Result,
 };
 
+/// The script answering the queries sent to the [`MockRpcClient`].
+type QueryAnswer =
+    Box<dyn Fn(&RpcContext, &QueryRequestPb) -> Result<QueryResponsePb> + Send + Sync>;
+/// The script answering the streaming queries with the streamed responses.
+type StreamAnswer =
+    Box<dyn Fn(&RpcContext, &QueryRequestPb) -> Vec<Result<QueryResponsePb>> + Send + Sync>;
+
 /// Rpc client used for testing.
+///
+/// The tables are routed by the `route_table`, and the other calls are
+/// answered by the scripts after the delay, which fail them e.g. by an error
+/// flag captured. The writes sent are recorded.
 pub struct MockRpcClient {
     pub route_table: Arc<DashMap<String, Endpoint>>,
+    query: QueryAnswer,
+    stream_query: StreamAnswer,
+    /// The delay of every query and write.
+    delay: Duration,
+    /// Limit the number of the queries and writes served at a time, like a
+    /// saturated connection.
+    permits: Option<Semaphore>,
+    writes: Mutex<Vec<(RpcContext, WriteRequestPb)>>,
+}
+
+#[allow(clippy::result_large_err)]
+impl Default for MockRpcClient {
+    /// Route no table, and answer every call with the empty response.
+    fn default() -> Self {
+        Self {
+            route_table: Arc::default(),
+            query: Box::new(|_, _| Ok(QueryResponsePb::default())),
+            stream_query: Box::new(|_, _| Vec::new()),
+            delay: Duration::ZERO,
+            permits: None,
+            writes: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl MockRpcClient {
+    pub fn with_route_table(mut self, route_table: Arc<DashMap<String, Endpoint>>) -> Self {
+        self.route_table = route_table;
+        self
+    }
+
+    pub fn with_query(
+        mut self,
+        answer: impl Fn(&RpcContext, &QueryRequestPb) -> Result<QueryResponsePb> + Send + Sync + 'static,
+    ) -> Self {
+        self.query = Box::new(answer);
+        self
+    }
+
+    pub fn with_stream_query(
+        mut self,
+        answer: impl Fn(&RpcContext, &QueryRequestPb) -> Vec<Result<QueryResponsePb>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.stream_query = Box::new(answer);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.permits = Some(Semaphore::new(max_concurrency));
+        self
+    }
+
+    /// The writes sent so far.
+    pub fn writes(&self) -> Vec<WriteRequestPb> {
+        let writes = self.writes.lock().unwrap();
+        writes.iter().map(|(_, req)| req.clone()).collect()
+    }
+
+    /// The contexts of the writes sent so far.
+    pub fn write_contexts(&self) -> Vec<RpcContext> {
+        let writes = self.writes.lock().unwrap();
+        writes.iter().map(|(ctx, _)| ctx.clone()).collect()
+    }
+
+    async fn wait(&self) {
+        let _permit = match &self.permits {
+            Some(permits) => Some(permits.acquire().await.unwrap()),
+            None => None,
+        };
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+    }
 }
 
 #[async_trait]
 impl RpcClient for MockRpcClient {
-    async fn sql_query(&self, _ctx: &RpcContext, _req: QueryRequestPb) -> Result<QueryResponsePb> {
-        todo!()
+    async fn sql_query(&self, ctx: &RpcContext, req: QueryRequestPb) -> Result<QueryResponsePb> {
+        self.wait().await;
+        (self.query)(ctx, &req)
     }
 
-    async fn write(&self, _ctx: &RpcContext, _req: WriteRequestPb) -> Result<WriteResponsePb> {
-        todo!()
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: QueryRequestPb,
+    ) -> Result<BoxStream<'static, Result<QueryResponsePb>>> {
+        let resps = (self.stream_query)(ctx, &req);
+        Ok(stream::iter(resps).boxed())
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: WriteRequestPb) -> Result<WriteResponsePb> {
+        self.wait().await;
+        self.writes.lock().unwrap().push((ctx.clone(), req));
+        Ok(WriteResponsePb::default())
     }
 
     async fn route(&self, _ctx: &RpcContext, req: RouteRequestPb) -> Result<RouteResponsePb> {
@@ -74,3 +183,15 @@ impl RpcClient for MockRpcClient {
         Ok(route_resp)
     }
 }
+
+/// Factory building the same [`MockRpcClient`] for all the endpoints.
+pub struct MockRpcClientFactory {
+    pub client: Arc<MockRpcClient>,
+}
+
+#[async_trait]
+impl RpcClientFactory for MockRpcClientFactory {
+    async fn build(&self, _endpoint: String) -> Result<Arc<dyn RpcClient>> {
+        Ok(self.client.clone())
+    }
+}
@@ -15,28 +15,65 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#[cfg(test)]
 mod mock_rpc_client;
+mod pooled_rpc_client;
 mod rpc_client_impl;
+pub(crate) mod token_provider;
 
-use std::{sync::Arc, time::Duration};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 use horaedbproto::storage::{
-    RouteRequest as RouteRequestPb, RouteResponse as RouteResponsePb,
+    RequestContext, RouteRequest as RouteRequestPb, RouteResponse as RouteResponsePb,
     SqlQueryRequest as QueryRequestPb, SqlQueryResponse as QueryResponsePb,
     WriteRequest as WriteRequestPb, WriteResponse as WriteResponsePb,
 };
 #[cfg(test)]
-pub use mock_rpc_client::MockRpcClient;
+pub use mock_rpc_client::{MockRpcClient, MockRpcClientFactory};
+pub use pooled_rpc_client::PooledRpcClient;
 pub use rpc_client_impl::RpcClientImplFactory;
+pub(crate) use token_provider::TokenCache;
+pub use token_provider::TokenProvider;
 
 use crate::errors::Result;
 
+/// The grpc metadata key of the request id, see [`RpcContext::request_id`].
+pub(crate) const REQUEST_ID_KEY: &str = "x-horaedb-request-id";
+/// The grpc metadata key of the [`RpcContext::idempotency_key`].
+pub(crate) const IDEMPOTENCY_KEY: &str = "x-horaedb-idempotency-key";
+
 /// Context for rpc request.
+///
+/// More fields may be added, so it is made from the default one by the setters
+/// below outside of the crate.
 #[derive(Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct RpcContext {
     pub database: Option<String>,
+    /// The deadline of the request, which overrides the default timeouts in
+    /// the [`RpcConfig`](crate::RpcConfig), e.g. for the heavy aggregations.
     pub timeout: Option<Duration>,
+    /// The custom headers sent as the grpc metadata, e.g. the priority.
+    pub headers: Vec<(String, String)>,
+    /// The id sent in the `x-horaedb-request-id` metadata to correlate the
+    /// request with the server logs, and a random one is generated for every
+    /// rpc if not set.
+    pub request_id: Option<String>,
+    /// The key sent in the `x-horaedb-idempotency-key` metadata of the writes,
+    /// by which the server can dedupe the retries of the same write.
+    ///
+    /// It should identify one write request, and a random one is generated for
+    /// every write if not set, which is kept across the retries by the
+    /// [`RetryableClient`](crate::RetryableClient).
+    ///
+    /// The writes split from one write by the client, e.g. the chunks of
+    /// [`DbClient::write_chunked`](crate::DbClient::write_chunked) and the
+    /// flushes of the [`WriteBuffer`](crate::WriteBuffer), carry the key
+    /// suffixed by their indexes, e.g. `key-0` and `key-1`, so that they are
+    /// not deduped as the same write.
+    pub idempotency_key: Option<String>,
 }
 
 impl RpcContext {
@@ -49,12 +86,56 @@ impl RpcContext {
         self.timeout = Some(timeout);
         self
     }
+
+    pub fn request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn idempotency_key(mut self, idempotency_key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(idempotency_key.into());
+        self
+    }
+
+    /// The context of the `idx`-th write split from the write of this context,
+    /// see [`RpcContext::idempotency_key`].
+    pub(crate) fn for_sub_write(&self, idx: usize) -> Cow<'_, Self> {
+        match &self.idempotency_key {
+            Some(key) => Cow::Owned(self.clone().idempotency_key(format!("{key}-{idx}"))),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// Add a custom header, and [`Error::Client`](crate::Error::Client) will be
+    /// returned by the request if it is not a valid grpc metadata.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
 }
 #[async_trait]
 pub trait RpcClient: Send + Sync {
     async fn sql_query(&self, ctx: &RpcContext, req: QueryRequestPb) -> Result<QueryResponsePb>;
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: QueryRequestPb,
+    ) -> Result<BoxStream<'static, Result<QueryResponsePb>>>;
     async fn write(&self, ctx: &RpcContext, req: WriteRequestPb) -> Result<WriteResponsePb>;
     async fn route(&self, ctx: &RpcContext, req: RouteRequestPb) -> Result<RouteResponsePb>;
+    /// Check whether the server is reachable and serving.
+    ///
+    /// By default no table is routed, which is cheap and goes through the whole
+    /// rpc path.
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        let req = RouteRequestPb {
+            context: Some(RequestContext {
+                database: ctx.database.clone().unwrap_or_default(),
+            }),
+            tables: vec![],
+        };
+        self.route(ctx, req).await.map(|_| ())
+    }
 }
 
 #[async_trait]
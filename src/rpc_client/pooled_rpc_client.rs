@@ -0,0 +1,141 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use horaedbproto::storage::{
+    RouteRequest as RouteRequestPb, RouteResponse as RouteResponsePb,
+    SqlQueryRequest as QueryRequestPb, SqlQueryResponse as QueryResponsePb,
+    WriteRequest as WriteRequestPb, WriteResponse as WriteResponsePb,
+};
+
+use crate::{
+    rpc_client::{RpcClient, RpcContext},
+    Result,
+};
+
+/// [`RpcClient`] sending the requests to the same endpoint through a pool of
+/// clients in turn.
+///
+/// Every client in the pool holds its own connection, so that the requests
+/// are not limited by the concurrency of a single http2 connection.
+#[derive(Clone)]
+pub struct PooledRpcClient {
+    clients: Arc<[Arc<dyn RpcClient>]>,
+    next: Arc<AtomicUsize>,
+}
+
+impl PooledRpcClient {
+    /// The `clients` should not be empty.
+    pub fn new(clients: Vec<Arc<dyn RpcClient>>) -> Self {
+        assert!(!clients.is_empty());
+
+        Self {
+            clients: clients.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    #[inline]
+    fn pick(&self) -> &dyn RpcClient {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        self.clients[idx].as_ref()
+    }
+}
+
+#[async_trait]
+impl RpcClient for PooledRpcClient {
+    async fn sql_query(&self, ctx: &RpcContext, req: QueryRequestPb) -> Result<QueryResponsePb> {
+        self.pick().sql_query(ctx, req).await
+    }
+
+    async fn stream_sql_query(
+        &self,
+        ctx: &RpcContext,
+        req: QueryRequestPb,
+    ) -> Result<BoxStream<'static, Result<QueryResponsePb>>> {
+        self.pick().stream_sql_query(ctx, req).await
+    }
+
+    async fn write(&self, ctx: &RpcContext, req: WriteRequestPb) -> Result<WriteResponsePb> {
+        self.pick().write(ctx, req).await
+    }
+
+    async fn route(&self, ctx: &RpcContext, req: RouteRequestPb) -> Result<RouteResponsePb> {
+        self.pick().route(ctx, req).await
+    }
+
+    async fn health_check(&self, ctx: &RpcContext) -> Result<()> {
+        self.pick().health_check(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use futures::future::join_all;
+    use horaedbproto::storage::WriteRequest as WriteRequestPb;
+
+    use super::PooledRpcClient;
+    use crate::rpc_client::{MockRpcClient, RpcClient, RpcContext};
+
+    /// Client serving one write at a time like a saturated connection.
+    fn make_serial_client() -> MockRpcClient {
+        MockRpcClient::default()
+            .with_delay(Duration::from_millis(20))
+            .with_max_concurrency(1)
+    }
+
+    async fn write_concurrently(client: &dyn RpcClient, num: usize) -> Duration {
+        let ctx = RpcContext::default();
+        let begin = Instant::now();
+        let writes = (0..num).map(|_| client.write(&ctx, WriteRequestPb::default()));
+        for resp in join_all(writes).await {
+            resp.unwrap();
+        }
+        begin.elapsed()
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let clients: Vec<_> = (0..4).map(|_| Arc::new(make_serial_client())).collect();
+        let pooled = PooledRpcClient::new(
+            clients
+                .iter()
+                .map(|c| c.clone() as Arc<dyn RpcClient>)
+                .collect(),
+        );
+
+        let pooled_elapsed = write_concurrently(&pooled, 8).await;
+        for client in &clients {
+            assert_eq!(client.writes().len(), 2);
+        }
+
+        // The same number of writes takes much longer through a single client.
+        let single_elapsed = write_concurrently(&make_serial_client(), 8).await;
+        assert!(pooled_elapsed * 2 < single_elapsed);
+    }
+}
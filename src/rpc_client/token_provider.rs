@@ -0,0 +1,137 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::{sync::Mutex, time::Instant};
+use tonic::metadata::{Ascii, MetadataValue};
+
+use crate::{Error, Result};
+
+/// Provider of the tokens sent in the `authorization: Bearer <token>` metadata,
+/// e.g. the OAuth access tokens which expire and have to be refreshed.
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// Fetch a fresh token, and its error fails the request.
+    async fn token(&self) -> Result<String>;
+}
+
+/// Cache of the token of the [`TokenProvider`], which is fetched again after
+/// the `ttl` instead of for every request.
+pub(crate) struct TokenCache {
+    provider: Arc<dyn TokenProvider>,
+    ttl: Duration,
+    cached: Mutex<Option<(MetadataValue<Ascii>, Instant)>>,
+}
+
+impl fmt::Debug for TokenCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenCache")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl TokenCache {
+    pub fn new(provider: Arc<dyn TokenProvider>, ttl: Duration) -> Self {
+        Self {
+            provider,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Get the cached authorization metadata, or make it from a new token if
+    /// the cached one expires.
+    ///
+    /// The concurrent requests wait for the same fetch.
+    pub async fn authorization(&self) -> Result<MetadataValue<Ascii>> {
+        let mut cached = self.cached.lock().await;
+        if let Some((metadata, fetched_at)) = &*cached {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(metadata.clone());
+            }
+        }
+
+        let token = self.provider.token().await?;
+        let metadata = MetadataValue::try_from(format!("Bearer {token}"))
+            .map_err(|e| Error::Client(format!("Invalid token, err:{e}")))?;
+        *cached = Some((metadata.clone(), Instant::now()));
+
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{TokenCache, TokenProvider};
+    use crate::{Error, Result};
+
+    /// Provider returning a new token for every fetch.
+    #[derive(Default)]
+    pub(crate) struct RotatingTokenProvider {
+        pub fetches: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TokenProvider for RotatingTokenProvider {
+        async fn token(&self) -> Result<String> {
+            let n = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(format!("token-{n}"))
+        }
+    }
+
+    struct InvalidTokenProvider;
+
+    #[async_trait]
+    impl TokenProvider for InvalidTokenProvider {
+        async fn token(&self) -> Result<String> {
+            Ok("invalid\ntoken".to_string())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_cache() {
+        let provider = Arc::new(RotatingTokenProvider::default());
+        let cache = TokenCache::new(provider.clone(), Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert_eq!(cache.authorization().await.unwrap(), "Bearer token-1");
+        }
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(cache.authorization().await.unwrap(), "Bearer token-2");
+        assert_eq!(cache.authorization().await.unwrap(), "Bearer token-2");
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+
+        let cache = TokenCache::new(Arc::new(InvalidTokenProvider), Duration::from_secs(60));
+        assert!(matches!(cache.authorization().await, Err(Error::Client(_))));
+    }
+}
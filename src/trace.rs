@@ -0,0 +1,168 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tracing of the client operations, enabled by the `tracing` feature.
+
+use std::future::Future;
+
+use opentelemetry::{trace::TraceContextExt, Context};
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tracing::{field::Empty, info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    model::{
+        sql_query::{Request as SqlQueryRequest, Response as SqlQueryResponse},
+        write::{Request as WriteRequest, Response as WriteResponse},
+    },
+    rpc_client::RpcContext,
+    Error, Result,
+};
+
+/// The header defined by [W3C Trace Context](https://www.w3.org/TR/trace-context/).
+const TRACEPARENT_HEADER: &str = "traceparent";
+const TRACEPARENT_VERSION: u8 = 0;
+
+#[allow(clippy::result_large_err)]
+pub(crate) async fn trace_sql_query<F>(
+    ctx: &RpcContext,
+    req: &SqlQueryRequest,
+    query: F,
+) -> Result<SqlQueryResponse>
+where
+    F: Future<Output = Result<SqlQueryResponse>>,
+{
+    let span = info_span!(
+        "sql_query",
+        database = ctx.database.as_deref(),
+        tables = req.tables.len(),
+        sql_len = req.sql.len(),
+        rows = Empty,
+        affected_rows = Empty,
+        error = Empty,
+        error_code = Empty,
+    );
+    let resp = query.instrument(span.clone()).await;
+
+    match &resp {
+        Ok(resp) => {
+            span.record("rows", resp.rows.len());
+            span.record("affected_rows", resp.affected_rows);
+        }
+        Err(e) => record_error(&span, e),
+    }
+
+    resp
+}
+
+#[allow(clippy::result_large_err)]
+pub(crate) async fn trace_write<F>(
+    ctx: &RpcContext,
+    req: &WriteRequest,
+    write: F,
+) -> Result<WriteResponse>
+where
+    F: Future<Output = Result<WriteResponse>>,
+{
+    let points: usize = req.point_groups.values().map(|points| points.len()).sum();
+    let span = info_span!(
+        "write",
+        database = ctx.database.as_deref(),
+        tables = req.point_groups.len(),
+        points,
+        success = Empty,
+        failed = Empty,
+        error = Empty,
+        error_code = Empty,
+    );
+    let resp = write.instrument(span.clone()).await;
+
+    match &resp {
+        Ok(resp) => {
+            span.record("success", resp.success);
+            span.record("failed", resp.failed);
+        }
+        Err(e) => record_error(&span, e),
+    }
+
+    resp
+}
+
+fn record_error(span: &Span, e: &Error) {
+    span.record("error", tracing::field::display(e));
+    if let Error::Server(e) = e {
+        span.record("error_code", e.code);
+    }
+}
+
+/// Inject the trace context of the current span into the `metadata`.
+pub(crate) fn inject_current_context(metadata: &mut MetadataMap) {
+    inject_context(&Span::current().context(), metadata);
+}
+
+fn inject_context(cx: &Context, metadata: &mut MetadataMap) {
+    let span = cx.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "{:02x}-{}-{}-{:02x}",
+        TRACEPARENT_VERSION,
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    // The traceparent only consists of hex digits and dashes, so it is always a
+    // valid metadata value.
+    if let Ok(value) = MetadataValue::try_from(traceparent) {
+        metadata.insert(TRACEPARENT_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opentelemetry::{
+        trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+        Context,
+    };
+    use tonic::metadata::MetadataMap;
+
+    use super::{inject_context, TRACEPARENT_HEADER};
+
+    #[test]
+    fn test_inject_context() {
+        let mut metadata = MetadataMap::new();
+        inject_context(&Context::new(), &mut metadata);
+        assert!(metadata.get(TRACEPARENT_HEADER).is_none());
+
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let cx = Context::new().with_remote_span_context(span_context);
+        inject_context(&cx, &mut metadata);
+        assert_eq!(
+            metadata.get(TRACEPARENT_HEADER).unwrap(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+    }
+}